@@ -1,7 +1,11 @@
+use crate::{
+    journal::Date,
+    render::{index::IndexSort, links::LinkStyle, mathjax::MathjaxPolicy, sanitize::HtmlPolicy},
+};
 use clap::{crate_authors, crate_version, App, Arg, ArgMatches, SubCommand};
 use std::{
     convert::TryFrom,
-    io,
+    env, io,
     path::{Path, PathBuf},
 };
 
@@ -20,25 +24,65 @@ pub fn create() -> App<'static, 'static> {
         )
         .subcommand(
             SubCommand::with_name("render")
-                .about("Render a Markdown document")
+                .about("Render a Markdown, Org or reStructuredText document")
                 .arg(arg_config())
-                // TODO app::create render `file` arg should be optional. Then we can read from
-                // stdin if a file path is not provided.
                 .arg(
                     Arg::with_name("FILE")
-                        .required(true)
+                        .required(false)
                         .index(1)
-                        .help("Markdown file to render"),
+                        .help("Markdown file to render, or `-` to read from stdin (the default)"),
                 )
-                // TODO app::create render `output` arg should be optional. Then we can write to
-                // stdout if a path is not provided.
                 .arg(
                     Arg::with_name("output")
                         .long("output")
                         .short("o")
                         .required(false)
                         .takes_value(true)
-                        .help("Path to output rendered HTML"),
+                        .help(
+                            "Path to output rendered HTML, or `-` for stdout. Defaults to the \
+                             input with an `html` extension, or stdout when reading from stdin",
+                        ),
+                )
+                .arg(
+                    Arg::with_name("base-dir")
+                        .long("base-dir")
+                        .required(false)
+                        .takes_value(true)
+                        .value_name("DIR")
+                        .help(
+                            "Directory relative links in the note are relative to. Defaults to \
+                             the directory of the input, or the current directory for stdin",
+                        ),
+                )
+                .arg(
+                    Arg::with_name("theme")
+                        .long("theme")
+                        .required(false)
+                        .takes_value(true)
+                        .help("Syntax highlighting theme for code blocks"),
+                )
+                .arg(
+                    Arg::with_name("stylesheet")
+                        .long("stylesheet")
+                        .required(false)
+                        .takes_value(true)
+                        .value_name("PATH")
+                        .help("Stylesheet to use instead of the configured one"),
+                )
+                .arg(
+                    Arg::with_name("inline-css")
+                        .long("inline-css")
+                        .required(false)
+                        .takes_value(false)
+                        .help("Inline the stylesheet in the page instead of linking to it"),
+                )
+                .arg(
+                    Arg::with_name("mathjax")
+                        .long("mathjax")
+                        .required(false)
+                        .takes_value(true)
+                        .possible_values(&["always", "never"])
+                        .help("Whether to include MathJax in the page"),
                 )
                 .arg(
                     Arg::with_name("force")
@@ -47,13 +91,157 @@ pub fn create() -> App<'static, 'static> {
                         .required(false)
                         .takes_value(false)
                         .help("Force output file to overwrite existing files"),
-                ),
+                )
+                .arg(arg_html())
+                .arg(arg_links())
+                .arg(arg_base_url()),
         )
         .subcommand(
             SubCommand::with_name("sync")
                 // TODO: app::sync better about message
                 .about("Sync a directory to it's rendered equivalent")
-                .arg(arg_config()),
+                .arg(arg_config())
+                .arg(arg_html())
+                .arg(arg_links())
+                .arg(arg_base_url())
+                .arg(
+                    Arg::with_name("index-sort")
+                        .long("index-sort")
+                        .required(false)
+                        .takes_value(true)
+                        .possible_values(&["name", "mtime", "title", "date"])
+                        .help("Order of the entries in index pages"),
+                )
+                .arg(
+                    Arg::with_name("no-search")
+                        .long("no-search")
+                        .required(false)
+                        .takes_value(false)
+                        .help("Don't generate the search page and index"),
+                ),
+        )
+        .subcommand(
+            SubCommand::with_name("search")
+                .about("Search the notes")
+                .arg(Arg::with_name("QUERY").required(true).index(1).help(
+                    "Words to search for, where `word*` matches a prefix, `\"a phrase\"` \
+                             matches consecutive words, and `title:`, `heading:`, `tag:`, `code:` \
+                             or `body:` limit a word or phrase to part of a note",
+                ))
+                .arg(
+                    Arg::with_name("tag")
+                        .long("tag")
+                        .required(false)
+                        .takes_value(true)
+                        .help("Only search notes with the tag, or a tag nested in it"),
+                )
+                .arg(
+                    Arg::with_name("in")
+                        .long("in")
+                        .required(false)
+                        .takes_value(true)
+                        .value_name("DIR")
+                        .help("Only search notes in the directory, relative to the notes"),
+                )
+                .arg(
+                    Arg::with_name("json")
+                        .long("json")
+                        .required(false)
+                        .takes_value(false)
+                        .help("Write the matches as JSON"),
+                ),
+        )
+        .subcommand(
+            SubCommand::with_name("journal")
+                .about("Create or open the daily note of a day in the journal")
+                .arg(Arg::with_name("DATE").required(false).index(1).help(
+                    "Day of the note as YYYY-MM-DD, or `today` (the default), `yesterday` or \
+                     `tomorrow`",
+                ))
+                .arg(
+                    Arg::with_name("print")
+                        .long("print")
+                        .short("p")
+                        .required(false)
+                        .takes_value(false)
+                        .help("Print the path of the note instead of opening it in $EDITOR"),
+                ),
+        )
+        .subcommand(
+            SubCommand::with_name("export")
+                .about("Export notes to share outside of the rendered notes")
+                .subcommand(
+                    SubCommand::with_name("html")
+                        .about("Export a note as a single self-contained HTML file")
+                        .arg(
+                            Arg::with_name("NOTE")
+                                .required(true)
+                                .index(1)
+                                .help("Markdown note to export"),
+                        )
+                        .arg(
+                            Arg::with_name("output")
+                                .long("output")
+                                .short("o")
+                                .required(false)
+                                .takes_value(true)
+                                .help(
+                                    "Path to output the HTML to, or `-` for stdout. Defaults to \
+                                     the note with an `html` extension",
+                                ),
+                        )
+                        .arg(
+                            Arg::with_name("prerender-math")
+                                .long("prerender-math")
+                                .required(false)
+                                .takes_value(false)
+                                .help("Render math to SVG instead of including MathJax"),
+                        )
+                        .arg(
+                            Arg::with_name("force")
+                                .long("force")
+                                .short("f")
+                                .required(false)
+                                .takes_value(false)
+                                .help("Force output file to overwrite existing files"),
+                        ),
+                )
+                .subcommand(
+                    SubCommand::with_name("epub")
+                        .about("Export a directory of notes as an EPUB book")
+                        .arg(
+                            Arg::with_name("DIR")
+                                .required(true)
+                                .index(1)
+                                .help("Directory of notes to export"),
+                        )
+                        .arg(
+                            Arg::with_name("output")
+                                .long("output")
+                                .short("o")
+                                .required(false)
+                                .takes_value(true)
+                                .help(
+                                    "Path to output the EPUB to, or `-` for stdout. Defaults to \
+                                     the directory with an `epub` extension",
+                                ),
+                        )
+                        .arg(
+                            Arg::with_name("prerender-math")
+                                .long("prerender-math")
+                                .required(false)
+                                .takes_value(false)
+                                .help("Render math to SVG, as e-readers don't run MathJax"),
+                        )
+                        .arg(
+                            Arg::with_name("force")
+                                .long("force")
+                                .short("f")
+                                .required(false)
+                                .takes_value(false)
+                                .help("Force output file to overwrite existing files"),
+                        ),
+                ),
         )
         .subcommand(
             SubCommand::with_name("info")
@@ -61,6 +249,27 @@ pub fn create() -> App<'static, 'static> {
                 .subcommand(
                     SubCommand::with_name("syntax-themes")
                         .about("Display the list of known syntax highlighting themes"),
+                )
+                .subcommand(
+                    SubCommand::with_name("syntaxes")
+                        .about("Display the list of known syntaxes and their file extensions"),
+                )
+                .subcommand(
+                    SubCommand::with_name("syntax-css")
+                        .about("Display the stylesheet for classed code blocks of a theme")
+                        .arg(
+                            Arg::with_name("THEME")
+                                .required(true)
+                                .index(1)
+                                .help("Syntax highlighting theme"),
+                        )
+                        .arg(
+                            Arg::with_name("dark")
+                                .long("dark")
+                                .required(false)
+                                .takes_value(true)
+                                .help("Theme to use when a dark color scheme is preferred"),
+                        ),
                 ),
         )
 }
@@ -75,12 +284,53 @@ fn arg_config() -> Arg<'static, 'static> {
         .help("Configuration file to use")
 }
 
+#[inline]
+fn arg_html() -> Arg<'static, 'static> {
+    Arg::with_name("html")
+        .long("html")
+        .required(false)
+        .takes_value(true)
+        .possible_values(&["trust", "sanitize", "escape"])
+        .help("How raw HTML in notes is rendered. Defaults to `trust`")
+}
+
+#[inline]
+fn arg_links() -> Arg<'static, 'static> {
+    Arg::with_name("links")
+        .long("links")
+        .required(false)
+        .takes_value(true)
+        .possible_values(&["absolute", "relative"])
+        .help("Whether links to other files are absolute paths or relative to the page")
+}
+
+#[inline]
+fn arg_base_url() -> Arg<'static, 'static> {
+    Arg::with_name("base-url")
+        .long("base-url")
+        .required(false)
+        .takes_value(true)
+        .value_name("URL")
+        .help("URL the rendered notes are published at, used as the prefix of links")
+}
+
 #[derive(Debug)]
 pub struct RenderArgs {
     pub config_path: PathBuf,
-    pub input_path: PathBuf,
-    pub output_path: PathBuf,
+    /// Markdown file to render, or `None` to read from stdin.
+    pub input_path: Option<PathBuf>,
+    /// Path to write the HTML to, or `None` to write to stdout.
+    pub output_path: Option<PathBuf>,
+    /// Directory relative links in the note are relative to.
+    pub base_dir: PathBuf,
     pub overwrite_output: bool,
+    pub html_policy: Option<HtmlPolicy>,
+    pub link_style: Option<LinkStyle>,
+    pub base_url: Option<String>,
+    pub theme: Option<String>,
+    pub stylesheet_path: Option<PathBuf>,
+    pub inline_css: bool,
+    pub mathjax_policy: Option<MathjaxPolicy>,
 }
 
 impl TryFrom<&ArgMatches<'static>> for RenderArgs {
@@ -88,19 +338,49 @@ impl TryFrom<&ArgMatches<'static>> for RenderArgs {
 
     fn try_from(matches: &ArgMatches<'static>) -> Result<Self, Self::Error> {
         let config_path = get_config_path(matches.value_of("config").map(PathBuf::from))?;
-        let input_path = matches.value_of("FILE").map(PathBuf::from).unwrap();
+        let input_path = matches
+            .value_of("FILE")
+            .filter(|&path| path != STDIO_PATH)
+            .map(absolute_path)
+            .transpose()?;
         let overwrite_output = matches.is_present("force");
         let output_path = get_output_path(
-            matches.value_of("output").map(PathBuf::from),
-            &input_path,
+            matches.value_of("output"),
+            input_path.as_deref(),
+            "html",
             overwrite_output,
-        )?;
+        )?
+        .map(absolute_path)
+        .transpose()?;
+        let base_dir = match matches.value_of("base-dir") {
+            Some(dir) => absolute_path(dir)?,
+            None => match input_path.as_ref().and_then(|path| path.parent()) {
+                Some(dir) => dir.to_path_buf(),
+                None => env::current_dir()?,
+            },
+        };
+        let html_policy = matches.value_of("html").map(str::parse).transpose()?;
+        let link_style = matches.value_of("links").map(str::parse).transpose()?;
+        let base_url = matches.value_of("base-url").map(String::from);
+        let stylesheet_path = matches
+            .value_of("stylesheet")
+            .map(absolute_path)
+            .transpose()?;
+        let mathjax_policy = matches.value_of("mathjax").map(str::parse).transpose()?;
 
         Ok(Self {
             config_path,
             input_path,
             output_path,
+            base_dir,
             overwrite_output,
+            html_policy,
+            link_style,
+            base_url,
+            theme: matches.value_of("theme").map(String::from),
+            stylesheet_path,
+            inline_css: matches.is_present("inline-css"),
+            mathjax_policy,
         })
     }
 }
@@ -108,6 +388,11 @@ impl TryFrom<&ArgMatches<'static>> for RenderArgs {
 #[derive(Debug)]
 pub struct SyncArgs {
     pub config_path: PathBuf,
+    pub html_policy: Option<HtmlPolicy>,
+    pub link_style: Option<LinkStyle>,
+    pub base_url: Option<String>,
+    pub index_sort: Option<IndexSort>,
+    pub no_search: bool,
 }
 
 impl TryFrom<&ArgMatches<'static>> for SyncArgs {
@@ -115,32 +400,153 @@ impl TryFrom<&ArgMatches<'static>> for SyncArgs {
 
     fn try_from(matches: &ArgMatches<'static>) -> Result<Self, Self::Error> {
         let config_path = get_config_path(matches.value_of("config").map(PathBuf::from))?;
-        Ok(Self { config_path })
+        let html_policy = matches.value_of("html").map(str::parse).transpose()?;
+        let link_style = matches.value_of("links").map(str::parse).transpose()?;
+        let base_url = matches.value_of("base-url").map(String::from);
+        let index_sort = matches.value_of("index-sort").map(str::parse).transpose()?;
+        Ok(Self {
+            config_path,
+            html_policy,
+            link_style,
+            base_url,
+            index_sort,
+            no_search: matches.is_present("no-search"),
+        })
     }
 }
 
-// TODO: app::get_output_path refactor to use option/result composition functions
-//  - Should remove duplicate code of checking foce and if the path exists.
-//  - Also add tests.
-fn get_output_path(output: Option<PathBuf>, input: &Path, overwrite: bool) -> io::Result<PathBuf> {
-    if let Some(path) = output {
-        if !overwrite && path.exists() {
-            return Err(io::Error::new(
-                io::ErrorKind::AlreadyExists,
-                format!("file exists at output path {}", path.display()),
-            ));
-        }
-        return Ok(path);
+#[derive(Debug)]
+pub struct SearchArgs {
+    pub query: String,
+    pub tag: Option<String>,
+    pub dir: Option<PathBuf>,
+    pub json: bool,
+}
+
+impl TryFrom<&ArgMatches<'static>> for SearchArgs {
+    type Error = io::Error;
+
+    fn try_from(matches: &ArgMatches<'static>) -> Result<Self, Self::Error> {
+        Ok(Self {
+            query: matches.value_of("QUERY").map(String::from).unwrap(),
+            tag: matches.value_of("tag").map(String::from),
+            dir: matches.value_of("in").map(PathBuf::from),
+            json: matches.is_present("json"),
+        })
     }
+}
+
+#[derive(Debug)]
+pub struct JournalArgs {
+    pub date: Date,
+    /// Whether to print the path of the note instead of opening it.
+    pub print: bool,
+}
+
+impl TryFrom<&ArgMatches<'static>> for JournalArgs {
+    type Error = io::Error;
 
-    let output_path = input.with_extension("html");
+    fn try_from(matches: &ArgMatches<'static>) -> Result<Self, Self::Error> {
+        let date = match matches.value_of("DATE") {
+            Some(date) => date.parse()?,
+            None => Date::today(),
+        };
+        Ok(Self {
+            date,
+            print: matches.is_present("print"),
+        })
+    }
+}
+
+#[derive(Debug)]
+pub struct ExportHtmlArgs {
+    pub note_path: PathBuf,
+    /// Path to write the HTML to, or `None` to write to stdout.
+    pub output_path: Option<PathBuf>,
+    pub prerender_math: bool,
+}
+
+impl TryFrom<&ArgMatches<'static>> for ExportHtmlArgs {
+    type Error = io::Error;
+
+    fn try_from(matches: &ArgMatches<'static>) -> Result<Self, Self::Error> {
+        let note_path = absolute_path(matches.value_of("NOTE").unwrap())?;
+        let output_path = get_output_path(
+            matches.value_of("output"),
+            Some(&note_path),
+            "html",
+            matches.is_present("force"),
+        )?;
+        Ok(Self {
+            note_path,
+            output_path,
+            prerender_math: matches.is_present("prerender-math"),
+        })
+    }
+}
+
+#[derive(Debug)]
+pub struct ExportEpubArgs {
+    pub dir: PathBuf,
+    /// Path to write the EPUB to, or `None` to write to stdout.
+    pub output_path: Option<PathBuf>,
+    pub prerender_math: bool,
+}
+
+impl TryFrom<&ArgMatches<'static>> for ExportEpubArgs {
+    type Error = io::Error;
+
+    fn try_from(matches: &ArgMatches<'static>) -> Result<Self, Self::Error> {
+        let dir = absolute_path(matches.value_of("DIR").unwrap())?;
+        let output_path = get_output_path(
+            matches.value_of("output"),
+            Some(&dir),
+            "epub",
+            matches.is_present("force"),
+        )?;
+        Ok(Self {
+            dir,
+            output_path,
+            prerender_math: matches.is_present("prerender-math"),
+        })
+    }
+}
+
+/// Path given on the command line for stdin or stdout.
+const STDIO_PATH: &str = "-";
+
+/// Path to write the output to, or `None` for stdout.
+///
+/// Output goes next to the input with the `extension` if there isn't an output path, or to
+/// stdout if the input is stdin.
+fn get_output_path(
+    output: Option<&str>,
+    input: Option<&Path>,
+    extension: &str,
+    overwrite: bool,
+) -> io::Result<Option<PathBuf>> {
+    let output_path = match (output, input) {
+        (Some(STDIO_PATH), _) | (None, None) => return Ok(None),
+        (Some(path), _) => PathBuf::from(path),
+        (None, Some(input)) => input.with_extension(extension),
+    };
     if !overwrite && output_path.exists() {
         return Err(io::Error::new(
             io::ErrorKind::AlreadyExists,
             format!("file exists at output path {}", output_path.display()),
         ));
     }
-    Ok(output_path)
+    Ok(Some(output_path))
+}
+
+/// Path made absolute with the current directory, so links can be made relative to it.
+fn absolute_path<P: AsRef<Path>>(path: P) -> io::Result<PathBuf> {
+    let path = path.as_ref();
+    if path.is_absolute() {
+        Ok(path.to_path_buf())
+    } else {
+        Ok(env::current_dir()?.join(path).components().collect())
+    }
 }
 
 // TODO: Refactor app::get_config_path to use Option and Result composition functions.
@@ -154,3 +560,26 @@ fn get_config_path(path: Option<PathBuf>) -> io::Result<PathBuf> {
         .map(|base| base.join("carbon/config.toml"))
         .ok_or_else(|| io::Error::new(io::ErrorKind::Other, "unable to determine config directory"))
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn output_paths() {
+        let input = Path::new("/nonexistent/note.md");
+        assert_eq!(
+            get_output_path(Some("-"), Some(input), "html", false).unwrap(),
+            None
+        );
+        assert_eq!(get_output_path(None, None, "html", false).unwrap(), None);
+        assert_eq!(
+            get_output_path(None, Some(input), "html", false).unwrap(),
+            Some(PathBuf::from("/nonexistent/note.html"))
+        );
+        assert_eq!(
+            get_output_path(Some("/nonexistent/out.html"), None, "html", false).unwrap(),
+            Some(PathBuf::from("/nonexistent/out.html"))
+        );
+    }
+}
@@ -1,6 +1,9 @@
-use crate::render::mathjax::MathjaxPolicy;
+use crate::render::{
+    code::HighlightStyle, file::FileRenderer, index::IndexSort, links::LinkStyle,
+    mathjax::MathjaxPolicy, sanitize::HtmlPolicy,
+};
 use globset::{Glob, GlobSet, GlobSetBuilder};
-use std::path::PathBuf;
+use std::{collections::HashMap, path::PathBuf};
 
 #[derive(Debug, Default)]
 pub struct Config {
@@ -13,17 +16,73 @@ pub struct RenderConfig {
     pub stylesheet_path: Option<PathBuf>,
     pub should_inline_stylesheet: bool,
     pub code_block_theme: String,
+    /// Theme used instead of `code_block_theme` when the reader prefers a dark color scheme.
+    pub code_block_dark_theme: Option<String>,
+    pub code_block_style: HighlightStyle,
+    /// Directories with additional `.sublime-syntax` files.
+    pub syntax_dirs: Vec<PathBuf>,
+    /// Directories with additional `.tmTheme` files.
+    pub theme_dirs: Vec<PathBuf>,
+    /// Directory to cache compiled syntaxes and themes, and rendered diagrams in.
+    pub cache_dir: Option<PathBuf>,
+    /// Code block language tokens to look up as another token, e.g. `tsx` as `js`.
+    pub language_aliases: HashMap<String, String>,
+    /// Local copy of the mermaid script, used to render mermaid diagrams.
+    pub mermaid_script: Option<PathBuf>,
+    /// Graphviz `dot` command used to render Graphviz diagrams. Diagrams are rendered as their
+    /// source if this is `None`.
+    pub dot_command: Option<PathBuf>,
+    /// Command used to render math to SVG when pages are exported with their math pre-rendered,
+    /// such as `tex2svg`.
+    pub math_command: Option<PathBuf>,
     pub mathjax_policy: MathjaxPolicy,
+    /// How raw HTML in notes is rendered.
+    pub html_policy: HtmlPolicy,
+    /// Directory with `note.html` and `index.html` templates that replace the built-in pages.
+    pub template_dir: Option<PathBuf>,
+    /// How links between rendered pages, and to stylesheets and scripts, are written.
+    pub link_style: LinkStyle,
+    /// URL the render directory is published at, used as the prefix of links within it.
+    pub base_url: Option<String>,
 }
 
+const LANGUAGE_ALIASES: &[(&str, &str)] = &[
+    ("console", "bash"),
+    ("dockerfile", "bash"),
+    ("jsx", "js"),
+    ("shell", "bash"),
+    ("shell-session", "bash"),
+    ("ts", "js"),
+    ("tsx", "js"),
+    ("typescript", "js"),
+    ("zsh", "bash"),
+];
+
 impl Default for RenderConfig {
     fn default() -> Self {
         let home_dir = dirs::home_dir().unwrap();
+        let config_dir = dirs::config_dir().map(|dir| dir.join("carbon"));
         Self {
             stylesheet_path: Some(home_dir.join("code/carbon-notes/style/github.css")),
             should_inline_stylesheet: false,
             code_block_theme: String::from("base16-ocean.dark"),
+            code_block_dark_theme: None,
+            code_block_style: HighlightStyle::Inline,
+            syntax_dirs: config_dir.iter().map(|dir| dir.join("syntaxes")).collect(),
+            theme_dirs: config_dir.iter().map(|dir| dir.join("themes")).collect(),
+            cache_dir: dirs::cache_dir().map(|dir| dir.join("carbon")),
+            language_aliases: LANGUAGE_ALIASES
+                .iter()
+                .map(|(alias, token)| (alias.to_string(), token.to_string()))
+                .collect(),
+            mermaid_script: config_dir.as_ref().map(|dir| dir.join("mermaid.min.js")),
+            dot_command: Some(PathBuf::from("dot")),
+            math_command: Some(PathBuf::from("tex2svg")),
             mathjax_policy: MathjaxPolicy::Always,
+            html_policy: HtmlPolicy::Trust,
+            template_dir: config_dir.as_ref().map(|dir| dir.join("templates")),
+            link_style: LinkStyle::Absolute,
+            base_url: None,
         }
     }
 }
@@ -36,6 +95,23 @@ pub struct SyncConfig {
     pub render_dir: PathBuf,
     pub ignore: GlobSet,
     pub incremental: bool,
+    /// Order of the entries in each group of index pages.
+    pub index_sort: IndexSort,
+    /// Whether to generate the search page and its index.
+    pub search: bool,
+    /// How files that aren't notes are rendered as pages, by their lowercase extension. Files
+    /// with other extensions are linked to as they are.
+    pub file_renderers: HashMap<String, FileRenderer>,
+    /// ImageMagick style command used to create the thumbnails of images. Index pages show images
+    /// at their full size if this is `None`.
+    pub thumbnail_command: Option<PathBuf>,
+    /// Largest width and height of thumbnails, in pixels.
+    pub thumbnail_size: u32,
+    /// Directory of daily notes at `YYYY/MM/DD.md`, whose years and months get calendar pages
+    /// instead of index pages.
+    pub journal_dir: Option<PathBuf>,
+    /// Template that `carbon journal` creates daily notes from, if it exists.
+    pub journal_template: Option<PathBuf>,
 }
 
 const GLOB_IGNORE: &[&str] = &[
@@ -49,9 +125,47 @@ const GLOB_IGNORE: &[&str] = &[
     "target",
 ];
 
+const FILE_RENDERERS: &[(&str, FileRenderer)] = &[
+    ("bmp", FileRenderer::Image),
+    ("c", FileRenderer::Code),
+    ("cpp", FileRenderer::Code),
+    ("css", FileRenderer::Code),
+    ("csv", FileRenderer::Csv),
+    ("gif", FileRenderer::Image),
+    ("go", FileRenderer::Code),
+    ("h", FileRenderer::Code),
+    ("hs", FileRenderer::Code),
+    ("java", FileRenderer::Code),
+    ("jpeg", FileRenderer::Image),
+    ("jpg", FileRenderer::Image),
+    ("js", FileRenderer::Code),
+    ("json", FileRenderer::Code),
+    ("log", FileRenderer::Text),
+    ("lua", FileRenderer::Code),
+    ("pdf", FileRenderer::Pdf),
+    ("png", FileRenderer::Image),
+    ("py", FileRenderer::Code),
+    ("rb", FileRenderer::Code),
+    ("rs", FileRenderer::Code),
+    ("sh", FileRenderer::Code),
+    ("sql", FileRenderer::Code),
+    ("tif", FileRenderer::Image),
+    ("tiff", FileRenderer::Image),
+    ("toml", FileRenderer::Code),
+    ("ts", FileRenderer::Code),
+    ("tsv", FileRenderer::Tsv),
+    ("txt", FileRenderer::Text),
+    ("webp", FileRenderer::Image),
+    ("xml", FileRenderer::Code),
+    ("yaml", FileRenderer::Code),
+    ("yml", FileRenderer::Code),
+];
+
 impl Default for SyncConfig {
     fn default() -> Self {
         let home_dir = dirs::home_dir().unwrap();
+        let notes_dir = home_dir.join("Dropbox/store");
+        let journal_dir = notes_dir.join("journal");
 
         let mut ignore = GlobSetBuilder::new();
         for glob in GLOB_IGNORE
@@ -62,10 +176,20 @@ impl Default for SyncConfig {
         }
 
         Self {
-            notes_dir: home_dir.join("Dropbox/store"),
+            notes_dir,
             render_dir: home_dir.join("Documents/carbon/rendered"),
             ignore: ignore.build().unwrap(),
             incremental: true,
+            index_sort: IndexSort::Name,
+            search: true,
+            file_renderers: FILE_RENDERERS
+                .iter()
+                .map(|(extension, renderer)| (extension.to_string(), *renderer))
+                .collect(),
+            thumbnail_command: Some(PathBuf::from("convert")),
+            thumbnail_size: 256,
+            journal_dir: Some(journal_dir),
+            journal_template: dirs::config_dir().map(|dir| dir.join("carbon/templates/journal.md")),
         }
     }
 }
@@ -0,0 +1,47 @@
+use crate::{
+    config::Config,
+    render::{code::HighlightStyle, links::LinkStyle, math::MathRenderer, mathjax::MathjaxPolicy},
+    sync::SyncOpts,
+};
+use std::{convert::TryFrom, io};
+
+pub mod epub;
+pub mod html;
+
+/// Options for exporting notes as files that can be shared and read outside of the render root.
+///
+/// Notes are rendered standalone, with the stylesheet inlined, inline code highlighting and
+/// absolute links, and without the search box, breadcrumbs or tags that link into the render
+/// root.
+pub struct ExportOpts {
+    pub sync_opts: SyncOpts,
+    /// Renders the math in exported notes, if it's rendered ahead of time rather than by MathJax.
+    pub math_renderer: Option<MathRenderer>,
+}
+
+impl ExportOpts {
+    /// Render math ahead of time rather than loading MathJax from its CDN.
+    pub fn with_prerendered_math(mut self, math_renderer: MathRenderer) -> Self {
+        self.sync_opts.mathjax_policy = MathjaxPolicy::Never;
+        self.math_renderer = Some(math_renderer);
+        self
+    }
+}
+
+impl TryFrom<Config> for ExportOpts {
+    type Error = io::Error;
+
+    fn try_from(mut config: Config) -> Result<Self, Self::Error> {
+        config.render.should_inline_stylesheet = true;
+        config.render.code_block_style = HighlightStyle::Inline;
+        config.render.link_style = LinkStyle::Absolute;
+        config.render.base_url = None;
+        config.sync.search = false;
+
+        let sync_opts = SyncOpts::try_from(config)?.standalone();
+        Ok(Self {
+            sync_opts,
+            math_renderer: None,
+        })
+    }
+}
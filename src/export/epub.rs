@@ -0,0 +1,799 @@
+use crate::{
+    export::{html::local_path, html::media_type, ExportOpts},
+    render::{
+        front_matter::FrontMatter,
+        index::{self, format_date},
+        source::SourceFormat,
+        stylesheet::Stylesheet,
+        transclude, TocEntry,
+    },
+    sync::{
+        object::{DirObject, Object, SourceFileObject},
+        summary::NoteWarning,
+        tree::DirTree,
+    },
+};
+use maud::html;
+use pulldown_cmark::{Event, Parser, Tag};
+use regex::{Captures, Regex};
+use serde_json::Value;
+use sha2::{Digest, Sha256};
+use std::{
+    collections::HashMap,
+    fs,
+    io::{self, Cursor, Write},
+    ops::Range,
+    path::{Path, PathBuf},
+    time::{SystemTime, UNIX_EPOCH},
+};
+use zip::{write::FileOptions, CompressionMethod, ZipWriter};
+
+const CONTAINER_XML: &str = r#"<?xml version="1.0" encoding="UTF-8"?>
+<container version="1.0" xmlns="urn:oasis:names:tc:opendocument:xmlns:container">
+  <rootfiles>
+    <rootfile full-path="OEBPS/content.opf" media-type="application/oebps-package+xml"/>
+  </rootfiles>
+</container>
+"#;
+
+const STYLESHEET_FILE_NAME: &str = "style.css";
+const NAV_FILE_NAME: &str = "nav.xhtml";
+
+/// Elements that are written as self closing tags in XHTML.
+const VOID_ELEMENTS: &[&str] = &[
+    "area", "br", "col", "embed", "hr", "img", "input", "link", "meta", "source", "track", "wbr",
+];
+
+/// Named entities that XML defines, which are kept as they are.
+const XML_ENTITIES: &[&str] = &["amp", "apos", "gt", "lt", "quot"];
+
+/// Directory of notes exported as an EPUB.
+pub struct ExportedEpub {
+    pub epub: Vec<u8>,
+    pub chapters: usize,
+    pub warnings: Vec<NoteWarning>,
+    /// Images that couldn't be embedded and math that couldn't be rendered.
+    pub errors: Vec<String>,
+}
+
+/// Note rendered as a chapter of the book.
+struct Chapter {
+    source: PathBuf,
+    file_name: String,
+    title: String,
+    content: String,
+}
+
+/// Entry in the navigation document, linking to a chapter or a heading in it. Directories
+/// without an intro don't link anywhere.
+struct NavPoint {
+    title: String,
+    href: Option<String>,
+    children: Vec<NavPoint>,
+}
+
+/// Image embedded in the book.
+struct Image {
+    file_name: String,
+    media_type: &'static str,
+    bytes: Vec<u8>,
+}
+
+/// Metadata of the book, from the front matter of the directory's intro.
+struct Metadata {
+    identifier: String,
+    title: String,
+    creators: Vec<String>,
+    language: String,
+    date: Option<String>,
+    description: Option<String>,
+    modified: String,
+}
+
+#[derive(Default)]
+struct Book {
+    chapters: Vec<Chapter>,
+    images: Vec<Image>,
+    /// File name of each embedded image by its source path.
+    image_names: HashMap<PathBuf, String>,
+    warnings: Vec<NoteWarning>,
+    errors: Vec<String>,
+}
+
+impl ExportOpts {
+    /// Export the notes in `dir` as an EPUB 3 book, with a chapter for each note in the order of
+    /// the directory's index pages. Metadata comes from the front matter of the directory's
+    /// intro, or its title if there's no front matter.
+    pub fn export_epub(&self, dir: &Path) -> io::Result<ExportedEpub> {
+        let tree = DirTree::with_root(
+            dir.to_path_buf(),
+            &self.sync_opts.dst_root,
+            &self.sync_opts.ignore,
+            // Only notes become chapters, so other files don't need renderers.
+            &HashMap::new(),
+        )?;
+        let mut book = Book::default();
+        let nav = self.add_dir(&tree.root, true, &mut book)?;
+        if book.chapters.is_empty() {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                format!("no notes to export in {}", dir.display()),
+            ));
+        }
+
+        let stylesheet = match self.sync_opts.stylesheet {
+            Some(Stylesheet::Inline(ref css)) => Some(css.as_str()),
+            _ => None,
+        };
+        let stylesheet_href = stylesheet.map(|_| STYLESHEET_FILE_NAME);
+        let chapter_names: HashMap<&Path, &str> = book
+            .chapters
+            .iter()
+            .map(|chapter| (chapter.source.as_path(), chapter.file_name.as_str()))
+            .collect();
+        let mut pages = vec![];
+        for chapter in &book.chapters {
+            let source_dir = chapter.source.parent().unwrap_or(dir);
+            let content = link_chapters(&chapter.content, source_dir, &chapter_names);
+            let content = embed_images(
+                &content,
+                source_dir,
+                &mut book.images,
+                &mut book.image_names,
+                &mut book.errors,
+            );
+            pages.push(xhtml_page(
+                &chapter.title,
+                stylesheet_href,
+                &to_xhtml(&content),
+            ));
+        }
+
+        let metadata = Metadata::read(&tree.root)?;
+
+        let mut zip = ZipWriter::new(Cursor::new(vec![]));
+        // The mimetype has to be first, and uncompressed, so the file can be identified by it.
+        let stored = FileOptions::default().compression_method(CompressionMethod::Stored);
+        let deflated = FileOptions::default().compression_method(CompressionMethod::Deflated);
+        zip.start_file("mimetype", stored)?;
+        zip.write_all(b"application/epub+zip")?;
+        zip.start_file("META-INF/container.xml", deflated)?;
+        zip.write_all(CONTAINER_XML.as_bytes())?;
+        zip.start_file("OEBPS/content.opf", deflated)?;
+        zip.write_all(package(&metadata, &book, &pages, stylesheet.is_some()).as_bytes())?;
+        zip.start_file(format!("OEBPS/{}", NAV_FILE_NAME), deflated)?;
+        zip.write_all(nav_page(&metadata.title, &nav).as_bytes())?;
+        if let Some(css) = stylesheet {
+            zip.start_file(format!("OEBPS/{}", STYLESHEET_FILE_NAME), deflated)?;
+            zip.write_all(css.as_bytes())?;
+        }
+        for (chapter, page) in book.chapters.iter().zip(&pages) {
+            zip.start_file(format!("OEBPS/{}", chapter.file_name), deflated)?;
+            zip.write_all(page.as_bytes())?;
+        }
+        for image in &book.images {
+            zip.start_file(format!("OEBPS/{}", image.file_name), stored)?;
+            zip.write_all(&image.bytes)?;
+        }
+        let epub = zip.finish()?.into_inner();
+
+        Ok(ExportedEpub {
+            epub,
+            chapters: book.chapters.len(),
+            warnings: book.warnings,
+            errors: book.errors,
+        })
+    }
+
+    /// Add the notes in the directory to the book, its intro first, and return its entry in the
+    /// navigation document. Directories without notes are left out.
+    ///
+    /// The intro of a directory is its entry, except at the root, which is the book itself.
+    fn add_dir(
+        &self,
+        dir: &DirObject,
+        is_root: bool,
+        book: &mut Book,
+    ) -> io::Result<Option<NavPoint>> {
+        let dir_name = dir
+            .path
+            .file_name()
+            .unwrap_or_default()
+            .to_string_lossy()
+            .to_string();
+        let mut point = NavPoint {
+            title: dir_name,
+            href: None,
+            children: vec![],
+        };
+        if let Some(intro) = index::intro_file(dir) {
+            let intro_point = self.add_note(intro, book)?;
+            if is_root {
+                point.children.push(intro_point);
+            } else {
+                point = intro_point;
+            }
+        }
+
+        for child in index::ordered_children(dir, self.sync_opts.index_sort)? {
+            match child {
+                Object::Dir(child_dir) => {
+                    point.children.extend(self.add_dir(child_dir, false, book)?)
+                }
+                Object::SourceFile(file) => point.children.push(self.add_note(file, book)?),
+                _ => {}
+            }
+        }
+        if point.href.is_some() || !point.children.is_empty() {
+            Ok(Some(point))
+        } else {
+            Ok(None)
+        }
+    }
+
+    fn add_note(&self, file: &SourceFileObject, book: &mut Book) -> io::Result<NavPoint> {
+        let dir = file.path.parent().unwrap_or(&self.sync_opts.src_root);
+        let source = file.read_content()?;
+        let (source, warnings) = match file.format() {
+            SourceFormat::Markdown => {
+                transclude::expand(&source, &file.path, &self.sync_opts.src_root)
+            }
+            _ => (source, vec![]),
+        };
+        let rendered = self
+            .sync_opts
+            .render_opts()
+            .with_links(self.sync_opts.links.clone())
+            .with_base_dir(dir.to_path_buf())
+            .with_format(file.format())
+            .render(&source)?;
+        for warning in warnings.into_iter().chain(rendered.warnings) {
+            book.warnings.push(NoteWarning {
+                path: file.path.clone(),
+                warning,
+            });
+        }
+
+        let mut content = rendered.content;
+        if let Some(ref math_renderer) = self.math_renderer {
+            let prerendered = math_renderer.prerender(&content);
+            content = prerendered.html;
+            book.errors.extend(prerendered.errors);
+        }
+
+        let title = rendered.title.unwrap_or_else(|| {
+            let stem = file.path.file_stem().unwrap_or_default();
+            stem.to_string_lossy().to_string()
+        });
+        let file_name = format!("chapter-{:03}.xhtml", book.chapters.len() + 1);
+        let point = NavPoint {
+            title: title.clone(),
+            href: Some(file_name.clone()),
+            children: heading_points(&rendered.toc, &file_name),
+        };
+        book.chapters.push(Chapter {
+            source: file.path.clone(),
+            file_name,
+            title,
+            content,
+        });
+        Ok(point)
+    }
+}
+
+impl Metadata {
+    fn read(root: &DirObject) -> io::Result<Self> {
+        let mut front_matter = FrontMatter::default();
+        let mut title = None;
+        if let Some(intro) = index::intro_file(root) {
+            let source = intro.read_content()?;
+            let parsed = intro.format().parse(&source);
+            title = first_h1(&parsed.events);
+            front_matter = parsed.front_matter.unwrap_or_default();
+        }
+
+        let title = front_matter
+            .title()
+            .map(String::from)
+            .or(title)
+            .unwrap_or_else(|| {
+                let name = root.path.file_name().unwrap_or_default();
+                name.to_string_lossy().to_string()
+            });
+        let creators = match front_matter.get("author").or(front_matter.get("authors")) {
+            Some(Value::String(author)) => vec![author.clone()],
+            Some(Value::Array(authors)) => authors
+                .iter()
+                .filter_map(Value::as_str)
+                .map(String::from)
+                .collect(),
+            _ => vec![],
+        };
+        let text = |key: &str| match front_matter.get(key) {
+            Some(Value::String(text)) => Some(text.clone()),
+            Some(Value::Number(number)) => Some(number.to_string()),
+            _ => None,
+        };
+        let identifier = text("identifier")
+            .or_else(|| text("isbn").map(|isbn| format!("urn:isbn:{}", isbn)))
+            .unwrap_or_else(|| uuid_urn(&root.path));
+
+        let now = SystemTime::now();
+        let secs = now
+            .duration_since(UNIX_EPOCH)
+            .map_or(0, |duration| duration.as_secs());
+        let modified = format!(
+            "{}T{:02}:{:02}:{:02}Z",
+            format_date(now),
+            secs / 3600 % 24,
+            secs / 60 % 60,
+            secs % 60
+        );
+
+        Ok(Self {
+            identifier,
+            title,
+            creators,
+            language: text("language")
+                .or_else(|| text("lang"))
+                .unwrap_or_else(|| String::from("en")),
+            date: text("date"),
+            description: text("description"),
+            modified,
+        })
+    }
+}
+
+/// Title of the note if its body starts with an H1.
+fn first_h1(events: &[(Event, Range<usize>)]) -> Option<String> {
+    match events.first() {
+        Some((Event::Start(Tag::Header(1)), _)) => {}
+        _ => return None,
+    }
+    let mut title = String::new();
+    for (event, _) in &events[1..] {
+        match event {
+            Event::End(Tag::Header(_)) => return Some(title),
+            Event::Text(text) => title.push_str(text),
+            _ => {}
+        }
+    }
+    None
+}
+
+/// Stable identifier for a book from the path of its directory, as a version 4 style UUID.
+fn uuid_urn(path: &Path) -> String {
+    let hash = Sha256::digest(path.to_string_lossy().as_bytes());
+    let mut bytes = [0; 16];
+    bytes.copy_from_slice(&hash[..16]);
+    bytes[6] = (bytes[6] & 0x0f) | 0x40;
+    bytes[8] = (bytes[8] & 0x3f) | 0x80;
+    let hex: String = bytes.iter().map(|byte| format!("{:02x}", byte)).collect();
+    format!(
+        "urn:uuid:{}-{}-{}-{}-{}",
+        &hex[..8],
+        &hex[8..12],
+        &hex[12..16],
+        &hex[16..20],
+        &hex[20..]
+    )
+}
+
+/// Navigation entries for the headings of a chapter, nested by level. A leading H1 is the title
+/// of the chapter, so it's left out.
+fn heading_points(toc: &[TocEntry], file_name: &str) -> Vec<NavPoint> {
+    let toc = match toc.first() {
+        Some(entry) if entry.level == 1 => &toc[1..],
+        _ => toc,
+    };
+    let mut points: Vec<(i32, NavPoint)> = vec![];
+    let mut stack: Vec<(i32, NavPoint)> = vec![];
+    for entry in toc {
+        let point = NavPoint {
+            title: entry.title.clone(),
+            href: Some(format!("{}#{}", file_name, entry.id)),
+            children: vec![],
+        };
+        while stack.last().is_some_and(|(level, _)| *level >= entry.level) {
+            let (level, done) = stack.pop().unwrap();
+            match stack.last_mut() {
+                Some((_, parent)) => parent.children.push(done),
+                None => points.push((level, done)),
+            }
+        }
+        stack.push((entry.level, point));
+    }
+    while let Some((level, done)) = stack.pop() {
+        match stack.last_mut() {
+            Some((_, parent)) => parent.children.push(done),
+            None => points.push((level, done)),
+        }
+    }
+    points.into_iter().map(|(_, point)| point).collect()
+}
+
+/// Point links to notes in the book at their chapters.
+fn link_chapters(html: &str, dir: &Path, chapters: &HashMap<&Path, &str>) -> String {
+    let re = Regex::new(r#"(<a\b[^>]*?\shref=")([^"]*)(")"#).unwrap();
+    re.replace_all(html, |caps: &Captures| {
+        let href = &caps[2];
+        let (path, fragment) = match href.split_once('#') {
+            Some((path, fragment)) => (path, Some(fragment)),
+            None => (href, None),
+        };
+        let chapter = local_path(path, dir)
+            .filter(|_| !path.is_empty())
+            .and_then(|path| chapters.get(path.as_path()));
+        match (chapter, fragment) {
+            (Some(chapter), Some(fragment)) => {
+                format!("{}{}#{}{}", &caps[1], chapter, fragment, &caps[3])
+            }
+            (Some(chapter), None) => format!("{}{}{}", &caps[1], chapter, &caps[3]),
+            (None, _) => caps[0].to_string(),
+        }
+    })
+    .into_owned()
+}
+
+/// Add the local images in the chapter to the book, and point the chapter at them.
+fn embed_images(
+    html: &str,
+    dir: &Path,
+    images: &mut Vec<Image>,
+    image_names: &mut HashMap<PathBuf, String>,
+    errors: &mut Vec<String>,
+) -> String {
+    let re = Regex::new(r#"(<img\b[^>]*?\ssrc=")([^"]*)(")"#).unwrap();
+    re.replace_all(html, |caps: &Captures| {
+        let path = match local_path(&caps[2], dir) {
+            Some(path) => path,
+            None => return caps[0].to_string(),
+        };
+        if let Some(file_name) = image_names.get(&path) {
+            return format!("{}{}{}", &caps[1], file_name, &caps[3]);
+        }
+        let bytes = match fs::read(&path) {
+            Ok(bytes) => bytes,
+            Err(e) => {
+                errors.push(format!("failed to embed {}: {}", path.display(), e));
+                return caps[0].to_string();
+            }
+        };
+        let extension = path.extension().unwrap_or_default().to_string_lossy();
+        let file_name = format!(
+            "images/image-{:03}.{}",
+            images.len() + 1,
+            extension.to_lowercase()
+        );
+        images.push(Image {
+            file_name: file_name.clone(),
+            media_type: media_type(&path),
+            bytes,
+        });
+        image_names.insert(path, file_name.clone());
+        format!("{}{}{}", &caps[1], file_name, &caps[3])
+    })
+    .into_owned()
+}
+
+/// Rendered HTML written as XHTML, with self closing void elements, quoted attribute values and
+/// without named entities that XML doesn't define.
+fn to_xhtml(html: &str) -> String {
+    let tag_re = Regex::new(
+        r#"<([A-Za-z][A-Za-z0-9-]*)((?:\s+[^\s"'<>/=]+(?:\s*=\s*(?:"[^"]*"|'[^']*'|[^\s"'<>=`]+))?)*)\s*/?>"#,
+    )
+    .unwrap();
+    let attribute_re =
+        Regex::new(r#"\s+([^\s"'<>/=]+)(?:\s*=\s*("[^"]*"|'[^']*'|[^\s"'<>=`]+))?"#).unwrap();
+    let entity_re = Regex::new(r"&([A-Za-z][A-Za-z0-9]*);").unwrap();
+
+    let html = tag_re.replace_all(html, |caps: &Captures| {
+        let name = &caps[1];
+        let mut tag = format!("<{}", name);
+        for attribute in attribute_re.captures_iter(&caps[2]) {
+            let key = &attribute[1];
+            match attribute.get(2).map(|value| value.as_str()) {
+                Some(value) if value.starts_with('"') || value.starts_with('\'') => {
+                    tag.push_str(&format!(" {}={}", key, value))
+                }
+                // Boolean attributes have their name as their value.
+                value => tag.push_str(&format!(" {}=\"{}\"", key, value.unwrap_or(key))),
+            }
+        }
+        if VOID_ELEMENTS.contains(&name.to_lowercase().as_str()) {
+            tag.push_str(" />");
+        } else {
+            tag.push('>');
+        }
+        tag
+    });
+    entity_re
+        .replace_all(&html, |caps: &Captures| match numeric_entity(&caps[1]) {
+            Some(entity) => entity,
+            None if XML_ENTITIES.contains(&&caps[1]) => caps[0].to_string(),
+            // Unknown entities are text, which XML would reject as an undefined entity.
+            None => format!("&amp;{}", &caps[0][1..]),
+        })
+        .into_owned()
+}
+
+/// Numeric character references for a named HTML entity, or `None` if XML defines it or it isn't
+/// an entity.
+fn numeric_entity(name: &str) -> Option<String> {
+    if XML_ENTITIES.contains(&name) {
+        return None;
+    }
+    // The markdown parser knows every named entity in HTML, so it's used to decode them.
+    let entity = format!("&{};", name);
+    let text: String = Parser::new(&entity)
+        .filter_map(|event| match event {
+            Event::Text(text) => Some(text.to_string()),
+            _ => None,
+        })
+        .collect();
+    if text != entity && !text.is_empty() {
+        Some(text.chars().map(|c| format!("&#{};", c as u32)).collect())
+    } else {
+        None
+    }
+}
+
+fn escape(text: &str) -> String {
+    html! { (text) }.into_string()
+}
+
+fn xhtml_page(title: &str, stylesheet: Option<&str>, body: &str) -> String {
+    let link = stylesheet.map_or(String::new(), |href| {
+        format!(
+            "<link rel=\"stylesheet\" type=\"text/css\" href=\"{}\" />\n",
+            href
+        )
+    });
+    format!(
+        r#"<?xml version="1.0" encoding="UTF-8"?>
+<!DOCTYPE html>
+<html xmlns="http://www.w3.org/1999/xhtml" xmlns:epub="http://www.idpf.org/2007/ops">
+<head>
+<meta charset="utf-8" />
+<title>{}</title>
+{}</head>
+<body>
+{}
+</body>
+</html>
+"#,
+        escape(title),
+        link,
+        body
+    )
+}
+
+fn nav_page(title: &str, nav: &Option<NavPoint>) -> String {
+    let mut list = String::new();
+    for point in nav.iter().flat_map(|nav| &nav.children) {
+        push_nav_point(&mut list, point);
+    }
+    xhtml_page(
+        title,
+        None,
+        &format!(
+            "<nav epub:type=\"toc\" id=\"toc\">\n<h1>{}</h1>\n<ol>{}</ol>\n</nav>",
+            escape(title),
+            list
+        ),
+    )
+}
+
+fn push_nav_point(list: &mut String, point: &NavPoint) {
+    list.push_str("<li>");
+    match point.href {
+        Some(ref href) => list.push_str(&format!(
+            "<a href=\"{}\">{}</a>",
+            escape(href),
+            escape(&point.title)
+        )),
+        None => list.push_str(&format!("<span>{}</span>", escape(&point.title))),
+    }
+    if !point.children.is_empty() {
+        list.push_str("<ol>");
+        for child in &point.children {
+            push_nav_point(list, child);
+        }
+        list.push_str("</ol>");
+    }
+    list.push_str("</li>");
+}
+
+fn package(metadata: &Metadata, book: &Book, pages: &[String], has_stylesheet: bool) -> String {
+    let mut meta = vec![
+        format!(
+            "<dc:identifier id=\"book-id\">{}</dc:identifier>",
+            escape(&metadata.identifier)
+        ),
+        format!("<dc:title>{}</dc:title>", escape(&metadata.title)),
+        format!("<dc:language>{}</dc:language>", escape(&metadata.language)),
+    ];
+    for creator in &metadata.creators {
+        meta.push(format!("<dc:creator>{}</dc:creator>", escape(creator)));
+    }
+    if let Some(ref date) = metadata.date {
+        meta.push(format!("<dc:date>{}</dc:date>", escape(date)));
+    }
+    if let Some(ref description) = metadata.description {
+        meta.push(format!(
+            "<dc:description>{}</dc:description>",
+            escape(description)
+        ));
+    }
+    meta.push(format!(
+        "<meta property=\"dcterms:modified\">{}</meta>",
+        metadata.modified
+    ));
+
+    let mut manifest = vec![format!(
+        "<item id=\"nav\" href=\"{}\" media-type=\"application/xhtml+xml\" properties=\"nav\"/>",
+        NAV_FILE_NAME
+    )];
+    if has_stylesheet {
+        manifest.push(format!(
+            "<item id=\"style\" href=\"{}\" media-type=\"text/css\"/>",
+            STYLESHEET_FILE_NAME
+        ));
+    }
+    let mut spine = vec![];
+    for (i, (chapter, page)) in book.chapters.iter().zip(pages).enumerate() {
+        let mut properties = vec![];
+        if page.contains("<svg") {
+            properties.push("svg");
+        }
+        if page.contains("src=\"http") {
+            properties.push("remote-resources");
+        }
+        let properties = if properties.is_empty() {
+            String::new()
+        } else {
+            format!(" properties=\"{}\"", properties.join(" "))
+        };
+        manifest.push(format!(
+            "<item id=\"chapter-{}\" href=\"{}\" media-type=\"application/xhtml+xml\"{}/>",
+            i + 1,
+            chapter.file_name,
+            properties
+        ));
+        spine.push(format!("<itemref idref=\"chapter-{}\"/>", i + 1));
+    }
+    for (i, image) in book.images.iter().enumerate() {
+        manifest.push(format!(
+            "<item id=\"image-{}\" href=\"{}\" media-type=\"{}\"/>",
+            i + 1,
+            image.file_name,
+            image.media_type
+        ));
+    }
+
+    format!(
+        r#"<?xml version="1.0" encoding="UTF-8"?>
+<package xmlns="http://www.idpf.org/2007/opf" version="3.0" unique-identifier="book-id">
+<metadata xmlns:dc="http://purl.org/dc/elements/1.1/">
+{}
+</metadata>
+<manifest>
+{}
+</manifest>
+<spine>
+{}
+</spine>
+</package>
+"#,
+        meta.join("\n"),
+        manifest.join("\n"),
+        spine.join("\n")
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::render::{
+        code::SyntaxHighlighter, diagram::Diagrams, mathjax::MathjaxPolicy, sanitize::HtmlPolicy,
+        syntax_assets::SyntaxAssets, template::Templates, RenderOpts,
+    };
+
+    fn toc_entry(level: i32, title: &str) -> TocEntry {
+        TocEntry {
+            level,
+            id: title.to_lowercase(),
+            title: title.to_string(),
+        }
+    }
+
+    fn titles(points: &[NavPoint]) -> Vec<String> {
+        points
+            .iter()
+            .map(|point| {
+                if point.children.is_empty() {
+                    point.title.clone()
+                } else {
+                    format!("{} {:?}", point.title, titles(&point.children))
+                }
+            })
+            .collect()
+    }
+
+    #[test]
+    fn headings_nested_by_level() {
+        let toc = vec![
+            toc_entry(1, "Title"),
+            toc_entry(2, "A"),
+            toc_entry(3, "B"),
+            toc_entry(3, "C"),
+            toc_entry(2, "D"),
+        ];
+        let points = heading_points(&toc, "chapter-001.xhtml");
+        assert_eq!(titles(&points), vec!["A [\"B\", \"C\"]", "D"]);
+        assert_eq!(points[1].href.as_deref(), Some("chapter-001.xhtml#d"));
+    }
+
+    #[test]
+    fn xhtml_void_elements() {
+        assert_eq!(
+            to_xhtml("<p>a<br>b&nbsp;<img src=\"x.png\" alt=\"x\"><hr /></p>"),
+            "<p>a<br />b&#160;<img src=\"x.png\" alt=\"x\" /><hr /></p>"
+        );
+    }
+
+    #[test]
+    fn xhtml_attributes_and_entities() {
+        assert_eq!(
+            to_xhtml("<input type=checkbox checked disabled><p>&copy; &amp;lt; &notanentity;</p>"),
+            "<input type=\"checkbox\" checked=\"checked\" disabled=\"disabled\" />\
+             <p>&#169; &amp;lt; &amp;notanentity;</p>"
+        );
+    }
+
+    #[test]
+    fn xhtml_chapter_with_callout() {
+        let highlighter =
+            SyntaxHighlighter::with_assets(SyntaxAssets::defaults(), "base16-ocean.dark").unwrap();
+        let diagrams = Diagrams {
+            mermaid_script: None,
+            dot_command: None,
+            cache_dir: None,
+        };
+        let templates = Templates::builtin();
+        let rendered = RenderOpts::new(
+            &None,
+            &None,
+            &highlighter,
+            &diagrams,
+            &MathjaxPolicy::Never,
+            &HtmlPolicy::Trust,
+            &templates,
+        )
+        .render("> [!note]+ Open note\n> Body\n\n<div>A&nbsp;&mdash;&nbsp;B</div>\n")
+        .unwrap();
+        let xhtml = to_xhtml(&rendered.content);
+        assert!(xhtml.contains("<details open=\"open\">"));
+        assert!(xhtml.contains("<div>A&#160;&#8212;&#160;B</div>"));
+    }
+
+    #[test]
+    fn links_to_chapters() {
+        let dir = Path::new("/notes/book");
+        let mut chapters = HashMap::new();
+        chapters.insert(Path::new("/notes/book/one.md"), "chapter-001.xhtml");
+        let html = "<a href=\"/notes/book/one.md#part\">1</a><a href=\"two.md\">2</a>";
+        assert_eq!(
+            link_chapters(html, dir, &chapters),
+            "<a href=\"chapter-001.xhtml#part\">1</a><a href=\"two.md\">2</a>"
+        );
+    }
+
+    #[test]
+    fn stable_identifier() {
+        let urn = uuid_urn(Path::new("/notes/book"));
+        assert_eq!(urn, uuid_urn(Path::new("/notes/book")));
+        assert_eq!(urn.len(), "urn:uuid:".len() + 36);
+        assert_eq!(&urn["urn:uuid:".len() + 14..][..1], "4");
+    }
+}
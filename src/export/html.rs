@@ -0,0 +1,184 @@
+use crate::{
+    export::ExportOpts,
+    render::{csp, links::decode_path, source::SourceFormat, transclude, Warning},
+};
+use regex::{Captures, Regex};
+use std::{
+    fs, io,
+    path::{Path, PathBuf},
+};
+
+/// Note exported as a single HTML file.
+pub struct ExportedHtml {
+    pub html: String,
+    /// Warnings about the note, at their line in the note with its transcluded notes expanded.
+    pub warnings: Vec<Warning>,
+    /// Assets that couldn't be embedded and math that couldn't be rendered, which are left as
+    /// they are.
+    pub errors: Vec<String>,
+}
+
+impl ExportOpts {
+    /// Export the note at `path` as a page that doesn't depend on any other file, with its
+    /// transcluded notes expanded, and its images and scripts embedded as data URIs.
+    pub fn export_html(&self, path: &Path) -> io::Result<ExportedHtml> {
+        let dir = path.parent().unwrap_or(&self.sync_opts.src_root);
+        let source = fs::read_to_string(path)?;
+        let format = SourceFormat::from_path(path).unwrap_or_default();
+        let (source, mut warnings) = match format {
+            SourceFormat::Markdown => transclude::expand(&source, path, &self.sync_opts.src_root),
+            _ => (source, vec![]),
+        };
+
+        let rendered = self
+            .sync_opts
+            .render_opts()
+            .with_links(self.sync_opts.links.clone())
+            .with_base_dir(dir.to_path_buf())
+            .with_format(format)
+            .render(&source)?;
+        warnings.extend(rendered.warnings);
+
+        let mut errors = vec![];
+        let mut html = rendered.html;
+        if let Some(ref math_renderer) = self.math_renderer {
+            let prerendered = math_renderer.prerender(&html);
+            html = prerendered.html;
+            errors.extend(prerendered.errors);
+        }
+
+        let embedded = embed_assets(&html, dir);
+        html = embedded.html;
+        errors.extend(embedded.errors);
+        if embedded.has_scripts {
+            html = csp::allow_script_source(&html, "data:");
+        }
+
+        Ok(ExportedHtml {
+            html,
+            warnings,
+            errors,
+        })
+    }
+}
+
+/// Page with its local assets embedded.
+struct Embedded {
+    html: String,
+    /// Whether any scripts were embedded, which the page's CSP has to allow.
+    has_scripts: bool,
+    errors: Vec<String>,
+}
+
+/// Replace the `src` of local images, scripts and media in the page with data URIs of the files.
+/// Paths that aren't absolute are relative to `dir`.
+fn embed_assets(html: &str, dir: &Path) -> Embedded {
+    let re =
+        Regex::new(r#"(<(img|script|source|video|audio|track)\b[^>]*?\ssrc=")([^"]*)(")"#).unwrap();
+    let mut has_scripts = false;
+    let mut errors = vec![];
+    let html = re.replace_all(html, |caps: &Captures| {
+        let src = &caps[3];
+        let data_uri = match local_path(src, dir) {
+            Some(path) => match data_uri(&path) {
+                Ok(data_uri) => data_uri,
+                Err(e) => {
+                    errors.push(format!("failed to embed {}: {}", path.display(), e));
+                    return caps[0].to_string();
+                }
+            },
+            None => return caps[0].to_string(),
+        };
+        has_scripts |= &caps[2] == "script";
+        format!("{}{}{}", &caps[1], data_uri, &caps[4])
+    });
+    Embedded {
+        html: html.into_owned(),
+        has_scripts,
+        errors,
+    }
+}
+
+/// Path of the file a `src` attribute refers to, if it's a local file.
+pub fn local_path(src: &str, dir: &Path) -> Option<PathBuf> {
+    let src = src.replace("&amp;", "&");
+    if src.starts_with("//") || src.starts_with('#') {
+        return None;
+    }
+    let src = match src.split_once(':') {
+        Some(("file", path)) => path.trim_start_matches("//").to_string(),
+        Some((scheme, _)) if !scheme.contains('/') => return None,
+        _ => src,
+    };
+    let src = src.split(['?', '#']).next().unwrap_or_default();
+    let path = dir.join(src);
+    if path.exists() {
+        Some(path)
+    } else {
+        Some(dir.join(decode_path(src)))
+    }
+}
+
+/// Contents of the file as a `data:` URI.
+fn data_uri(path: &Path) -> io::Result<String> {
+    let bytes = fs::read(path)?;
+    Ok(format!(
+        "data:{};base64,{}",
+        media_type(path),
+        base64::encode(bytes)
+    ))
+}
+
+pub fn media_type(path: &Path) -> &'static str {
+    let extension = path
+        .extension()
+        .and_then(|extension| extension.to_str())
+        .unwrap_or_default()
+        .to_ascii_lowercase();
+    match extension.as_str() {
+        "png" => "image/png",
+        "jpg" | "jpeg" => "image/jpeg",
+        "gif" => "image/gif",
+        "svg" => "image/svg+xml",
+        "webp" => "image/webp",
+        "avif" => "image/avif",
+        "bmp" => "image/bmp",
+        "ico" => "image/x-icon",
+        "js" | "mjs" => "text/javascript",
+        "mp4" | "m4v" => "video/mp4",
+        "webm" => "video/webm",
+        "ogg" | "ogv" => "video/ogg",
+        "mp3" => "audio/mpeg",
+        "wav" => "audio/wav",
+        "vtt" => "text/vtt",
+        _ => "application/octet-stream",
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_util::temp_dir;
+
+    #[test]
+    fn embed_local_assets() {
+        let dir = temp_dir("export-html");
+        fs::write(dir.join("a b.png"), b"png").unwrap();
+        fs::write(dir.join("script.js"), b"js").unwrap();
+
+        let html = format!(
+            "<img src=\"a%20b.png\" alt=\"A\"><img src=\"https://example.com/c.png\">\
+             <script src=\"file://{}/script.js\"></script><img src=\"missing.png\">",
+            dir.display()
+        );
+        let embedded = embed_assets(&html, &dir);
+        assert_eq!(
+            embedded.html,
+            "<img src=\"data:image/png;base64,cG5n\" alt=\"A\">\
+             <img src=\"https://example.com/c.png\">\
+             <script src=\"data:text/javascript;base64,anM=\"></script><img src=\"missing.png\">"
+        );
+        assert!(embedded.has_scripts);
+        assert_eq!(embedded.errors.len(), 1);
+    }
+}
@@ -1,11 +1,72 @@
+use crate::{
+    config::RenderConfig,
+    render::{
+        code::{HighlightStyle, SyntaxHighlighter},
+        syntax_assets::SyntaxAssets,
+    },
+};
 use std::io;
-use syntect::highlighting::ThemeSet;
-
-pub fn list_syntax_themes() -> io::Result<()> {
-    let theme_set = ThemeSet::load_defaults();
-    let keys = theme_set.themes.keys();
-    keys.map(ToString::to_string).for_each(|tn| {
-        println!("{}", tn);
-    });
+
+pub fn list_syntax_themes(config: &RenderConfig) -> io::Result<()> {
+    let assets = load_assets(config)?;
+    let custom_themes = assets.custom_themes();
+    let mut names: Vec<&String> = assets.theme_set.themes.keys().collect();
+    names.sort();
+    for name in names {
+        if custom_themes.contains(&name.as_str()) {
+            println!("{} (custom)", name);
+        } else {
+            println!("{}", name);
+        }
+    }
     Ok(())
 }
+
+pub fn list_syntaxes(config: &RenderConfig) -> io::Result<()> {
+    let assets = load_assets(config)?;
+    let custom_syntaxes = assets.custom_syntaxes();
+    let mut syntaxes: Vec<_> = assets
+        .syntax_set
+        .syntaxes()
+        .iter()
+        .filter(|syntax| !syntax.hidden)
+        .collect();
+    syntaxes.sort_by(|a, b| a.name.cmp(&b.name));
+    for syntax in syntaxes {
+        let custom = if custom_syntaxes.contains(&syntax.name.as_str()) {
+            " (custom)"
+        } else {
+            ""
+        };
+        println!(
+            "{}{} [{}]",
+            syntax.name,
+            custom,
+            syntax.file_extensions.join(", ")
+        );
+    }
+    Ok(())
+}
+
+pub fn print_syntax_css(
+    config: &RenderConfig,
+    theme_name: &str,
+    dark_theme_name: Option<&str>,
+) -> io::Result<()> {
+    let mut highlighter = SyntaxHighlighter::with_assets(load_assets(config)?, theme_name)?
+        .with_style(HighlightStyle::Classed);
+    if let Some(dark_theme_name) = dark_theme_name {
+        highlighter = highlighter.with_dark_theme(dark_theme_name)?;
+    }
+    print!("{}", highlighter.stylesheet());
+    Ok(())
+}
+
+#[inline]
+fn load_assets(config: &RenderConfig) -> io::Result<SyntaxAssets> {
+    SyntaxAssets::load(
+        &config.syntax_dirs,
+        &config.theme_dirs,
+        config.cache_dir.as_deref(),
+    )
+}
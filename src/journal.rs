@@ -0,0 +1,473 @@
+use crate::{
+    config::Config,
+    render::{
+        links::Links,
+        nav::{PageLink, Siblings},
+        source::SourceFormat,
+    },
+    sync::object::{DirObject, Object, SourceFileObject},
+};
+use minijinja::{context, Environment};
+use serde::{Deserialize, Serialize};
+use std::{
+    collections::{BTreeMap, BTreeSet},
+    convert::TryFrom,
+    fmt, fs, io,
+    path::{Path, PathBuf},
+    str::FromStr,
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+pub const MONTH_NAMES: &[&str] = &[
+    "January",
+    "February",
+    "March",
+    "April",
+    "May",
+    "June",
+    "July",
+    "August",
+    "September",
+    "October",
+    "November",
+    "December",
+];
+
+/// Names of the days of the week, starting on Monday.
+pub const WEEKDAY_NAMES: &[&str] = &[
+    "Monday",
+    "Tuesday",
+    "Wednesday",
+    "Thursday",
+    "Friday",
+    "Saturday",
+    "Sunday",
+];
+
+/// Template of new daily notes, unless there's a journal template in the config.
+const JOURNAL_TEMPLATE: &str = "---
+date: {{ date }}
+---
+
+# {{ weekday }}, {{ day }} {{ month_name }} {{ year }}
+";
+
+/// Day in the proleptic Gregorian calendar.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+pub struct Date {
+    pub year: i32,
+    /// Month of the year, from 1.
+    pub month: u32,
+    /// Day of the month, from 1.
+    pub day: u32,
+}
+
+impl Date {
+    /// Date of the day, if it's a day of the month.
+    pub fn new(year: i32, month: u32, day: u32) -> Option<Self> {
+        if (1..=12).contains(&month) && day >= 1 && day <= days_in_month(year, month) {
+            Some(Self { year, month, day })
+        } else {
+            None
+        }
+    }
+
+    /// Date of the time in UTC.
+    pub fn from_time(time: SystemTime) -> Self {
+        let secs = time
+            .duration_since(UNIX_EPOCH)
+            .map(|duration| duration.as_secs() as i64)
+            .unwrap_or(0);
+        Self::from_days(secs.div_euclid(86_400))
+    }
+
+    pub fn today() -> Self {
+        Self::from_time(SystemTime::now())
+    }
+
+    /// Date that is `days` after the epoch, from
+    /// http://howardhinnant.github.io/date_algorithms.html
+    pub fn from_days(days: i64) -> Self {
+        let days = days + 719_468;
+        let era = days.div_euclid(146_097);
+        let day_of_era = days - era * 146_097;
+        let year_of_era =
+            (day_of_era - day_of_era / 1460 + day_of_era / 36524 - day_of_era / 146_096) / 365;
+        let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+        let mp = (5 * day_of_year + 2) / 153;
+        let day = day_of_year - (153 * mp + 2) / 5 + 1;
+        let month = if mp < 10 { mp + 3 } else { mp - 9 };
+        let year = year_of_era + era * 400 + if month <= 2 { 1 } else { 0 };
+        Self {
+            year: year as i32,
+            month: month as u32,
+            day: day as u32,
+        }
+    }
+
+    /// Days from the epoch to the date, which is the inverse of `from_days`.
+    pub fn days(self) -> i64 {
+        let year = i64::from(self.year) - if self.month <= 2 { 1 } else { 0 };
+        let era = year.div_euclid(400);
+        let year_of_era = year - era * 400;
+        let month = i64::from(self.month);
+        let day_of_year = (153 * (if month > 2 { month - 3 } else { month + 9 }) + 2) / 5
+            + i64::from(self.day)
+            - 1;
+        let day_of_era = year_of_era * 365 + year_of_era / 4 - year_of_era / 100 + day_of_year;
+        era * 146_097 + day_of_era - 719_468
+    }
+
+    pub fn add_days(self, days: i64) -> Self {
+        Self::from_days(self.days() + days)
+    }
+
+    /// Day of the week, from 0 for Monday.
+    pub fn weekday(self) -> usize {
+        // The epoch was a Thursday.
+        (self.days() + 3).rem_euclid(7) as usize
+    }
+
+    pub fn month_name(self) -> &'static str {
+        MONTH_NAMES[self.month as usize - 1]
+    }
+}
+
+impl FromStr for Date {
+    type Err = io::Error;
+
+    /// Date as `YYYY-MM-DD`, or `today`, `yesterday` or `tomorrow`.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "today" => return Ok(Date::today()),
+            "yesterday" => return Ok(Date::today().add_days(-1)),
+            "tomorrow" => return Ok(Date::today().add_days(1)),
+            _ => {}
+        }
+        let mut parts = s.splitn(3, '-');
+        let mut part = |len: usize| parts.next().and_then(|part| number(part, len));
+        match (part(4), part(2), part(2)) {
+            (Some(year), Some(month), Some(day)) => Date::new(year as i32, month, day),
+            _ => None,
+        }
+        .ok_or_else(|| {
+            io::Error::new(
+                io::ErrorKind::InvalidInput,
+                format!("invalid date `{}`, expected YYYY-MM-DD", s),
+            )
+        })
+    }
+}
+
+impl fmt::Display for Date {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{:04}-{:02}-{:02}", self.year, self.month, self.day)
+    }
+}
+
+pub fn days_in_month(year: i32, month: u32) -> u32 {
+    match month {
+        2 if year % 4 == 0 && (year % 100 != 0 || year % 400 == 0) => 29,
+        2 => 28,
+        4 | 6 | 9 | 11 => 30,
+        _ => 31,
+    }
+}
+
+/// Date of a daily note, which is a note at `YYYY/MM/DD` in the journal.
+pub fn date_of(journal_root: &Path, path: &Path) -> Option<Date> {
+    SourceFormat::from_path(path)?;
+    let relative = path.strip_prefix(journal_root).ok()?;
+    let mut parts = relative.iter().map(|part| part.to_str());
+    let year = number(parts.next()??, 4)?;
+    let month = number(parts.next()??, 2)?;
+    let day = number(Path::new(parts.next()??).file_stem()?.to_str()?, 2)?;
+    match parts.next() {
+        Some(_) => None,
+        None => Date::new(year as i32, month, day),
+    }
+}
+
+/// Year and month of a directory in the journal, which is `YYYY` for a year or `YYYY/MM` for a
+/// month of the year.
+pub fn calendar_of(journal_root: &Path, dir: &Path) -> Option<(i32, Option<u32>)> {
+    let relative = dir.strip_prefix(journal_root).ok()?;
+    let mut parts = relative.iter().map(|part| part.to_str());
+    let year = number(parts.next()??, 4)? as i32;
+    let month = match parts.next() {
+        Some(month) => Some(number(month?, 2).filter(|month| (1..=12).contains(month))?),
+        None => None,
+    };
+    match parts.next() {
+        Some(_) => None,
+        None => Some((year, month)),
+    }
+}
+
+/// Number written with exactly `len` digits.
+fn number(s: &str, len: usize) -> Option<u32> {
+    if s.len() == len && s.bytes().all(|b| b.is_ascii_digit()) {
+        s.parse().ok()
+    } else {
+        None
+    }
+}
+
+/// Daily notes and their directories in the notes being synced.
+pub struct Journal<'a> {
+    pub root: &'a Path,
+    pub days: BTreeMap<Date, &'a SourceFileObject>,
+    pub years: BTreeMap<i32, &'a DirObject>,
+    pub months: BTreeMap<(i32, u32), &'a DirObject>,
+}
+
+impl<'a> Journal<'a> {
+    /// Journal at `root` in the notes, which is empty if the notes don't have it.
+    pub fn of(notes: &'a DirObject, root: &'a Path) -> Self {
+        let mut journal = Self {
+            root,
+            days: BTreeMap::new(),
+            years: BTreeMap::new(),
+            months: BTreeMap::new(),
+        };
+        if let Some(dir) = find_dir(notes, root) {
+            journal.add_dir(dir);
+        }
+        journal
+    }
+
+    fn add_dir(&mut self, dir: &'a DirObject) {
+        match calendar_of(self.root, &dir.path) {
+            Some((year, Some(month))) => {
+                self.months.insert((year, month), dir);
+            }
+            Some((year, None)) => {
+                self.years.insert(year, dir);
+            }
+            None => {}
+        }
+        for child in &dir.children {
+            match child {
+                Object::SourceFile(file) => {
+                    if let Some(date) = date_of(self.root, &file.path) {
+                        self.days.insert(date, file);
+                    }
+                }
+                Object::Dir(child_dir) => self.add_dir(child_dir),
+                _ => {}
+            }
+        }
+    }
+
+    pub fn date_of(&self, path: &Path) -> Option<Date> {
+        date_of(self.root, path).filter(|date| self.days.contains_key(date))
+    }
+
+    pub fn dates(&self) -> BTreeSet<Date> {
+        self.days.keys().copied().collect()
+    }
+
+    /// Links from the daily note to the notes of the days before and after it.
+    pub fn siblings(&self, date: Date, links: &Links) -> Siblings {
+        let page = self.days.get(&date).map(|file| file.render_path.as_path());
+        let link = |date: &Date| PageLink {
+            name: date.to_string(),
+            href: links.href(page, &self.days[date].render_path),
+        };
+        let (previous, next) = adjacent(&self.dates(), date);
+        Siblings {
+            previous: previous.as_ref().map(link),
+            next: next.as_ref().map(link),
+        }
+    }
+}
+
+/// Days before and after `date` in `days`, which doesn't have to have the date itself.
+pub fn adjacent(days: &BTreeSet<Date>, date: Date) -> (Option<Date>, Option<Date>) {
+    let previous = days.range(..date).next_back().copied();
+    let next = days.range(date.add_days(1)..).next().copied();
+    (previous, next)
+}
+
+/// Directory at `path` in the tree of `root`.
+fn find_dir<'a>(root: &'a DirObject, path: &Path) -> Option<&'a DirObject> {
+    if root.path == path {
+        return Some(root);
+    }
+    root.children.iter().find_map(|child| match child {
+        Object::Dir(dir) if path.starts_with(&dir.path) => find_dir(dir, path),
+        _ => None,
+    })
+}
+
+pub struct JournalOpts {
+    /// Directory of the daily notes, at `YYYY/MM/DD.md` in it.
+    pub root: PathBuf,
+    /// Template that new daily notes are created from, if it exists.
+    pub template: Option<PathBuf>,
+}
+
+impl JournalOpts {
+    /// Daily note of the day, which is a Markdown note unless it's already written in another
+    /// format.
+    pub fn note_path(&self, date: Date) -> PathBuf {
+        let dir = self
+            .root
+            .join(format!("{:04}", date.year))
+            .join(format!("{:02}", date.month));
+        let name = format!("{:02}", date.day);
+        SourceFormat::ALL
+            .iter()
+            .map(|format| dir.join(&name).with_extension(format.extension()))
+            .find(|path| path.is_file())
+            .unwrap_or_else(|| {
+                dir.join(name)
+                    .with_extension(SourceFormat::Markdown.extension())
+            })
+    }
+
+    /// Create the daily note of the day from the template, unless it already exists.
+    ///
+    /// Returns the path of the note and whether it was created.
+    pub fn create(&self, date: Date) -> io::Result<(PathBuf, bool)> {
+        let path = self.note_path(date);
+        if path.exists() {
+            return Ok((path, false));
+        }
+        let template = match self.template.as_ref().filter(|path| path.is_file()) {
+            Some(template) => fs::read_to_string(template)?,
+            None => String::from(JOURNAL_TEMPLATE),
+        };
+        let content = render_template(&template, date)?;
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        fs::write(&path, content)?;
+        Ok((path, true))
+    }
+}
+
+impl TryFrom<Config> for JournalOpts {
+    type Error = io::Error;
+
+    fn try_from(config: Config) -> Result<Self, Self::Error> {
+        let root = config.sync.journal_dir.ok_or_else(|| {
+            io::Error::new(
+                io::ErrorKind::NotFound,
+                "no journal directory is configured",
+            )
+        })?;
+        Ok(Self {
+            root,
+            template: config.sync.journal_template,
+        })
+    }
+}
+
+/// Daily note from a [minijinja](https://docs.rs/minijinja) template, which has the `date` as
+/// `YYYY-MM-DD` along with its `year`, `month`, `day`, `weekday` and `month_name`.
+pub fn render_template(template: &str, date: Date) -> io::Result<String> {
+    let ctx = context! {
+        date => date.to_string(),
+        year => date.year,
+        month => date.month,
+        day => date.day,
+        weekday => WEEKDAY_NAMES[date.weekday()],
+        month_name => date.month_name(),
+    };
+    let mut env = Environment::new();
+    env.set_keep_trailing_newline(true);
+    env.render_str(template, ctx).map_err(|e| {
+        io::Error::new(
+            io::ErrorKind::InvalidData,
+            format!("failed to render journal template: {}", e),
+        )
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn date(s: &str) -> Date {
+        s.parse().unwrap()
+    }
+
+    #[test]
+    fn parse_dates() {
+        assert_eq!(date("2024-02-29"), Date::new(2024, 2, 29).unwrap());
+        assert!("2023-02-29".parse::<Date>().is_err());
+        assert!("2024-2-09".parse::<Date>().is_err());
+        assert!("2024-13-01".parse::<Date>().is_err());
+        assert_eq!(date("2024-03-01").to_string(), "2024-03-01");
+    }
+
+    #[test]
+    fn days_from_epoch() {
+        assert_eq!(Date::from_days(0), date("1970-01-01"));
+        assert_eq!(date("2000-02-29").days(), 11_016);
+        assert_eq!(date("1969-12-31").days(), -1);
+        assert_eq!(date("2024-02-28").add_days(1), date("2024-02-29"));
+        assert_eq!(date("2024-12-31").add_days(1), date("2025-01-01"));
+        assert_eq!(date("2026-10-18").weekday(), 6);
+        assert_eq!(date("1970-01-01").weekday(), 3);
+    }
+
+    #[test]
+    fn journal_paths() {
+        let root = Path::new("/notes/journal");
+        assert_eq!(
+            date_of(root, Path::new("/notes/journal/2026/10/18.md")),
+            Some(date("2026-10-18"))
+        );
+        assert_eq!(
+            date_of(root, Path::new("/notes/journal/2026/10/18.png")),
+            None
+        );
+        assert_eq!(
+            date_of(root, Path::new("/notes/journal/2026/02/30.md")),
+            None
+        );
+        assert_eq!(
+            date_of(root, Path::new("/notes/journal/2026/10/ideas.md")),
+            None
+        );
+        assert_eq!(date_of(root, Path::new("/notes/2026/10/18.md")), None);
+
+        assert_eq!(
+            calendar_of(root, Path::new("/notes/journal/2026")),
+            Some((2026, None))
+        );
+        assert_eq!(
+            calendar_of(root, Path::new("/notes/journal/2026/10")),
+            Some((2026, Some(10)))
+        );
+        assert_eq!(calendar_of(root, Path::new("/notes/journal")), None);
+        assert_eq!(calendar_of(root, Path::new("/notes/journal/2026/13")), None);
+        assert_eq!(calendar_of(root, Path::new("/notes/journal/misc")), None);
+    }
+
+    #[test]
+    fn adjacent_days() {
+        let days: BTreeSet<Date> = vec![date("2026-09-30"), date("2026-10-02")]
+            .into_iter()
+            .collect();
+        assert_eq!(
+            adjacent(&days, date("2026-10-01")),
+            (Some(date("2026-09-30")), Some(date("2026-10-02")))
+        );
+        assert_eq!(
+            adjacent(&days, date("2026-10-02")),
+            (Some(date("2026-09-30")), None)
+        );
+    }
+
+    #[test]
+    fn template() {
+        let note = render_template(JOURNAL_TEMPLATE, date("2026-10-18")).unwrap();
+        assert_eq!(
+            note,
+            "---\ndate: 2026-10-18\n---\n\n# Sunday, 18 October 2026\n"
+        );
+    }
+}
@@ -1,56 +1,236 @@
 #![feature(proc_macro_hygiene)]
 
 use self::{
-    app::{RenderArgs, SyncArgs},
+    app::{ExportEpubArgs, ExportHtmlArgs, JournalArgs, RenderArgs, SearchArgs, SyncArgs},
     config::Config,
-    render::{code::SyntaxHighlighter, stylesheet::Stylesheet, RenderOpts},
+    export::ExportOpts,
+    journal::JournalOpts,
+    render::{math::MathRenderer, source::SourceFormat},
+    search::{query::Query, SearchOpts},
     sync::SyncOpts,
 };
 use clap::ArgMatches;
 use std::{
     convert::TryFrom,
+    env,
     fs::File,
     io::{self, Read, Write},
+    process::Command,
 };
 
 mod app;
 mod config;
+mod export;
 mod info;
+mod journal;
 mod render;
+mod search;
 mod sync;
+#[cfg(test)]
+mod test_util;
 
 fn cmd_render(args: RenderArgs) -> io::Result<()> {
-    let config = Config::default();
+    let mut config = Config::default();
+    if let Some(html_policy) = args.html_policy {
+        config.render.html_policy = html_policy;
+    }
+    if let Some(link_style) = args.link_style {
+        config.render.link_style = link_style;
+    }
+    if args.base_url.is_some() {
+        config.render.base_url = args.base_url;
+    }
+    if let Some(theme) = args.theme {
+        config.render.code_block_theme = theme;
+    }
+    if args.stylesheet_path.is_some() {
+        config.render.stylesheet_path = args.stylesheet_path;
+    }
+    if args.inline_css {
+        config.render.should_inline_stylesheet = true;
+    }
+    if let Some(mathjax_policy) = args.mathjax_policy {
+        config.render.mathjax_policy = mathjax_policy;
+    }
 
     let mut markdown = String::new();
-    File::open(&args.input_path).and_then(|mut fh| fh.read_to_string(&mut markdown))?;
-
-    // TODO: main::cmd_render stylesheet to be moved to RenderOpts::TryFrom<Config>
-    let stylesheet_path = config.render.stylesheet_path.as_ref();
-    let stylesheet = stylesheet_path
-        .map(|path| Stylesheet::new(path, config.render.should_inline_stylesheet))
-        .transpose()?;
-
-    // TODO: main::cmd_render syntax_highlighter to be moved to RenderOpts::TryFrom<Config>
-    let syntax_highlighter = SyntaxHighlighter::with_theme(&config.render.code_block_theme)?;
-
-    let render = RenderOpts::new(
-        &stylesheet,
-        &syntax_highlighter,
-        &config.render.mathjax_policy,
-    );
-    let html = render.render(&markdown)?;
-    File::create(&args.output_path).and_then(|mut fh| fh.write_all(&html.as_bytes()))
+    match args.input_path {
+        Some(ref path) => File::open(path).and_then(|mut fh| fh.read_to_string(&mut markdown))?,
+        None => io::stdin().read_to_string(&mut markdown)?,
+    };
+
+    let format = args
+        .input_path
+        .as_deref()
+        .and_then(SourceFormat::from_path)
+        .unwrap_or_default();
+    let opts = SyncOpts::try_from(config)?.standalone();
+    let rendered = opts.render_note(
+        &markdown,
+        format,
+        &args.base_dir,
+        args.output_path.as_deref(),
+    )?;
+    let input_name = match args.input_path {
+        Some(ref path) => path.display().to_string(),
+        None => String::from("<stdin>"),
+    };
+    for warning in &rendered.warnings {
+        eprintln!("{}:{}", input_name, warning);
+    }
+    match args.output_path {
+        Some(ref path) => {
+            File::create(path).and_then(|mut fh| fh.write_all(rendered.html.as_bytes()))
+        }
+        None => io::stdout().write_all(rendered.html.as_bytes()),
+    }
+}
+
+fn cmd_sync(args: SyncArgs) -> io::Result<()> {
+    let mut config = Config::default();
+    if let Some(html_policy) = args.html_policy {
+        config.render.html_policy = html_policy;
+    }
+    if let Some(link_style) = args.link_style {
+        config.render.link_style = link_style;
+    }
+    if args.base_url.is_some() {
+        config.render.base_url = args.base_url;
+    }
+    if let Some(index_sort) = args.index_sort {
+        config.sync.index_sort = index_sort;
+    }
+    if args.no_search {
+        config.sync.search = false;
+    }
+    let summary = SyncOpts::try_from(config)?.sync()?;
+    for warning in &summary.warnings {
+        eprintln!("{}", warning);
+    }
+    println!("{}", summary);
+    Ok(())
+}
+
+fn cmd_search(args: SearchArgs) -> io::Result<()> {
+    let query: Query = args.query.parse()?;
+    let mut opts = SearchOpts::from(Config::default());
+    opts.tag = args.tag;
+    opts.dir = args.dir;
+    let matches = opts.search(&query)?;
+    if args.json {
+        let json = serde_json::to_string_pretty(&matches)
+            .map_err(|e| io::Error::other(format!("failed to serialize search matches: {}", e)))?;
+        println!("{}", json);
+        return Ok(());
+    }
+    let highlight = search::should_highlight();
+    for search_match in &matches {
+        println!("{}", search_match.display(highlight));
+    }
+    Ok(())
 }
 
-fn cmd_sync(_args: SyncArgs) -> io::Result<()> {
+fn cmd_journal(args: JournalArgs) -> io::Result<()> {
+    let opts = JournalOpts::try_from(Config::default())?;
+    let (path, created) = opts.create(args.date)?;
+    if created {
+        eprintln!("created {}", path.display());
+    }
+
+    let editor = env::var("VISUAL")
+        .or_else(|_| env::var("EDITOR"))
+        .ok()
+        .filter(|editor| !editor.trim().is_empty());
+    let editor = match editor {
+        Some(editor) if !args.print => editor,
+        _ => {
+            println!("{}", path.display());
+            return Ok(());
+        }
+    };
+    // The editor can have arguments of its own, such as `code --wait`.
+    let mut words = editor.split_whitespace();
+    let status = Command::new(words.next().unwrap())
+        .args(words)
+        .arg(&path)
+        .status()
+        .map_err(|e| io::Error::new(e.kind(), format!("failed to run {}: {}", editor, e)))?;
+    if status.success() {
+        Ok(())
+    } else {
+        Err(io::Error::other(format!(
+            "{} exited with {}",
+            editor, status
+        )))
+    }
+}
+
+fn cmd_export(matches: &ArgMatches<'static>) -> io::Result<()> {
+    match matches.subcommand() {
+        ("html", Some(matches)) => cmd_export_html(ExportHtmlArgs::try_from(matches)?),
+        ("epub", Some(matches)) => cmd_export_epub(ExportEpubArgs::try_from(matches)?),
+        _ => unimplemented!(),
+    }
+}
+
+fn cmd_export_html(args: ExportHtmlArgs) -> io::Result<()> {
     let config = Config::default();
-    SyncOpts::try_from(config)?.sync()
+    let math_renderer = MathRenderer::from(&config.render);
+    let mut opts = ExportOpts::try_from(config)?;
+    if args.prerender_math {
+        opts = opts.with_prerendered_math(math_renderer);
+    }
+
+    let exported = opts.export_html(&args.note_path)?;
+    for warning in &exported.warnings {
+        eprintln!("{}:{}", args.note_path.display(), warning);
+    }
+    for error in &exported.errors {
+        eprintln!("{}: {}", args.note_path.display(), error);
+    }
+    match args.output_path {
+        Some(ref path) => {
+            File::create(path).and_then(|mut fh| fh.write_all(exported.html.as_bytes()))
+        }
+        None => io::stdout().write_all(exported.html.as_bytes()),
+    }
+}
+
+fn cmd_export_epub(args: ExportEpubArgs) -> io::Result<()> {
+    let config = Config::default();
+    let math_renderer = MathRenderer::from(&config.render);
+    let mut opts = ExportOpts::try_from(config)?;
+    if args.prerender_math {
+        opts = opts.with_prerendered_math(math_renderer);
+    }
+
+    let exported = opts.export_epub(&args.dir)?;
+    for warning in &exported.warnings {
+        eprintln!("{}", warning);
+    }
+    for error in &exported.errors {
+        eprintln!("{}: {}", args.dir.display(), error);
+    }
+    match args.output_path {
+        Some(ref path) => {
+            File::create(path).and_then(|mut fh| fh.write_all(&exported.epub))?;
+            eprintln!("exported {} notes to {}", exported.chapters, path.display());
+            Ok(())
+        }
+        None => io::stdout().write_all(&exported.epub),
+    }
 }
 
 fn cmd_info(matches: &ArgMatches<'static>) -> io::Result<()> {
+    let config = Config::default();
     match matches.subcommand() {
-        ("syntax-themes", _) => info::list_syntax_themes(),
+        ("syntax-themes", _) => info::list_syntax_themes(&config.render),
+        ("syntaxes", _) => info::list_syntaxes(&config.render),
+        ("syntax-css", Some(matches)) => info::print_syntax_css(
+            &config.render,
+            matches.value_of("THEME").unwrap(),
+            matches.value_of("dark"),
+        ),
         _ => unimplemented!(),
     }
 }
@@ -61,6 +241,9 @@ fn main() -> io::Result<()> {
     match matches.subcommand() {
         ("render", Some(matches)) => cmd_render(RenderArgs::try_from(matches)?),
         ("sync", Some(matches)) => cmd_sync(SyncArgs::try_from(matches)?),
+        ("search", Some(matches)) => cmd_search(SearchArgs::try_from(matches)?),
+        ("journal", Some(matches)) => cmd_journal(JournalArgs::try_from(matches)?),
+        ("export", Some(matches)) => cmd_export(matches),
         ("info", Some(matches)) => cmd_info(matches),
         _ => unimplemented!(),
     }
@@ -1,99 +1,462 @@
 use self::{
-    code::{CodeBlock, SyntaxHighlighter},
+    callout::Callout,
+    code::{CodeBlock, CodeInfo, SyntaxHighlighter},
+    diagram::{DiagramKind, Diagrams, GraphvizDiagram, MermaidDiagram},
+    embed::Include,
+    file::TABLE_SORT_SCRIPT,
+    front_matter::FrontMatter,
     header::HeaderStart,
+    links::{rewrite_note_link, Links},
     mathjax::MathjaxPolicy,
+    nav::{PageLink, Siblings},
     note::Note,
+    sanitize::{HtmlPolicy, Sanitizer},
+    source::SourceFormat,
     stylesheet::Stylesheet,
+    template::Templates,
+};
+use maud::Render;
+use pulldown_cmark::{html, Event, Tag};
+use std::{
+    fmt, fs, io,
+    ops::Range,
+    path::{Path, PathBuf},
 };
-use pulldown_cmark::{html, Event, Parser, Tag};
-use std::io;
 
+pub mod calendar;
+pub mod callout;
 pub mod code;
+pub mod csp;
+pub mod diagram;
+pub mod embed;
+pub mod file;
+pub mod front_matter;
 pub mod index;
+pub mod links;
+pub mod math;
 pub mod mathjax;
+pub mod nav;
 pub mod note;
+pub mod org;
+pub mod pdf;
+pub mod rst;
+pub mod sanitize;
+pub mod search;
+pub mod source;
 pub mod stylesheet;
+pub mod syntax_assets;
+pub mod tags;
+pub mod template;
+pub mod thumbnail;
+pub mod transclude;
 
 mod header;
-mod template;
 
-type ParserOptions = pulldown_cmark::Options;
+pub use self::header::TocEntry;
 
 pub trait ToHtml {
     fn to_html(&self) -> String;
 }
 
+/// Problem found in a note that didn't stop it from being rendered.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Warning {
+    /// Line in the note where the problem is.
+    pub line: usize,
+    pub message: String,
+}
+
+impl Warning {
+    fn new<S: Into<String>>(source: &str, offset: usize, message: S) -> Self {
+        let line = source[..offset].matches('\n').count() + 1;
+        Self {
+            line,
+            message: message.into(),
+        }
+    }
+}
+
+impl fmt::Display for Warning {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}: {}", self.line, self.message)
+    }
+}
+
+/// Rendered note along with what was found while rendering it.
+pub struct Rendered {
+    pub html: String,
+    /// HTML of the note itself, without the page around it.
+    pub content: String,
+    /// Whether the note has mermaid diagrams, and so needs the mermaid script.
+    pub has_mermaid: bool,
+    /// Whether the note has sortable tables of embedded files, along with the script that sorts
+    /// them.
+    pub has_tables: bool,
+    /// Title from the front matter, or the first header if it's an H1.
+    pub title: Option<String>,
+    pub toc: Vec<TocEntry>,
+    pub front_matter: FrontMatter,
+    /// Tags from the front matter and inline `#tags`, sorted.
+    pub tags: Vec<String>,
+    /// Files embedded into the note, which it depends on even though they aren't notes.
+    pub dependencies: Vec<PathBuf>,
+    pub warnings: Vec<Warning>,
+}
+
 #[derive(Default)]
 struct RenderState<'a> {
     /// Title of the page if the first line is a header.
     title: Option<String>,
     /// ATX header level if a header is being processed.
     header: Option<i32>,
+    toc: Vec<TocEntry>,
     /// Code block if a code block is being processed.
     code_block: Option<CodeBlock<'a>>,
+    /// Diagram if a diagram code block is being processed.
+    diagram: Option<DiagramBlock>,
+    /// Whether the page has mermaid diagrams, and so needs the mermaid script.
+    has_mermaid: bool,
+    /// Whether the page has sortable tables, and so needs the script that sorts them.
+    has_tables: bool,
+    /// Whether an include code block is being processed, whose own content is dropped.
+    in_include: bool,
+    /// Callout of each blockquote being processed, innermost last.
+    blockquotes: Vec<Option<Callout>>,
+    /// Whether inline code is being processed, where `#tags` aren't tags.
+    in_code: bool,
+    tags: Vec<String>,
+    dependencies: Vec<PathBuf>,
+    sanitizer: Sanitizer,
+    warnings: Vec<Warning>,
+}
+
+/// Source of a diagram code block.
+struct DiagramBlock {
+    kind: DiagramKind,
+    info: String,
+    /// Offset of the code block in the markdown source.
+    offset: usize,
+    source: String,
 }
 
 pub struct RenderOpts<'a> {
     stylesheet: &'a Option<Stylesheet>,
+    syntax_stylesheet: &'a Option<Stylesheet>,
     syntax_highlighter: &'a SyntaxHighlighter,
+    diagrams: &'a Diagrams,
     mathjax_policy: &'a MathjaxPolicy,
+    html_policy: &'a HtmlPolicy,
+    templates: &'a Templates,
+    /// Breadcrumbs to the directory of the note.
+    breadcrumbs: Vec<PageLink>,
+    siblings: Siblings,
+    links: Links,
+    /// Path the note is rendered to, which links in the page are relative to.
+    render_path: Option<PathBuf>,
+    /// Directory with the tag pages, which the tags of the note link to.
+    tags_dir: Option<PathBuf>,
+    /// Search page that the search box on the note opens.
+    search_page: Option<PathBuf>,
+    /// Directory the note is in, which relative links in the note are relative to.
+    base_dir: Option<PathBuf>,
+    /// Directory the other notes in the note's directory are rendered to, which links to them
+    /// are resolved against once they're rewritten to the rendered notes.
+    rendered_dir: Option<PathBuf>,
+    /// Directory of the note's source, which embedded and included files are relative to. Links
+    /// aren't resolved against it, unlike `base_dir`.
+    source_dir: Option<PathBuf>,
+    /// Format the note is written in.
+    format: SourceFormat,
 }
 
 impl<'a> RenderOpts<'a> {
     pub fn new(
         stylesheet: &'a Option<Stylesheet>,
+        syntax_stylesheet: &'a Option<Stylesheet>,
         syntax_highlighter: &'a SyntaxHighlighter,
+        diagrams: &'a Diagrams,
         mathjax_policy: &'a MathjaxPolicy,
+        html_policy: &'a HtmlPolicy,
+        templates: &'a Templates,
     ) -> Self {
         Self {
             stylesheet,
+            syntax_stylesheet,
             syntax_highlighter,
+            diagrams,
             mathjax_policy,
+            html_policy,
+            templates,
+            breadcrumbs: vec![],
+            siblings: Siblings::default(),
+            links: Links::default(),
+            render_path: None,
+            tags_dir: None,
+            search_page: None,
+            base_dir: None,
+            rendered_dir: None,
+            source_dir: None,
+            format: SourceFormat::Markdown,
         }
     }
 
-    pub fn render(&self, markdown: &str) -> io::Result<String> {
-        let md_parser = self.md_parser(&markdown);
-        let (state, events) = self.process_events(md_parser)?;
+    pub fn with_breadcrumbs(mut self, breadcrumbs: Vec<PageLink>) -> Self {
+        self.breadcrumbs = breadcrumbs;
+        self
+    }
+
+    pub fn with_siblings(mut self, siblings: Siblings) -> Self {
+        self.siblings = siblings;
+        self
+    }
+
+    pub fn with_links(mut self, links: Links) -> Self {
+        self.links = links;
+        self
+    }
+
+    pub fn with_render_path(mut self, render_path: PathBuf) -> Self {
+        self.render_path = Some(render_path);
+        self
+    }
+
+    pub fn with_tags_dir(mut self, tags_dir: PathBuf) -> Self {
+        self.tags_dir = Some(tags_dir);
+        self
+    }
+
+    pub fn with_search_page(mut self, search_page: PathBuf) -> Self {
+        self.search_page = Some(search_page);
+        self
+    }
+
+    pub fn with_base_dir(mut self, base_dir: PathBuf) -> Self {
+        self.base_dir = Some(base_dir);
+        self
+    }
+
+    pub fn with_rendered_dir(mut self, rendered_dir: PathBuf) -> Self {
+        self.rendered_dir = Some(rendered_dir);
+        self
+    }
+
+    pub fn with_source_dir(mut self, source_dir: PathBuf) -> Self {
+        self.source_dir = Some(source_dir);
+        self
+    }
+
+    pub fn with_format(mut self, format: SourceFormat) -> Self {
+        self.format = format;
+        self
+    }
+
+    pub fn render(&self, markdown: &str) -> io::Result<Rendered> {
+        let mut warnings = vec![];
+        let parsed = self.format.parse(markdown);
+        let front_matter = match parsed.front_matter {
+            Ok(front_matter) => front_matter,
+            Err(message) => {
+                warnings.push(Warning::new(markdown, 0, message));
+                FrontMatter::default()
+            }
+        };
+
+        let body_start = parsed.body_start;
+        let body = &markdown[body_start..];
+        let (mut state, events) = self.process_events(body, parsed.events)?;
+        state.warnings.extend(
+            parsed
+                .warnings
+                .into_iter()
+                .map(|(offset, message)| Warning::new(body, offset, message)),
+        );
+        let body_line = markdown[..body_start].matches('\n').count();
+        warnings.extend(state.warnings.drain(..).map(|mut warning| {
+            warning.line += body_line;
+            warning
+        }));
 
         let mut html_buf = String::new();
         html::push_html(&mut html_buf, events.into_iter());
 
-        Ok(Note::new(
-            &html_buf,
-            &state.title,
-            self.stylesheet,
-            self.mathjax_policy,
-        )
-        .to_html())
+        let mermaid_script = self
+            .diagrams
+            .mermaid_script
+            .as_deref()
+            .filter(|_| state.has_mermaid);
+        let title = front_matter
+            .title()
+            .map(String::from)
+            .or_else(|| state.title.take());
+        let mut tags = tags::from_front_matter(&front_matter);
+        tags.append(&mut state.tags);
+        tags.sort();
+        tags.dedup();
+        let tag_links = match self.tags_dir {
+            Some(ref tags_dir) => {
+                tags::page_links(&self.links, tags_dir, self.render_path.as_deref(), &tags)
+            }
+            None => vec![],
+        };
+        let note = Note {
+            rendered_html: &html_buf,
+            title: &title,
+            toc: &state.toc,
+            front_matter: &front_matter,
+            tags: &tag_links,
+            breadcrumbs: &self.breadcrumbs,
+            siblings: &self.siblings,
+            stylesheet: self.stylesheet,
+            syntax_stylesheet: self.syntax_stylesheet,
+            mermaid_script,
+            mathjax_policy: self.mathjax_policy,
+            html_policy: self.html_policy,
+            links: &self.links,
+            path: self.render_path.as_deref(),
+            search_page: self.search_page.as_deref(),
+            table_sort: state.has_tables,
+        };
+        let html = self.templates.render(&note.template())?;
+        Ok(Rendered {
+            html,
+            content: html_buf,
+            has_mermaid: state.has_mermaid,
+            has_tables: state.has_tables,
+            title,
+            toc: state.toc,
+            front_matter,
+            tags,
+            dependencies: state.dependencies,
+            warnings,
+        })
     }
 
     fn process_events(
         &self,
-        events: impl Iterator<Item = Event<'a>>,
+        source: &str,
+        events: Vec<(Event<'a>, Range<usize>)>,
     ) -> io::Result<(RenderState, Vec<Event>)> {
         let mut state = RenderState::default();
         let mut processed_events = vec![];
 
-        for event in events {
+        // Collected so callout markers can be looked ahead for at the start of a blockquote.
+        let mut events = events.into_iter();
+        while let Some((event, range)) = events.next() {
             match event {
+                Event::Start(Tag::BlockQuote) => {
+                    let callout = Callout::from_events(events.as_slice());
+                    match callout {
+                        Some((callout, marker_len)) => {
+                            processed_events.push(Event::Html(callout.start_html().into()));
+                            // Drop the marker line, and keep the rest of its paragraph.
+                            if let Some((Event::SoftBreak, _)) | Some((Event::HardBreak, _)) =
+                                events.nth(marker_len)
+                            {
+                                processed_events.push(Event::Start(Tag::Paragraph));
+                            }
+                            state.blockquotes.push(Some(callout));
+                        }
+                        None => {
+                            processed_events.push(Event::Start(Tag::BlockQuote));
+                            state.blockquotes.push(None);
+                        }
+                    }
+                }
+                Event::End(Tag::BlockQuote) => match state.blockquotes.pop() {
+                    Some(Some(callout)) => {
+                        processed_events.push(Event::Html(callout.end_html().into()))
+                    }
+                    _ => processed_events.push(Event::End(Tag::BlockQuote)),
+                },
                 Event::Start(Tag::Header(atx_level)) => {
                     state.header = Some(atx_level);
                 }
-                Event::Start(Tag::CodeBlock(language)) => {
-                    let code_block = CodeBlock::new(&self.syntax_highlighter, &language)
-                        .unwrap_or_else(|_| {
-                            eprintln!("unknown language block {:?}", language);
-                            CodeBlock::new(&self.syntax_highlighter, "txt").unwrap()
+                Event::Start(Tag::Paragraph) => {
+                    let embedded = source.get(range.clone()).and_then(embed::embedded_file);
+                    match embedded.and_then(|name| self.render_embed(&mut state, name)) {
+                        Some(Ok(html)) => {
+                            // Skip the rest of the paragraph, which is the `![[name]]` itself.
+                            for (event, _) in events.by_ref() {
+                                if event == Event::End(Tag::Paragraph) {
+                                    break;
+                                }
+                            }
+                            processed_events.push(Event::Html(html.into()));
+                        }
+                        Some(Err(message)) => {
+                            state
+                                .warnings
+                                .push(Warning::new(source, range.start, message));
+                            processed_events.push(Event::Start(Tag::Paragraph));
+                        }
+                        None => processed_events.push(Event::Start(Tag::Paragraph)),
+                    }
+                }
+                Event::Start(Tag::CodeBlock(info)) => {
+                    if let Some(include) = Include::parse(&info) {
+                        state.in_include = true;
+                        let html = match include {
+                            Ok(include) => self.render_include(&mut state, include),
+                            Err(message) => Err(message),
+                        };
+                        match html {
+                            Ok(html) => processed_events.push(Event::Html(html.into())),
+                            Err(message) => {
+                                state
+                                    .warnings
+                                    .push(Warning::new(source, range.start, message));
+                            }
+                        }
+                        continue;
+                    }
+                    let code_info = CodeInfo::parse(&info);
+                    if let Some(kind) = DiagramKind::from_language(&code_info.language) {
+                        state.diagram = Some(DiagramBlock {
+                            kind,
+                            info: info.to_string(),
+                            offset: range.start,
+                            source: String::new(),
                         });
+                        continue;
+                    }
+
+                    let (code_block, known_language) = self.code_block(&info);
+                    if !known_language {
+                        state.warnings.push(Warning::new(
+                            source,
+                            range.start,
+                            format!(
+                                "unknown code block language `{}`, rendering as plain text",
+                                code_info.language
+                            ),
+                        ));
+                    }
                     state.code_block = Some(code_block);
                 }
+                Event::Start(Tag::Code) => {
+                    state.in_code = true;
+                    processed_events.push(Event::Start(Tag::Code));
+                }
+                Event::End(Tag::Code) => {
+                    state.in_code = false;
+                    processed_events.push(Event::End(Tag::Code));
+                }
+                Event::Text(_) if state.in_include => {}
                 Event::Text(text) => {
+                    let in_code =
+                        state.in_code || state.code_block.is_some() || state.diagram.is_some();
+                    if state.sanitizer.is_dropping() && !in_code {
+                        continue;
+                    }
+                    if !in_code {
+                        state.tags.extend(tags::from_text(&text));
+                    }
                     state.header = state.header.and_then(|atx_level| {
                         if state.title.is_none() && atx_level == 1 {
                             state.title = Some(text.to_string());
                         }
                         let header_start = HeaderStart::new(atx_level, &text);
+                        state.toc.push(TocEntry::from(&header_start));
                         processed_events.push(Event::Html(header_start.to_html().into()));
                         None
                     });
@@ -101,29 +464,193 @@ impl<'a> RenderOpts<'a> {
                         code_block.push(&text);
                         continue;
                     }
+                    if let Some(ref mut diagram) = state.diagram {
+                        diagram.source.push_str(&text);
+                        continue;
+                    }
                     processed_events.push(Event::Text(text));
                 }
+                Event::End(Tag::CodeBlock(_)) if state.in_include => state.in_include = false,
                 Event::End(Tag::CodeBlock(_)) => {
+                    if let Some(diagram) = state.diagram.take() {
+                        let html = self.render_diagram(&mut state, source, diagram);
+                        processed_events.push(Event::Html(html.into()));
+                        continue;
+                    }
                     state.code_block = state.code_block.and_then(|block| {
                         processed_events.push(Event::Html(block.to_html().into()));
                         None
                     });
                 }
+                Event::Start(Tag::Link(link_type, dest, title)) => {
+                    let rewritten = rewrite_note_link(&dest).filter(|_| self.links.is_portable());
+                    let dest = match rewritten {
+                        Some(note) => {
+                            let dir = self.rendered_dir.as_ref().or(self.base_dir.as_ref());
+                            self.resolve_relative(&note, dir).unwrap_or(note).into()
+                        }
+                        None => self
+                            .resolve_relative(&dest, self.base_dir.as_ref())
+                            .map_or(dest, Into::into),
+                    };
+                    processed_events.push(Event::Start(Tag::Link(link_type, dest, title)));
+                }
+                Event::Start(Tag::Image(link_type, dest, title)) => {
+                    let dest = self
+                        .resolve_relative(&dest, self.base_dir.as_ref())
+                        .map_or(dest, Into::into);
+                    processed_events.push(Event::Start(Tag::Image(link_type, dest, title)));
+                }
+                Event::Html(_) | Event::InlineHtml(_) => {
+                    processed_events.push(self.html_policy.apply(&mut state.sanitizer, event));
+                }
+                Event::End(Tag::HtmlBlock) | Event::End(Tag::Paragraph) => {
+                    state.sanitizer.end_block();
+                    processed_events.push(event);
+                }
                 ev => processed_events.push(ev),
             }
         }
+        if state.has_tables {
+            let script = format!(
+                "<script type=\"text/javascript\">{}</script>",
+                TABLE_SORT_SCRIPT
+            );
+            processed_events.push(Event::Html(script.into()));
+        }
         Ok((state, processed_events))
     }
 
-    #[inline]
-    fn md_parser(&self, content: &'a str) -> Parser<'a> {
-        let mut opts = pulldown_cmark::Options::empty();
-        opts.insert(ParserOptions::ENABLE_TABLES);
-        opts.insert(ParserOptions::ENABLE_FOOTNOTES);
-        opts.insert(ParserOptions::ENABLE_STRIKETHROUGH);
-        opts.insert(ParserOptions::ENABLE_TASKLISTS);
+    /// Path of a file embedded into the note, which is recorded as a dependency of the note
+    /// whether or not it exists so the note is rendered again when it changes.
+    fn embedded_path(&self, state: &mut RenderState, name: &str) -> PathBuf {
+        let dir = self.source_dir.as_ref().or(self.base_dir.as_ref());
+        let path = embed::resolve(name, dir.map(PathBuf::as_path));
+        if !state.dependencies.contains(&path) {
+            state.dependencies.push(path.clone());
+        }
+        path
+    }
+
+    /// HTML of a file embedded with `![[name]]`, which is a sortable table for CSV and TSV files
+    /// and highlighted code for files with a known syntax.
+    ///
+    /// Returns `None` for other files, which are left as they are, or an error if the file can't
+    /// be read.
+    fn render_embed(&self, state: &mut RenderState, name: &str) -> Option<Result<String, String>> {
+        let extension = Path::new(name).extension()?.to_str()?.to_lowercase();
+        let delimiter = match extension.as_str() {
+            "csv" => Some(','),
+            "tsv" => Some('\t'),
+            _ => None,
+        };
+        let code_block = match delimiter {
+            Some(_) => None,
+            None => {
+                let info = CodeInfo {
+                    language: extension,
+                    title: Some(name.to_string()),
+                    ..Default::default()
+                };
+                Some(CodeBlock::with_info(self.syntax_highlighter, info).ok()?)
+            }
+        };
+
+        let path = self.embedded_path(state, name);
+        let contents = match fs::read_to_string(&path) {
+            Ok(contents) => contents,
+            Err(e) => {
+                return Some(Err(format!(
+                    "failed to read embedded file {}: {}",
+                    path.display(),
+                    e
+                )))
+            }
+        };
+        let html = match (code_block, delimiter) {
+            (Some(mut code_block), _) => {
+                code_block.push(&contents);
+                code_block.to_html()
+            }
+            (None, Some(delimiter)) => {
+                state.has_tables = true;
+                file::delimited_table(&contents, delimiter).into_string()
+            }
+            (None, None) => return None,
+        };
+        Some(Ok(html))
+    }
+
+    /// HTML of the lines of a file included with an `include` code block.
+    fn render_include(&self, state: &mut RenderState, include: Include) -> Result<String, String> {
+        let path = self.embedded_path(state, &include.path);
+        let contents = fs::read_to_string(&path)
+            .map_err(|e| format!("failed to read included file {}: {}", path.display(), e))?;
+        let code = include.select(&contents)?;
+        let mut code_block = match CodeBlock::with_info(self.syntax_highlighter, include.info) {
+            Ok(code_block) => code_block,
+            Err(e) => return Err(e.to_string()),
+        };
+        code_block.push(code);
+        Ok(code_block.to_html())
+    }
+
+    /// Relative link in the note, resolved against `dir` when the page isn't rendered into it.
+    fn resolve_relative(&self, dest: &str, dir: Option<&PathBuf>) -> Option<String> {
+        self.links
+            .resolve_relative(dest, dir?, self.render_path.as_deref())
+    }
+
+    /// Code block for the info string, and whether its language is known.
+    ///
+    /// Code blocks with an unknown language fall back to plain text.
+    fn code_block(&self, info: &str) -> (CodeBlock<'a>, bool) {
+        match CodeBlock::new(self.syntax_highlighter, info) {
+            Ok(code_block) => (code_block, true),
+            Err(_) => {
+                let fallback = CodeInfo {
+                    language: String::from("txt"),
+                    ..CodeInfo::parse(info)
+                };
+                let code_block = CodeBlock::with_info(self.syntax_highlighter, fallback).unwrap();
+                (code_block, false)
+            }
+        }
+    }
+
+    /// Render a diagram, or its source as a code block if the diagram can't be rendered.
+    fn render_diagram(
+        &self,
+        state: &mut RenderState,
+        source: &str,
+        diagram: DiagramBlock,
+    ) -> String {
+        let rendered = match diagram.kind {
+            DiagramKind::Mermaid => match self.diagrams.mermaid_script {
+                Some(_) => {
+                    state.has_mermaid = true;
+                    Ok(MermaidDiagram(&diagram.source).render().into_string())
+                }
+                None => Err(io::Error::new(
+                    io::ErrorKind::NotFound,
+                    "no local mermaid script is configured",
+                )),
+            },
+            DiagramKind::Graphviz => self
+                .diagrams
+                .render_dot(&diagram.source)
+                .map(|svg| GraphvizDiagram(&svg).render().into_string()),
+        };
 
-        // TODO: RenderOpts::md_parser should include broken link callback
-        Parser::new_with_broken_link_callback(content, opts, None)
+        rendered.unwrap_or_else(|e| {
+            state.warnings.push(Warning::new(
+                source,
+                diagram.offset,
+                format!("{}, rendering diagram source", e),
+            ));
+            let (mut code_block, _) = self.code_block(&diagram.info);
+            code_block.push(&diagram.source);
+            code_block.to_html()
+        })
     }
 }
@@ -0,0 +1,267 @@
+use crate::{
+    journal::{self, Date, Journal, MONTH_NAMES, WEEKDAY_NAMES},
+    render::{
+        front_matter::FrontMatter,
+        index::INDEX_FILE_NAME,
+        mathjax::MathjaxPolicy,
+        nav::{self, PageLink, Siblings},
+        template::{PageKind, Template},
+    },
+    sync::{object::DirObject, SyncOpts},
+};
+use maud::{html, Markup, Render};
+use std::path::{Path, PathBuf};
+
+/// Week of a month, from Monday to Sunday, with the days that are in the month.
+type Week = [Option<u32>; 7];
+
+/// Calendar page of a year or a month in the journal, which replaces the index page of its
+/// directory. Days with daily notes link to them.
+pub struct CalendarPage<'a> {
+    opts: &'a SyncOpts,
+    render_path: PathBuf,
+    title: Option<String>,
+    front_matter: FrontMatter,
+    breadcrumbs: Vec<PageLink>,
+    /// Links to the calendars of the years or months before and after this one.
+    siblings: Siblings,
+    months: Vec<Month>,
+    /// Whether this is the calendar of a single month, rather than of a year.
+    is_month: bool,
+}
+
+struct Month {
+    name: String,
+    /// Link to the calendar of the month, on the calendar of its year.
+    href: Option<String>,
+    weeks: Vec<Week>,
+    /// Links to the daily notes of the month, by day.
+    notes: Vec<Option<String>>,
+}
+
+impl<'a> CalendarPage<'a> {
+    /// Calendar of the year, or of the month in the year, where `dir` is its directory in the
+    /// journal.
+    pub fn new(
+        opts: &'a SyncOpts,
+        journal: &Journal,
+        dir: &DirObject,
+        year: i32,
+        month: Option<u32>,
+    ) -> Self {
+        let render_path = dir.render_path.join(INDEX_FILE_NAME);
+        let href = |target: &Path| opts.links.href(Some(&render_path), target);
+        let page = |dir: &DirObject| dir.render_path.join(INDEX_FILE_NAME);
+
+        let breadcrumbs = nav::breadcrumbs(
+            &opts.src_root,
+            &opts.dst_root,
+            &dir.path,
+            &opts.links,
+            Some(&render_path),
+        );
+        let months = match month {
+            Some(month) => vec![month],
+            None => (1..=12).collect(),
+        };
+        let months = months
+            .into_iter()
+            .map(|month| {
+                let notes = (1..=journal::days_in_month(year, month))
+                    .map(|day| {
+                        let date = Date { year, month, day };
+                        journal.days.get(&date).map(|file| href(&file.render_path))
+                    })
+                    .collect();
+                Month {
+                    name: month_name(year, month),
+                    href: match journal.months.get(&(year, month)) {
+                        Some(month_dir) if dir.path != month_dir.path => {
+                            Some(href(&page(month_dir)))
+                        }
+                        _ => None,
+                    },
+                    weeks: weeks(year, month),
+                    notes,
+                }
+            })
+            .collect();
+
+        let siblings = match month {
+            Some(month) => {
+                let link = |(&(year, month), dir): (&(i32, u32), &&DirObject)| PageLink {
+                    name: month_name(year, month),
+                    href: href(&page(dir)),
+                };
+                Siblings {
+                    previous: journal.months.range(..(year, month)).next_back().map(link),
+                    next: journal.months.range((year, month + 1)..).next().map(link),
+                }
+            }
+            None => {
+                let link = |(year, dir): (&i32, &&DirObject)| PageLink {
+                    name: year.to_string(),
+                    href: href(&page(dir)),
+                };
+                Siblings {
+                    previous: journal.years.range(..year).next_back().map(link),
+                    next: journal.years.range(year + 1..).next().map(link),
+                }
+            }
+        };
+        let title = match month {
+            Some(month) => month_name(year, month),
+            None => year.to_string(),
+        };
+
+        Self {
+            opts,
+            render_path,
+            title: Some(title),
+            front_matter: FrontMatter::default(),
+            breadcrumbs,
+            siblings,
+            months,
+            is_month: month.is_some(),
+        }
+    }
+
+    pub fn render_path(&self) -> &Path {
+        &self.render_path
+    }
+
+    pub fn template(&self) -> Template<'_> {
+        Template {
+            kind: PageKind::Index,
+            content: self.render(),
+            title: &self.title,
+            toc: &[],
+            front_matter: &self.front_matter,
+            tags: &[],
+            breadcrumbs: &self.breadcrumbs,
+            siblings: &self.siblings,
+            stylesheet: &self.opts.stylesheet,
+            syntax_stylesheet: &None,
+            mermaid_script: None,
+            mathjax_policy: &MathjaxPolicy::Never,
+            html_policy: &self.opts.html_policy,
+            links: &self.opts.links,
+            path: Some(&self.render_path),
+            search_page: self.opts.search_page.as_deref(),
+            table_sort: false,
+        }
+    }
+
+    fn render_month(&self, month: &Month) -> Markup {
+        html! {
+            table class="calendar-month" {
+                @if !self.is_month {
+                    caption {
+                        @match month.href {
+                            Some(ref href) => a href=(href) { (month.name) },
+                            None => (month.name),
+                        }
+                    }
+                }
+                thead {
+                    tr {
+                        @for weekday in WEEKDAY_NAMES {
+                            th title=(weekday) { (weekday.get(..2).unwrap_or_default()) }
+                        }
+                    }
+                }
+                tbody {
+                    @for week in &month.weeks {
+                        tr {
+                            @for day in week {
+                                @match day.map(|day| (day, &month.notes[day as usize - 1])) {
+                                    Some((day, Some(href))) => td class="calendar-day calendar-note" {
+                                        a href=(href) { (day) }
+                                    },
+                                    Some((day, None)) => td class="calendar-day" { (day) },
+                                    None => td {},
+                                }
+                            }
+                        }
+                    }
+                }
+            }
+        }
+    }
+}
+
+impl<'a> Render for CalendarPage<'a> {
+    fn render(&self) -> Markup {
+        html! {
+            h1 { (self.title.as_deref().unwrap_or_default()) }
+            div class=(if self.is_month { "calendar" } else { "calendar calendar-year" }) {
+                @for month in &self.months {
+                    (self.render_month(month))
+                }
+            }
+        }
+    }
+}
+
+fn month_name(year: i32, month: u32) -> String {
+    format!("{} {}", MONTH_NAMES[month as usize - 1], year)
+}
+
+/// Weeks of the month, with the days before the first and after the last day of the month left
+/// out.
+fn weeks(year: i32, month: u32) -> Vec<Week> {
+    let first = Date {
+        year,
+        month,
+        day: 1,
+    };
+    let mut weeks = vec![];
+    let mut week = [None; 7];
+    let mut weekday = first.weekday();
+    for day in 1..=journal::days_in_month(year, month) {
+        week[weekday] = Some(day);
+        weekday += 1;
+        if weekday == 7 {
+            weeks.push(week);
+            week = [None; 7];
+            weekday = 0;
+        }
+    }
+    if weekday > 0 {
+        weeks.push(week);
+    }
+    weeks
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn weeks_of_month() {
+        // October 2026 starts on a Thursday.
+        let october = weeks(2026, 10);
+        assert_eq!(october.len(), 5);
+        assert_eq!(
+            october[0],
+            [None, None, None, Some(1), Some(2), Some(3), Some(4)]
+        );
+        assert_eq!(
+            october[4],
+            [
+                Some(26),
+                Some(27),
+                Some(28),
+                Some(29),
+                Some(30),
+                Some(31),
+                None
+            ]
+        );
+
+        // February 2021 starts on a Monday and fits in four weeks.
+        let february = weeks(2021, 2);
+        assert_eq!(february.len(), 4);
+        assert_eq!(february[3][6], Some(28));
+    }
+}
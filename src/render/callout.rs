@@ -0,0 +1,170 @@
+use maud::html;
+use pulldown_cmark::{Event, Tag};
+use regex::Regex;
+use std::ops::Range;
+
+/// Whether a collapsible callout starts out expanded or collapsed.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Fold {
+    Open,
+    Closed,
+}
+
+/// Blockquote starting with a GitHub-style marker such as `[!NOTE]` or `[!WARNING] Title`.
+///
+/// A `-` or `+` directly after the marker makes the callout collapsible, collapsed or expanded
+/// respectively.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Callout {
+    /// Lowercase kind of the callout, e.g. `warning`.
+    pub kind: String,
+    pub title: String,
+    pub fold: Option<Fold>,
+}
+
+impl Callout {
+    /// Parse the callout marker from the first line of a blockquote.
+    pub fn parse(line: &str) -> Option<Self> {
+        let re_marker = Regex::new(r"^\[!([A-Za-z]+)\]([-+]?)\s*(.*)$").unwrap();
+        let captures = re_marker.captures(line.trim())?;
+
+        let kind = captures[1].to_lowercase();
+        let fold = match &captures[2] {
+            "-" => Some(Fold::Closed),
+            "+" => Some(Fold::Open),
+            _ => None,
+        };
+        let title = match captures[3].trim() {
+            "" => default_title(&kind),
+            title => title.to_string(),
+        };
+        Some(Self { kind, title, fold })
+    }
+
+    /// Callout from the events following the start of a blockquote.
+    ///
+    /// The marker must be the plain text of the first line of the first paragraph. Returns the
+    /// callout and the number of events before the break or paragraph end that closes the marker
+    /// line.
+    pub fn from_events(events: &[(Event, Range<usize>)]) -> Option<(Self, usize)> {
+        match events.first() {
+            Some((Event::Start(Tag::Paragraph), _)) => {}
+            _ => return None,
+        }
+
+        let mut line = String::new();
+        for (i, (event, _)) in events.iter().enumerate().skip(1) {
+            match event {
+                Event::Text(text) => line.push_str(text),
+                Event::SoftBreak | Event::HardBreak | Event::End(Tag::Paragraph) => {
+                    return Callout::parse(&line).map(|callout| (callout, i));
+                }
+                _ => return None,
+            }
+        }
+        None
+    }
+
+    /// Opening tags of the callout, including its title.
+    pub fn start_html(&self) -> String {
+        let (details, title) = match self.fold {
+            Some(fold) => (
+                if fold == Fold::Open {
+                    "<details open>"
+                } else {
+                    "<details>"
+                },
+                html! { summary class="callout-title" { (self.title) } },
+            ),
+            None => ("", html! { p class="callout-title" { (self.title) } }),
+        };
+        format!(
+            "<aside class=\"callout callout-{}\">{}{}",
+            self.kind,
+            details,
+            title.into_string()
+        )
+    }
+
+    /// Closing tags of the callout.
+    pub fn end_html(&self) -> &'static str {
+        match self.fold {
+            Some(_) => "</details></aside>",
+            None => "</aside>",
+        }
+    }
+}
+
+/// Kind with its first letter uppercase, e.g. `Warning` for `warning`.
+fn default_title(kind: &str) -> String {
+    let mut chars = kind.chars();
+    match chars.next() {
+        Some(first) => first.to_uppercase().chain(chars).collect(),
+        None => String::new(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use pulldown_cmark::Parser;
+
+    fn blockquote_callout(markdown: &str) -> Option<(Callout, usize)> {
+        let events: Vec<_> = Parser::new(markdown).into_offset_iter().collect();
+        Callout::from_events(&events[1..])
+    }
+
+    #[test]
+    fn parse_marker() {
+        assert_eq!(
+            Callout::parse("[!WARNING]"),
+            Some(Callout {
+                kind: String::from("warning"),
+                title: String::from("Warning"),
+                fold: None,
+            })
+        );
+        assert_eq!(Callout::parse("[link]"), None);
+        assert_eq!(Callout::parse("text [!NOTE]"), None);
+    }
+
+    #[test]
+    fn parse_custom_title_and_fold() {
+        assert_eq!(
+            Callout::parse("[!tip]- Read  this "),
+            Some(Callout {
+                kind: String::from("tip"),
+                title: String::from("Read  this"),
+                fold: Some(Fold::Closed),
+            })
+        );
+    }
+
+    #[test]
+    fn from_events() {
+        let (callout, len) = blockquote_callout("> [!NOTE] Title\n> body").unwrap();
+        assert_eq!(callout.title, "Title");
+        // Start of the paragraph and the text events of the marker line.
+        assert_eq!(len, 5);
+        assert!(blockquote_callout("> quote\n> [!NOTE]").is_none());
+        assert!(blockquote_callout("> [!NOTE] *emphasis*").is_none());
+    }
+
+    #[test]
+    fn html() {
+        let callout = Callout::parse("[!NOTE] <b>").unwrap();
+        assert_eq!(
+            callout.start_html(),
+            "<aside class=\"callout callout-note\"><p class=\"callout-title\">&lt;b&gt;</p>"
+        );
+        assert_eq!(callout.end_html(), "</aside>");
+
+        let callout = Callout::parse("[!NOTE]+").unwrap();
+        assert_eq!(
+            callout.start_html(),
+            "<aside class=\"callout callout-note\"><details open>\
+             <summary class=\"callout-title\">Note</summary>"
+        );
+        assert_eq!(callout.end_html(), "</details></aside>");
+    }
+}
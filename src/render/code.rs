@@ -1,74 +1,500 @@
-use crate::render::ToHtml;
-use std::io;
+use crate::{
+    config::RenderConfig,
+    render::{syntax_assets::SyntaxAssets, ToHtml},
+};
+use maud::{html, Markup, PreEscaped, Render};
+use std::{collections::HashMap, convert::TryFrom, fmt::Write, io, ops::RangeInclusive};
 use syntect::{
-    highlighting::{Theme, ThemeSet},
-    html::highlighted_html_for_string,
-    parsing::{SyntaxReference, SyntaxSet},
+    easy::HighlightLines,
+    highlighting::{Color, FontStyle, Theme, ThemeSet},
+    html::{start_highlighted_html_snippet, styled_line_to_highlighted_html, IncludeBackground},
+    parsing::{BasicScopeStackOp, ParseState, Scope, ScopeStack, SyntaxReference, SyntaxSet},
+    util::LinesWithEndings,
 };
 
-const DEFAULT_LANGUAGE_TOKEN: &str = "txt";
+/// How highlighted code is styled in the rendered HTML.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum HighlightStyle {
+    /// Every token has an inline `style` attribute with the colors of the theme.
+    Inline,
+    /// Every token has CSS classes named after its scopes, which are styled by a stylesheet
+    /// generated from the theme.
+    Classed,
+}
 
 pub struct SyntaxHighlighter {
     theme: Theme,
+    /// Theme for readers that prefer a dark color scheme. Only used with classed highlighting.
+    dark_theme: Option<Theme>,
+    style: HighlightStyle,
     syntax_set: SyntaxSet,
+    theme_set: ThemeSet,
+    /// Language tokens that are looked up as another token, e.g. `tsx` as `js`.
+    aliases: HashMap<String, String>,
 }
 
 impl SyntaxHighlighter {
-    pub fn with_theme(theme_name: &str) -> io::Result<Self> {
-        let theme_set = ThemeSet::load_defaults();
-        let theme = theme_set.themes.get(theme_name).ok_or_else(|| {
-            io::Error::new(
-                io::ErrorKind::NotFound,
-                format!("unknown syntax highlighting theme {}", theme_name),
-            )
-        })?;
-
+    pub fn with_assets(assets: SyntaxAssets, theme_name: &str) -> io::Result<Self> {
         Ok(Self {
-            theme: theme.clone(),
-            syntax_set: SyntaxSet::load_defaults_newlines(),
+            theme: find_theme(&assets.theme_set, theme_name)?,
+            dark_theme: None,
+            style: HighlightStyle::Inline,
+            syntax_set: assets.syntax_set,
+            theme_set: assets.theme_set,
+            aliases: HashMap::new(),
         })
     }
+
+    pub fn with_dark_theme(mut self, theme_name: &str) -> io::Result<Self> {
+        self.dark_theme = Some(find_theme(&self.theme_set, theme_name)?);
+        Ok(self)
+    }
+
+    pub fn with_style(mut self, style: HighlightStyle) -> Self {
+        self.style = style;
+        self
+    }
+
+    pub fn with_aliases(mut self, aliases: HashMap<String, String>) -> Self {
+        self.aliases = aliases;
+        self
+    }
+
+    /// Find the syntax for a language token, or for its alias if no syntax has the token, so
+    /// loaded syntaxes take precedence over the aliases.
+    fn find_syntax(&self, token: &str) -> Option<&SyntaxReference> {
+        self.syntax_set.find_syntax_by_token(token).or_else(|| {
+            self.aliases
+                .get(token)
+                .and_then(|alias| self.syntax_set.find_syntax_by_token(alias))
+        })
+    }
+
+    /// Find the syntax for untagged code from its first line, such as a shebang, falling back to
+    /// plain text.
+    fn detect_syntax(&self, code: &str) -> &SyntaxReference {
+        let first_line = code.lines().next().unwrap_or_default();
+        self.syntax_set
+            .find_syntax_by_first_line(first_line)
+            .unwrap_or_else(|| self.syntax_set.find_syntax_plain_text())
+    }
+
+    pub fn style(&self) -> HighlightStyle {
+        self.style
+    }
+
+    /// Stylesheet for classed code blocks, including the dark theme if there is one.
+    pub fn stylesheet(&self) -> String {
+        let mut css = theme_css(&self.theme);
+        if let Some(ref dark_theme) = self.dark_theme {
+            css.push_str("\n@media (prefers-color-scheme: dark) {\n");
+            css.push_str(&theme_css(dark_theme));
+            css.push_str("}\n");
+        }
+        css
+    }
+}
+
+impl TryFrom<&RenderConfig> for SyntaxHighlighter {
+    type Error = io::Error;
+
+    fn try_from(config: &RenderConfig) -> Result<Self, Self::Error> {
+        let assets = SyntaxAssets::load(
+            &config.syntax_dirs,
+            &config.theme_dirs,
+            config.cache_dir.as_deref(),
+        )?;
+        let mut highlighter = SyntaxHighlighter::with_assets(assets, &config.code_block_theme)?
+            .with_style(config.code_block_style)
+            .with_aliases(config.language_aliases.clone());
+        if let Some(ref dark_theme) = config.code_block_dark_theme {
+            highlighter = highlighter.with_dark_theme(dark_theme)?;
+        }
+        Ok(highlighter)
+    }
+}
+
+fn find_theme(theme_set: &ThemeSet, theme_name: &str) -> io::Result<Theme> {
+    theme_set.themes.get(theme_name).cloned().ok_or_else(|| {
+        io::Error::new(
+            io::ErrorKind::NotFound,
+            format!("unknown syntax highlighting theme {}", theme_name),
+        )
+    })
+}
+
+/// Generate CSS rules for classed code blocks from a theme.
+///
+/// Only theme items that select a single scope are converted, since these make up nearly all of
+/// the rules in practice and map directly onto the classes of a token.
+pub fn theme_css(theme: &Theme) -> String {
+    let mut css = String::new();
+
+    let settings = &theme.settings;
+    css.push_str(".code-block pre.code {");
+    if let Some(background) = settings.background {
+        write!(css, " background-color: {};", css_color(background)).unwrap();
+    }
+    if let Some(foreground) = settings.foreground {
+        write!(css, " color: {};", css_color(foreground)).unwrap();
+    }
+    css.push_str(" }\n");
+
+    for item in &theme.scopes {
+        let selectors: Vec<String> = item
+            .scope
+            .selectors
+            .iter()
+            .filter_map(|selector| selector.extract_single_scope())
+            .map(|scope| format!(".code-block .{}", scope_classes(scope).join(".")))
+            .collect();
+        if selectors.is_empty() {
+            continue;
+        }
+
+        let mut declarations = String::new();
+        if let Some(foreground) = item.style.foreground {
+            write!(declarations, " color: {};", css_color(foreground)).unwrap();
+        }
+        if let Some(background) = item.style.background {
+            write!(
+                declarations,
+                " background-color: {};",
+                css_color(background)
+            )
+            .unwrap();
+        }
+        if let Some(font_style) = item.style.font_style {
+            if font_style.contains(FontStyle::BOLD) {
+                declarations.push_str(" font-weight: bold;");
+            }
+            if font_style.contains(FontStyle::ITALIC) {
+                declarations.push_str(" font-style: italic;");
+            }
+            if font_style.contains(FontStyle::UNDERLINE) {
+                declarations.push_str(" text-decoration: underline;");
+            }
+        }
+        if !declarations.is_empty() {
+            writeln!(css, "{} {{{} }}", selectors.join(", "), declarations).unwrap();
+        }
+    }
+    css
+}
+
+fn css_color(color: Color) -> String {
+    if color.a == 0xFF {
+        format!("#{:02x}{:02x}{:02x}", color.r, color.g, color.b)
+    } else {
+        format!(
+            "#{:02x}{:02x}{:02x}{:02x}",
+            color.r, color.g, color.b, color.a
+        )
+    }
+}
+
+/// Classes of a scope, which are the atoms of the scope (e.g. `source.rust` is `source rust`).
+fn scope_classes(scope: Scope) -> Vec<String> {
+    scope.build_string().split('.').map(String::from).collect()
+}
+
+/// Attributes of a fenced code block parsed from its info string.
+///
+/// The info string is the language token followed by any number of comma or whitespace separated
+/// attributes, for example `rust,linenos,hl=3-5 title="main.rs"`.
+#[derive(Debug, Default, PartialEq)]
+pub struct CodeInfo {
+    pub language: String,
+    pub line_numbers: bool,
+    pub highlighted: Vec<RangeInclusive<usize>>,
+    pub title: Option<String>,
+    pub start_line: Option<usize>,
+    /// Attributes that aren't understood by the code block itself.
+    pub extra: Vec<(String, Option<String>)>,
+}
+
+impl CodeInfo {
+    pub fn parse(info: &str) -> Self {
+        let mut tokens = tokenize_info(info).into_iter();
+        let mut code_info = CodeInfo {
+            language: tokens.next().map(|(key, _)| key).unwrap_or_default(),
+            ..Default::default()
+        };
+
+        for (key, value) in tokens {
+            match (key.as_str(), value) {
+                ("linenos", None) | ("numbers", None) => code_info.line_numbers = true,
+                ("hl", Some(ranges)) | ("highlight", Some(ranges)) => {
+                    code_info.highlighted.extend(parse_line_ranges(&ranges))
+                }
+                ("title", Some(title)) => code_info.title = Some(title),
+                ("start", Some(start)) | ("linenostart", Some(start)) => {
+                    code_info.start_line = start.parse().ok();
+                    code_info.line_numbers = true;
+                }
+                (_, value) => code_info.extra.push((key, value)),
+            }
+        }
+        code_info
+    }
+
+    fn first_line(&self) -> usize {
+        self.start_line.unwrap_or(1)
+    }
+
+    fn is_highlighted(&self, line: usize) -> bool {
+        self.highlighted.iter().any(|range| range.contains(&line))
+    }
+}
+
+/// Attributes whose values are line ranges.
+const LINE_RANGE_KEYS: &[&str] = &["hl", "highlight", "lines"];
+
+/// Split an info string into `(key, value)` pairs, where bare words have no value.
+///
+/// Values can be double quoted to include separators, e.g. `title="a file.rs"`. Line ranges can
+/// also be separated by commas without quotes, e.g. `hl=1,3-4`.
+fn tokenize_info(info: &str) -> Vec<(String, Option<String>)> {
+    let mut tokens = vec![];
+    let mut chars = info.trim().chars().peekable();
+
+    loop {
+        while chars.peek().is_some_and(|c| *c == ',' || c.is_whitespace()) {
+            chars.next();
+        }
+        if chars.peek().is_none() {
+            break;
+        }
+
+        let mut key = String::new();
+        while let Some(&c) = chars.peek() {
+            if c == ',' || c == '=' || c.is_whitespace() {
+                break;
+            }
+            key.push(c);
+            chars.next();
+        }
+
+        let mut value = None;
+        if chars.peek() == Some(&'=') {
+            chars.next();
+            let mut buf = String::new();
+            if chars.peek() == Some(&'"') {
+                chars.next();
+                for c in chars.by_ref() {
+                    if c == '"' {
+                        break;
+                    }
+                    buf.push(c);
+                }
+            } else {
+                let is_ranges = LINE_RANGE_KEYS.contains(&key.as_str());
+                while let Some(&c) = chars.peek() {
+                    if c.is_whitespace() {
+                        break;
+                    }
+                    if c == ',' {
+                        // A comma followed by a line number is the next range, not the next
+                        // attribute.
+                        let mut ahead = chars.clone();
+                        ahead.next();
+                        if !is_ranges || !ahead.peek().is_some_and(char::is_ascii_digit) {
+                            break;
+                        }
+                    }
+                    buf.push(c);
+                    chars.next();
+                }
+            }
+            value = Some(buf);
+        }
+        tokens.push((key, value));
+    }
+    tokens
+}
+
+/// Parse line ranges such as `3-5`, `7` or `1-2;9` into inclusive ranges.
+///
+/// Invalid ranges are skipped.
+fn parse_line_ranges(ranges: &str) -> Vec<RangeInclusive<usize>> {
+    ranges
+        .split([';', ','])
+        .filter_map(|range| {
+            let mut bounds = range.trim().splitn(2, '-').map(str::parse::<usize>);
+            match (bounds.next(), bounds.next()) {
+                (Some(Ok(start)), None) => Some(start..=start),
+                (Some(Ok(start)), Some(Ok(end))) if start <= end => Some(start..=end),
+                _ => None,
+            }
+        })
+        .collect()
 }
 
 pub struct CodeBlock<'a> {
-    theme: &'a Theme,
-    syntax_set: &'a SyntaxSet,
-    syntax_ref: &'a SyntaxReference,
+    highlighter: &'a SyntaxHighlighter,
+    /// Syntax of the code, or `None` if it should be detected from the first line of the code.
+    syntax_ref: Option<&'a SyntaxReference>,
+    info: CodeInfo,
     code: String,
 }
 
 impl<'a> CodeBlock<'a> {
-    pub fn new(highlighter: &'a SyntaxHighlighter, token: &str) -> io::Result<Self> {
-        let language_token = if token.is_empty() {
-            DEFAULT_LANGUAGE_TOKEN
+    pub fn new(highlighter: &'a SyntaxHighlighter, info: &str) -> io::Result<Self> {
+        CodeBlock::with_info(highlighter, CodeInfo::parse(info))
+    }
+
+    pub fn with_info(highlighter: &'a SyntaxHighlighter, info: CodeInfo) -> io::Result<Self> {
+        let syntax_ref = if info.language.is_empty() {
+            None
         } else {
-            token
-        };
-        highlighter
-            .syntax_set
-            .find_syntax_by_token(&language_token)
-            .ok_or_else(|| {
+            let syntax_ref = highlighter.find_syntax(&info.language).ok_or_else(|| {
                 io::Error::new(
                     io::ErrorKind::NotFound,
-                    format!("unknown syntax highlighting token {}", language_token),
+                    format!("unknown syntax highlighting token {}", info.language),
                 )
-            })
-            .map(|syntax_ref| Self {
-                theme: &highlighter.theme,
-                syntax_set: &highlighter.syntax_set,
-                syntax_ref,
-                code: String::new(),
-            })
+            })?;
+            Some(syntax_ref)
+        };
+
+        Ok(Self {
+            highlighter,
+            syntax_ref,
+            info,
+            code: String::new(),
+        })
+    }
+
+    fn syntax_ref(&self) -> &'a SyntaxReference {
+        self.syntax_ref
+            .unwrap_or_else(|| self.highlighter.detect_syntax(&self.code))
     }
 
     pub fn push(&mut self, code: &str) {
         self.code.push_str(code)
     }
+
+    fn highlighted_lines(&self) -> Vec<String> {
+        let mut lines = match self.highlighter.style {
+            HighlightStyle::Inline => self.inline_lines(),
+            HighlightStyle::Classed => self.classed_lines(),
+        };
+        // Each line is rendered as a block, so the trailing newline would double space it.
+        for line in &mut lines {
+            if let Some(newline) = line.rfind('\n') {
+                line.remove(newline);
+            }
+        }
+        lines
+    }
+
+    fn inline_lines(&self) -> Vec<String> {
+        let mut highlighter = HighlightLines::new(self.syntax_ref(), &self.highlighter.theme);
+        let bg = self.background();
+        LinesWithEndings::from(&self.code)
+            .map(|line| {
+                let regions = highlighter.highlight(line, &self.highlighter.syntax_set);
+                styled_line_to_highlighted_html(&regions[..], IncludeBackground::IfDifferent(bg))
+            })
+            .collect()
+    }
+
+    /// Highlight lines with classed spans.
+    ///
+    /// Scopes can span multiple lines, so the spans of scopes that are still open are closed at
+    /// the end of each line and reopened at the start of the next to keep each line balanced.
+    fn classed_lines(&self) -> Vec<String> {
+        let mut parse_state = ParseState::new(self.syntax_ref());
+        let mut stack = ScopeStack::new();
+
+        LinesWithEndings::from(&self.code)
+            .map(|line| {
+                let mut html = String::new();
+                for scope in stack.as_slice() {
+                    push_scope_span(&mut html, *scope);
+                }
+
+                let mut cursor = 0;
+                for (index, op) in parse_state.parse_line(line, &self.highlighter.syntax_set) {
+                    if index > cursor {
+                        html.push_str(&escape(&line[cursor..index]));
+                        cursor = index;
+                    }
+                    stack.apply_with_hook(&op, |basic_op, _| match basic_op {
+                        BasicScopeStackOp::Push(scope) => push_scope_span(&mut html, scope),
+                        BasicScopeStackOp::Pop => html.push_str("</span>"),
+                    });
+                }
+                html.push_str(&escape(&line[cursor..]));
+
+                for _ in stack.as_slice() {
+                    html.push_str("</span>");
+                }
+                html
+            })
+            .collect()
+    }
+
+    #[inline]
+    fn background(&self) -> Color {
+        self.highlighter
+            .theme
+            .settings
+            .background
+            .unwrap_or(Color::WHITE)
+    }
+
+    fn render_line(&self, index: usize, line: &str) -> Markup {
+        let line_number = self.info.first_line() + index;
+        let class = if self.info.is_highlighted(line_number) {
+            "code-line code-line-highlighted"
+        } else {
+            "code-line"
+        };
+        html! {
+            span class=(class) {
+                @if self.info.line_numbers {
+                    span class="code-line-number" { (line_number) }
+                }
+                (PreEscaped(line))
+            }
+        }
+    }
+}
+
+impl<'a> Render for CodeBlock<'a> {
+    fn render(&self) -> Markup {
+        let pre_start = match self.highlighter.style {
+            HighlightStyle::Inline => start_highlighted_html_snippet(&self.highlighter.theme).0,
+            HighlightStyle::Classed => String::from("<pre class=\"code\">"),
+        };
+        let lines = self.highlighted_lines();
+        html! {
+            div class="code-block" {
+                @if let Some(ref title) = self.info.title {
+                    div class="code-title" { (title) }
+                }
+                (PreEscaped(pre_start))
+                @for (index, line) in lines.iter().enumerate() {
+                    (self.render_line(index, line))
+                }
+                (PreEscaped("</pre>"))
+            }
+        }
+    }
+}
+
+fn push_scope_span(html: &mut String, scope: Scope) {
+    write!(html, "<span class=\"{}\">", scope_classes(scope).join(" ")).unwrap();
+}
+
+#[inline]
+fn escape(text: &str) -> String {
+    html! { (text) }.into_string()
 }
 
 impl<'a> ToHtml for CodeBlock<'a> {
     fn to_html(&self) -> String {
-        highlighted_html_for_string(&self.code, &self.syntax_set, &self.syntax_ref, &self.theme)
+        self.render().into_string()
     }
 }
 
@@ -78,22 +504,206 @@ mod tests {
 
     #[test]
     fn invalid_language_token() {
-        let highlighter = SyntaxHighlighter::with_theme("base16-ocean.dark").unwrap();
+        let highlighter =
+            SyntaxHighlighter::with_assets(SyntaxAssets::defaults(), "base16-ocean.dark").unwrap();
         assert!(CodeBlock::new(&highlighter, "invalid-language-token").is_err());
     }
 
     #[test]
     fn invalid_theme_name() {
-        assert!(SyntaxHighlighter::with_theme("invalid-theme-name").is_err());
+        assert!(
+            SyntaxHighlighter::with_assets(SyntaxAssets::defaults(), "invalid-theme-name").is_err()
+        );
     }
 
     #[test]
     fn valid_language_token() {
-        let highlighter = SyntaxHighlighter::with_theme("base16-ocean.dark").unwrap();
+        let highlighter =
+            SyntaxHighlighter::with_assets(SyntaxAssets::defaults(), "base16-ocean.dark").unwrap();
         let mut block = CodeBlock::new(&highlighter, "rs").unwrap();
         block.push("fn main() { println!(\"{}\"); }");
 
         let html = block.to_html();
         assert!(html.contains("fn") && html.contains("main"));
     }
+
+    #[test]
+    fn valid_language_token_with_attributes() {
+        let highlighter =
+            SyntaxHighlighter::with_assets(SyntaxAssets::defaults(), "base16-ocean.dark").unwrap();
+        assert!(CodeBlock::new(&highlighter, "rust,linenos,hl=3-5 title=\"main.rs\"").is_ok());
+    }
+
+    #[test]
+    fn aliased_language_token() {
+        let mut aliases = HashMap::new();
+        aliases.insert(String::from("tsx"), String::from("js"));
+        let highlighter =
+            SyntaxHighlighter::with_assets(SyntaxAssets::defaults(), "base16-ocean.dark")
+                .unwrap()
+                .with_aliases(aliases);
+        assert!(CodeBlock::new(&highlighter, "tsx").is_ok());
+        assert!(CodeBlock::new(&highlighter, "console").is_err());
+
+        let default_aliases = crate::config::RenderConfig::default().language_aliases;
+        let highlighter =
+            SyntaxHighlighter::with_assets(SyntaxAssets::defaults(), "base16-ocean.dark")
+                .unwrap()
+                .with_aliases(default_aliases);
+        assert!(CodeBlock::new(&highlighter, "dockerfile").is_ok());
+    }
+
+    #[test]
+    fn loaded_syntax_over_alias() {
+        let dir = crate::test_util::temp_dir("code-syntax-over-alias");
+        std::fs::write(
+            dir.join("typescript.sublime-syntax"),
+            "%YAML 1.2
+---
+name: TypeScript
+file_extensions: [ts]
+scope: source.ts
+contexts:
+  main:
+    - match: '\\b(interface|type)\\b'
+      scope: keyword.declaration.ts
+",
+        )
+        .unwrap();
+        let dirs = vec![dir];
+        let mut aliases = HashMap::new();
+        aliases.insert(String::from("ts"), String::from("js"));
+        let highlighter = SyntaxHighlighter::with_assets(
+            SyntaxAssets::load(&dirs, &[], None).unwrap(),
+            "base16-ocean.dark",
+        )
+        .unwrap()
+        .with_aliases(aliases);
+        let block = CodeBlock::new(&highlighter, "ts").unwrap();
+        assert_eq!(block.syntax_ref().name, "TypeScript");
+    }
+
+    #[test]
+    fn detect_untagged_language_from_first_line() {
+        let highlighter =
+            SyntaxHighlighter::with_assets(SyntaxAssets::defaults(), "base16-ocean.dark").unwrap();
+        let mut block = CodeBlock::new(&highlighter, "").unwrap();
+        block.push("#!/usr/bin/env python\nprint(1)\n");
+        assert_eq!(block.syntax_ref().name, "Python");
+
+        let mut block = CodeBlock::new(&highlighter, "").unwrap();
+        block.push("just some text\n");
+        assert_eq!(block.syntax_ref().name, "Plain Text");
+    }
+
+    #[test]
+    fn parse_info_language_only() {
+        assert_eq!(
+            CodeInfo::parse("rust"),
+            CodeInfo {
+                language: "rust".into(),
+                ..Default::default()
+            }
+        );
+    }
+
+    #[test]
+    fn parse_info_attributes() {
+        assert_eq!(
+            CodeInfo::parse("rust,linenos,hl=3-5 title=\"main.rs\" start=10"),
+            CodeInfo {
+                language: "rust".into(),
+                line_numbers: true,
+                highlighted: vec![3..=5],
+                title: Some("main.rs".into()),
+                start_line: Some(10),
+                extra: vec![],
+            }
+        );
+    }
+
+    #[test]
+    fn parse_info_comma_separated_ranges() {
+        let info = CodeInfo::parse("rust,hl=1,3-4,linenos");
+        assert_eq!(info.highlighted, vec![1..=1, 3..=4]);
+        assert!(info.line_numbers);
+        assert!(info.extra.is_empty());
+    }
+
+    #[test]
+    fn parse_info_quoted_title_with_separators() {
+        let info = CodeInfo::parse("py title=\"a, b.py\"");
+        assert_eq!(info.title, Some("a, b.py".into()));
+    }
+
+    #[test]
+    fn parse_info_extra_attributes() {
+        let info = CodeInfo::parse("py path=script.py");
+        assert_eq!(info.extra, vec![("path".into(), Some("script.py".into()))]);
+    }
+
+    #[test]
+    fn parse_line_ranges_mixed() {
+        assert_eq!(parse_line_ranges("1-2;7;x;5-3"), vec![1..=2, 7..=7]);
+    }
+
+    #[test]
+    fn render_line_numbers_with_offset() {
+        let highlighter =
+            SyntaxHighlighter::with_assets(SyntaxAssets::defaults(), "base16-ocean.dark").unwrap();
+        let mut block = CodeBlock::new(&highlighter, "txt,start=10,hl=11").unwrap();
+        block.push("a\nb\n");
+
+        let html = block.to_html();
+        assert!(html.contains("<span class=\"code-line-number\">10</span>"));
+        assert!(html.contains("code-line-highlighted\"><span class=\"code-line-number\">11"));
+    }
+
+    #[test]
+    fn render_classed_lines_are_balanced() {
+        let highlighter =
+            SyntaxHighlighter::with_assets(SyntaxAssets::defaults(), "base16-ocean.dark")
+                .unwrap()
+                .with_style(HighlightStyle::Classed);
+        let mut block = CodeBlock::new(&highlighter, "rs").unwrap();
+        block.push("/* a\nb */\nfn main() {}\n");
+
+        for line in block.classed_lines() {
+            assert_eq!(
+                line.matches("<span").count(),
+                line.matches("</span>").count()
+            );
+        }
+        assert!(block.to_html().contains("<span class=\"comment"));
+        assert!(!block.to_html().contains("style="));
+    }
+
+    #[test]
+    fn stylesheet_with_dark_theme() {
+        let highlighter =
+            SyntaxHighlighter::with_assets(SyntaxAssets::defaults(), "InspiredGitHub")
+                .and_then(|highlighter| highlighter.with_dark_theme("base16-ocean.dark"))
+                .unwrap()
+                .with_style(HighlightStyle::Classed);
+        let css = highlighter.stylesheet();
+        assert!(css.starts_with(".code-block pre.code {"));
+        assert!(css.contains("@media (prefers-color-scheme: dark)"));
+    }
+
+    #[test]
+    fn theme_css_single_scope_rules() {
+        let theme_set = ThemeSet::load_defaults();
+        let css = theme_css(&theme_set.themes["base16-ocean.dark"]);
+        assert!(css.contains(".code-block .comment"));
+    }
+
+    #[test]
+    fn render_title() {
+        let highlighter =
+            SyntaxHighlighter::with_assets(SyntaxAssets::defaults(), "base16-ocean.dark").unwrap();
+        let block = CodeBlock::new(&highlighter, "txt title=main.rs").unwrap();
+        assert!(block
+            .to_html()
+            .contains("<div class=\"code-title\">main.rs</div>"));
+    }
 }
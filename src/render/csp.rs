@@ -0,0 +1,160 @@
+use crate::render::{diagram::MERMAID_CONFIG, file::TABLE_SORT_SCRIPT, index::INDEX_FILTER_SCRIPT};
+use maud::{html, Markup, Render};
+use sha2::{Digest, Sha256};
+
+/// Host that MathJax, along with its extensions and fonts, is loaded from.
+const MATHJAX_HOST: &str = "https://cdnjs.cloudflare.com";
+
+/// Content Security Policy for a page, allowing only the scripts that carbon adds to it.
+///
+/// Pages are viewed both from the file system and from a web server, so `file:` is allowed
+/// alongside `'self'`.
+pub struct ContentSecurityPolicy {
+    pub mermaid: bool,
+    pub mathjax: bool,
+    /// Whether the page is an index page, with the script for its filter box.
+    pub index_filter: bool,
+    /// Whether the page has a search box, which submits to the search page.
+    pub search_form: bool,
+    /// Whether the page is the search page, with the search index and script.
+    pub search_scripts: bool,
+    /// Whether the page has sortable tables, with the script that sorts them.
+    pub table_sort: bool,
+}
+
+impl ContentSecurityPolicy {
+    pub fn directives(&self) -> String {
+        let mut script_src = vec![];
+        let mut font_src = vec!["'self'", "file:", "data:"];
+        let mermaid_hash = script_hash(MERMAID_CONFIG);
+        if self.mermaid || self.search_scripts {
+            script_src.extend(&["'self'", "file:"]);
+        }
+        if self.mermaid {
+            script_src.push(mermaid_hash.as_str());
+        }
+        let index_filter_hash = script_hash(INDEX_FILTER_SCRIPT);
+        if self.index_filter {
+            script_src.push(index_filter_hash.as_str());
+        }
+        let table_sort_hash = script_hash(TABLE_SORT_SCRIPT);
+        if self.table_sort {
+            script_src.push(table_sort_hash.as_str());
+        }
+        if self.mathjax {
+            // MathJax evaluates its inline configuration itself.
+            script_src.extend(&[MATHJAX_HOST, "'unsafe-eval'"]);
+            font_src.push(MATHJAX_HOST);
+        }
+        if script_src.is_empty() {
+            script_src.push("'none'");
+        }
+
+        [
+            String::from("default-src 'self' file:"),
+            format!("script-src {}", script_src.join(" ")),
+            String::from("style-src 'self' file: 'unsafe-inline'"),
+            format!("font-src {}", font_src.join(" ")),
+            String::from("img-src * data:"),
+            String::from("media-src *"),
+            String::from("object-src 'none'"),
+            String::from("base-uri 'none'"),
+            String::from(if self.search_form {
+                "form-action 'self' file:"
+            } else {
+                "form-action 'none'"
+            }),
+        ]
+        .join("; ")
+    }
+}
+
+impl Render for ContentSecurityPolicy {
+    fn render(&self) -> Markup {
+        html! { meta http-equiv="Content-Security-Policy" content=(self.directives()); }
+    }
+}
+
+/// Add a source to the `script-src` of the policy in a rendered page, for scripts that are added
+/// to the page after it's rendered.
+pub fn allow_script_source(html: &str, source: &str) -> String {
+    if html.contains("script-src 'none'") {
+        html.replacen("script-src 'none'", &format!("script-src {}", source), 1)
+    } else {
+        html.replacen("script-src ", &format!("script-src {} ", source), 1)
+    }
+}
+
+/// CSP source expression allowing an inline script with the given content.
+fn script_hash(script: &str) -> String {
+    format!(
+        "'sha256-{}'",
+        base64::encode(Sha256::digest(script.as_bytes()))
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn no_scripts() {
+        let csp = ContentSecurityPolicy {
+            mermaid: false,
+            mathjax: false,
+            index_filter: false,
+            search_form: false,
+            search_scripts: false,
+            table_sort: false,
+        };
+        assert!(csp.directives().contains("script-src 'none';"));
+    }
+
+    #[test]
+    fn allows_page_scripts() {
+        let csp = ContentSecurityPolicy {
+            mermaid: true,
+            mathjax: true,
+            index_filter: true,
+            search_form: true,
+            search_scripts: true,
+            table_sort: true,
+        }
+        .directives();
+        let script_src = csp
+            .split("; ")
+            .find(|directive| directive.starts_with("script-src"))
+            .unwrap();
+        assert!(script_src.contains(&script_hash(MERMAID_CONFIG)));
+        assert!(script_src.contains(&script_hash(INDEX_FILTER_SCRIPT)));
+        assert!(script_src.contains(&script_hash(TABLE_SORT_SCRIPT)));
+        assert!(script_src.contains(&format!("{} 'unsafe-eval'", MATHJAX_HOST)));
+        assert!(!script_src.contains("'unsafe-inline'"));
+        assert!(csp.contains("form-action 'self' file:"));
+    }
+
+    #[test]
+    fn allow_added_script_source() {
+        let html = ContentSecurityPolicy {
+            mermaid: false,
+            mathjax: false,
+            index_filter: false,
+            search_form: false,
+            search_scripts: false,
+            table_sort: false,
+        }
+        .render()
+        .into_string();
+        let html = allow_script_source(&html, "data:");
+        assert!(html.contains("script-src data:;"));
+        assert!(allow_script_source(&html, "file:").contains("script-src file: data:;"));
+    }
+
+    #[test]
+    fn hash_of_script() {
+        assert_eq!(
+            script_hash("alert('Hello, world.');"),
+            "'sha256-qznLcsROx4GACP2dm0UCKCzCG+HiZ1guq6ZZDob/Tng='"
+        );
+    }
+}
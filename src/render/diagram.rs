@@ -0,0 +1,208 @@
+use crate::config::RenderConfig;
+use maud::{html, Markup, PreEscaped, Render};
+use std::{
+    collections::hash_map::DefaultHasher,
+    fs,
+    hash::{Hash, Hasher},
+    io::{self, Write},
+    path::{Path, PathBuf},
+    process::{Command, Stdio},
+};
+
+pub const MERMAID_CONFIG: &str = "mermaid.initialize({ startOnLoad: true });";
+
+/// Kind of diagram described by the source of a fenced code block.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum DiagramKind {
+    Mermaid,
+    Graphviz,
+}
+
+impl DiagramKind {
+    pub fn from_language(language: &str) -> Option<Self> {
+        match language {
+            "mermaid" => Some(DiagramKind::Mermaid),
+            "dot" | "graphviz" => Some(DiagramKind::Graphviz),
+            _ => None,
+        }
+    }
+}
+
+/// Options for rendering diagram code blocks.
+pub struct Diagrams {
+    /// Path to the local copy of the mermaid script that pages with mermaid diagrams include.
+    pub mermaid_script: Option<PathBuf>,
+    /// Graphviz `dot` command used to render Graphviz diagrams as SVG.
+    pub dot_command: Option<PathBuf>,
+    /// Directory to cache the rendered SVG of Graphviz diagrams in.
+    pub cache_dir: Option<PathBuf>,
+}
+
+impl Diagrams {
+    /// Render the Graphviz diagram as inline SVG.
+    ///
+    /// Rendered diagrams are cached by the hash of their source, so `dot` is only invoked for new
+    /// or changed diagrams.
+    pub fn render_dot(&self, source: &str) -> io::Result<String> {
+        let dot_command = self.dot_command.as_ref().ok_or_else(|| {
+            io::Error::new(
+                io::ErrorKind::NotFound,
+                "rendering graphviz diagrams is disabled",
+            )
+        })?;
+
+        let cache_path = self
+            .cache_dir
+            .as_ref()
+            .map(|dir| dot_cache_path(dir, source));
+        if let Some(svg) = cache_path
+            .as_ref()
+            .and_then(|path| fs::read_to_string(path).ok())
+        {
+            return Ok(svg);
+        }
+
+        let svg = run_dot(dot_command, source)?;
+        if let Some(path) = cache_path {
+            fs::create_dir_all(path.parent().unwrap())
+                .and_then(|_| fs::write(&path, &svg))
+                .unwrap_or_else(|e| {
+                    eprintln!("failed to cache diagram at {}: {}", path.display(), e)
+                });
+        }
+        Ok(svg)
+    }
+}
+
+impl From<&RenderConfig> for Diagrams {
+    fn from(config: &RenderConfig) -> Self {
+        Self {
+            mermaid_script: config.mermaid_script.clone().filter(|path| path.exists()),
+            dot_command: config.dot_command.clone(),
+            cache_dir: config.cache_dir.as_ref().map(|dir| dir.join("diagrams")),
+        }
+    }
+}
+
+fn run_dot(dot_command: &Path, source: &str) -> io::Result<String> {
+    let mut child = Command::new(dot_command)
+        .arg("-Tsvg")
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .map_err(|e| {
+            io::Error::new(
+                e.kind(),
+                format!("failed to run {}: {}", dot_command.display(), e),
+            )
+        })?;
+    child.stdin.take().unwrap().write_all(source.as_bytes())?;
+
+    let output = child.wait_with_output()?;
+    if !output.status.success() {
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidData,
+            format!(
+                "{} failed: {}",
+                dot_command.display(),
+                stderr.lines().next().unwrap_or_default()
+            ),
+        ));
+    }
+
+    // Drop the XML declaration and doctype so the SVG can be inlined into the page.
+    let svg = String::from_utf8_lossy(&output.stdout);
+    let start = svg.find("<svg").unwrap_or(0);
+    Ok(svg[start..].to_string())
+}
+
+fn dot_cache_path(cache_dir: &Path, source: &str) -> PathBuf {
+    let mut hasher = DefaultHasher::new();
+    source.hash(&mut hasher);
+    cache_dir.join(format!("{:016x}.svg", hasher.finish()))
+}
+
+/// Container for a mermaid diagram which is rendered in the browser by the mermaid script.
+pub struct MermaidDiagram<'a>(pub &'a str);
+
+impl<'a> Render for MermaidDiagram<'a> {
+    fn render(&self) -> Markup {
+        html! { div class="diagram mermaid" { (self.0) } }
+    }
+}
+
+/// Graphviz diagram rendered as inline SVG.
+pub struct GraphvizDiagram<'a>(pub &'a str);
+
+impl<'a> Render for GraphvizDiagram<'a> {
+    fn render(&self) -> Markup {
+        html! { div class="diagram graphviz" { (PreEscaped(self.0)) } }
+    }
+}
+
+/// Scripts to render the mermaid diagrams on a page.
+pub struct MermaidScript<'a>(pub &'a Path);
+
+impl<'a> Render for MermaidScript<'a> {
+    fn render(&self) -> Markup {
+        html! {
+            script type="text/javascript" src=(self.0.display()) {}
+            script type="text/javascript" { (PreEscaped(MERMAID_CONFIG)) }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_util::temp_dir;
+
+    #[test]
+    fn diagram_kind_from_language() {
+        assert_eq!(
+            DiagramKind::from_language("mermaid"),
+            Some(DiagramKind::Mermaid)
+        );
+        assert_eq!(
+            DiagramKind::from_language("dot"),
+            Some(DiagramKind::Graphviz)
+        );
+        assert_eq!(DiagramKind::from_language("rust"), None);
+    }
+
+    #[test]
+    fn mermaid_source_is_escaped() {
+        let html = MermaidDiagram("graph TD; A-->B").render().into_string();
+        assert_eq!(
+            html,
+            "<div class=\"diagram mermaid\">graph TD; A--&gt;B</div>"
+        );
+    }
+
+    #[test]
+    fn render_dot_missing_command() {
+        let diagrams = Diagrams {
+            mermaid_script: None,
+            dot_command: Some(PathBuf::from("/nonexistent/dot")),
+            cache_dir: None,
+        };
+        let err = diagrams.render_dot("digraph { a -> b }").unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::NotFound);
+    }
+
+    #[test]
+    fn render_dot_from_cache() {
+        let cache_dir = temp_dir("diagram-cache");
+        let source = "digraph { a -> b }";
+        fs::write(dot_cache_path(&cache_dir, source), "<svg>cached</svg>").unwrap();
+
+        let diagrams = Diagrams {
+            mermaid_script: None,
+            dot_command: Some(PathBuf::from("/nonexistent/dot")),
+            cache_dir: Some(cache_dir),
+        };
+        assert_eq!(diagrams.render_dot(source).unwrap(), "<svg>cached</svg>");
+    }
+}
@@ -0,0 +1,212 @@
+use crate::render::{code::CodeInfo, source::SourceFormat};
+use std::{
+    ops::RangeInclusive,
+    path::{Path, PathBuf},
+};
+
+/// Name of the file a paragraph embeds with `![[name]]`, if it's a file that's embedded when the
+/// note is rendered. Embedded notes are transcluded before the note is rendered instead.
+pub fn embedded_file(paragraph: &str) -> Option<&str> {
+    let name = paragraph.trim().strip_prefix("![[")?.strip_suffix("]]")?;
+    let name = name.split(['|', '#']).next().unwrap_or_default().trim();
+    if name.is_empty() || name.contains("]]") || name.contains('\n') {
+        return None;
+    }
+    match Path::new(name).extension() {
+        Some(_) if SourceFormat::from_path(Path::new(name)).is_none() => Some(name),
+        _ => None,
+    }
+}
+
+/// Code from another file, included with a fenced code block such as
+/// ```` ```include path=script.py lines=10-30 ````.
+///
+/// The language is the extension of the file unless there's a `lang` attribute, and the title is
+/// the path unless there's a `title`. Other attributes are those of any code block, where line
+/// numbers and highlighted lines are the lines of the file.
+#[derive(Debug, PartialEq)]
+pub struct Include {
+    pub path: String,
+    /// Lines of the file that are included, or all of them if `None`.
+    pub lines: Option<RangeInclusive<usize>>,
+    pub info: CodeInfo,
+}
+
+impl Include {
+    /// Include described by the info string of a code block, if it's an `include` block with a
+    /// path.
+    pub fn parse(info: &str) -> Option<Result<Self, String>> {
+        let mut info = CodeInfo::parse(info);
+        if info.language != "include" {
+            return None;
+        }
+        info.language = String::new();
+
+        let mut path = None;
+        let mut lines = None;
+        let mut extra = vec![];
+        for (key, value) in info.extra.drain(..) {
+            match (key.as_str(), value) {
+                ("path", Some(value)) => path = Some(value),
+                ("lines", Some(value)) => lines = Some(value),
+                ("lang", Some(value)) => info.language = value,
+                (_, value) => extra.push((key, value)),
+            }
+        }
+        info.extra = extra;
+
+        let path = match path {
+            Some(path) if !path.is_empty() => path,
+            _ => return Some(Err(String::from("include block has no `path`"))),
+        };
+        let lines = match lines.as_deref().map(parse_line_range) {
+            Some(Some(lines)) => Some(lines),
+            Some(None) => {
+                return Some(Err(format!(
+                    "invalid lines `{}` in include block",
+                    lines.unwrap_or_default()
+                )))
+            }
+            None => None,
+        };
+        if info.language.is_empty() {
+            info.language = Path::new(&path)
+                .extension()
+                .unwrap_or_default()
+                .to_string_lossy()
+                .to_string();
+        }
+        if info.title.is_none() {
+            info.title = Some(path.clone());
+        }
+        if let Some(ref lines) = lines {
+            info.start_line = Some(*lines.start());
+        }
+        Some(Ok(Self { path, lines, info }))
+    }
+
+    /// Included lines of the file's contents, with an error if the file doesn't have them.
+    pub fn select<'a>(&self, contents: &'a str) -> Result<&'a str, String> {
+        let lines = match self.lines {
+            Some(ref lines) => lines,
+            None => return Ok(contents),
+        };
+        let mut offsets = contents
+            .split_inclusive('\n')
+            .scan(0, |offset, line| {
+                let start = *offset;
+                *offset += line.len();
+                Some((start, *offset))
+            })
+            .skip(lines.start() - 1);
+        let start = match offsets.next() {
+            Some((start, end)) if *lines.end() == *lines.start() => {
+                return Ok(&contents[start..end])
+            }
+            Some((start, _)) => start,
+            None => {
+                return Err(format!(
+                    "{} has fewer than {} lines",
+                    self.path,
+                    lines.start()
+                ))
+            }
+        };
+        let end = offsets
+            .take(lines.end() - lines.start())
+            .last()
+            .map_or(contents.len(), |(_, end)| end);
+        Ok(&contents[start..end])
+    }
+}
+
+/// Lines such as `10-30`, or `10` for a single line. Lines are numbered from 1.
+fn parse_line_range(lines: &str) -> Option<RangeInclusive<usize>> {
+    let mut bounds = lines.trim().splitn(2, '-').map(str::parse::<usize>);
+    match (bounds.next(), bounds.next()) {
+        (Some(Ok(start)), None) if start > 0 => Some(start..=start),
+        (Some(Ok(start)), Some(Ok(end))) if start > 0 && start <= end => Some(start..=end),
+        _ => None,
+    }
+}
+
+/// Path of an embedded or included file, relative to the directory of the note.
+pub fn resolve(name: &str, dir: Option<&Path>) -> PathBuf {
+    match dir {
+        Some(dir) => dir.join(name),
+        None => PathBuf::from(name),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn embedded_file_names() {
+        assert_eq!(embedded_file("![[data.csv]]\n"), Some("data.csv"));
+        assert_eq!(
+            embedded_file("![[sub/run.py|The script]]"),
+            Some("sub/run.py")
+        );
+        assert_eq!(embedded_file("![[note]]"), None);
+        assert_eq!(embedded_file("![[note.md]]"), None);
+        assert_eq!(embedded_file("see ![[data.csv]]"), None);
+        assert_eq!(embedded_file("![[a.csv]] and ![[b.csv]]"), None);
+    }
+
+    #[test]
+    fn parse_include() {
+        assert_eq!(Include::parse("rust linenos"), None);
+
+        let include = Include::parse("include path=\"sub/run.py\" lines=10-30 linenos")
+            .unwrap()
+            .unwrap();
+        assert_eq!(include.path, "sub/run.py");
+        assert_eq!(include.lines, Some(10..=30));
+        assert_eq!(include.info.language, "py");
+        assert_eq!(include.info.title.as_deref(), Some("sub/run.py"));
+        assert_eq!(include.info.start_line, Some(10));
+        assert!(include.info.line_numbers);
+
+        let include = Include::parse("include path=Makefile lang=make title=Build")
+            .unwrap()
+            .unwrap();
+        assert_eq!(include.info.language, "make");
+        assert_eq!(include.info.title.as_deref(), Some("Build"));
+
+        assert!(Include::parse("include lines=1-2").unwrap().is_err());
+        assert!(Include::parse("include path=a.py lines=3-1")
+            .unwrap()
+            .is_err());
+    }
+
+    #[test]
+    fn select_lines() {
+        let include = |info: &str| Include::parse(info).unwrap().unwrap();
+        let contents = "one\ntwo\nthree\nfour";
+        assert_eq!(
+            include("include path=a").select(contents).unwrap(),
+            contents
+        );
+        assert_eq!(
+            include("include path=a lines=2-3")
+                .select(contents)
+                .unwrap(),
+            "two\nthree\n"
+        );
+        assert_eq!(
+            include("include path=a lines=3-9")
+                .select(contents)
+                .unwrap(),
+            "three\nfour"
+        );
+        assert_eq!(
+            include("include path=a lines=2").select(contents).unwrap(),
+            "two\n"
+        );
+        assert!(include("include path=a lines=5-6")
+            .select(contents)
+            .is_err());
+    }
+}
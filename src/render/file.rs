@@ -0,0 +1,410 @@
+use crate::{
+    render::{
+        code::{CodeBlock, CodeInfo},
+        front_matter::FrontMatter,
+        index::format_size,
+        mathjax::MathjaxPolicy,
+        nav::{self, PageLink, Siblings},
+        pdf::PdfInfo,
+        template::{PageKind, Template},
+    },
+    sync::{
+        object::{DirObject, RenderedFileObject},
+        SyncOpts,
+    },
+};
+use maud::{html, Markup, PreEscaped, Render};
+use std::{
+    collections::HashMap,
+    fs, io,
+    path::{Path, PathBuf},
+    str::FromStr,
+};
+
+/// How a file that isn't a note is rendered as a page of its own.
+#[derive(Debug, Clone, Copy, PartialEq, Hash)]
+pub enum FileRenderer {
+    /// Preformatted text.
+    Text,
+    /// Code highlighted as the language of its extension.
+    Code,
+    /// Sortable table of comma separated values.
+    Csv,
+    /// Sortable table of tab separated values.
+    Tsv,
+    /// Raster image, which also gets a thumbnail for the gallery of its index page.
+    Image,
+    /// PDF in the browser's viewer, along with its title and page count.
+    Pdf,
+}
+
+impl FromStr for FileRenderer {
+    type Err = io::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "text" => Ok(FileRenderer::Text),
+            "code" => Ok(FileRenderer::Code),
+            "csv" => Ok(FileRenderer::Csv),
+            "tsv" => Ok(FileRenderer::Tsv),
+            "image" => Ok(FileRenderer::Image),
+            "pdf" => Ok(FileRenderer::Pdf),
+            _ => Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                format!("unknown file renderer `{}`", s),
+            )),
+        }
+    }
+}
+
+/// Renderer for the file at `path` by its extension, if it's rendered as a page.
+pub fn renderer_for(
+    path: &Path,
+    renderers: &HashMap<String, FileRenderer>,
+) -> Option<FileRenderer> {
+    let extension = path.extension()?.to_str()?.to_lowercase();
+    renderers.get(&extension).copied()
+}
+
+/// Script that sorts the rows of a sortable table by the column whose header is clicked.
+///
+/// Columns where every value is a number are sorted numerically. Clicking the same header again
+/// reverses the order.
+pub const TABLE_SORT_SCRIPT: &str = r#"
+    (function () {
+        var tables = document.querySelectorAll("table.sortable");
+        for (var t = 0; t < tables.length; t++) {
+            var table = tables[t];
+            var headers = table.querySelectorAll("thead th");
+            for (var h = 0; h < headers.length; h++) {
+                headers[h].addEventListener("click", sort.bind(null, table, h));
+            }
+        }
+
+        function sort(table, column, event) {
+            var header = event.currentTarget;
+            var ascending = header.getAttribute("aria-sort") !== "ascending";
+            var body = table.tBodies[0];
+            var rows = Array.prototype.slice.call(body.rows);
+            var values = rows.map(function (row) {
+                var cell = row.cells[column];
+                return cell ? cell.textContent.trim() : "";
+            });
+            var numeric = values.every(function (value) {
+                return value === "" || !isNaN(Number(value));
+            });
+            var order = rows.map(function (row, i) { return i; });
+            order.sort(function (a, b) {
+                var x = values[a], y = values[b];
+                var cmp = numeric ? Number(x) - Number(y) : x.localeCompare(y);
+                return ascending ? cmp : -cmp;
+            });
+            var headers = table.querySelectorAll("thead th");
+            for (var h = 0; h < headers.length; h++) {
+                headers[h].removeAttribute("aria-sort");
+            }
+            header.setAttribute("aria-sort", ascending ? "ascending" : "descending");
+            order.forEach(function (i) { body.appendChild(rows[i]); });
+        }
+    })();
+"#;
+
+/// Page of a file that isn't a note, such as a text, code, CSV or PDF file.
+pub struct FilePage<'a> {
+    opts: &'a SyncOpts,
+    file: &'a RenderedFileObject,
+    /// File the page links to as the raw file, which is its copy in the render root if it has
+    /// one.
+    raw: PathBuf,
+    title: Option<String>,
+    front_matter: FrontMatter,
+    breadcrumbs: Vec<PageLink>,
+    siblings: Siblings,
+    /// Contents of text files, with any invalid UTF-8 replaced.
+    content: String,
+    pdf: Option<PdfInfo>,
+}
+
+impl<'a> FilePage<'a> {
+    /// Page for the file in `dir`.
+    pub fn new(
+        opts: &'a SyncOpts,
+        dir: &DirObject,
+        file: &'a RenderedFileObject,
+    ) -> io::Result<Self> {
+        let mut content = String::new();
+        let mut pdf = None;
+        match file.renderer {
+            FileRenderer::Image => {}
+            FileRenderer::Pdf => pdf = Some(PdfInfo::parse(&fs::read(&file.path)?)),
+            _ => content = String::from_utf8_lossy(&fs::read(&file.path)?).into_owned(),
+        }
+        let title = pdf
+            .as_ref()
+            .and_then(|pdf| pdf.title.clone())
+            .unwrap_or_else(|| file_name(&file.path));
+        let breadcrumbs = nav::breadcrumbs(
+            &opts.src_root,
+            &opts.dst_root,
+            &dir.path,
+            &opts.links,
+            Some(&file.render_path),
+        );
+        Ok(Self {
+            opts,
+            file,
+            raw: opts
+                .copy_of(dir, &file.path)
+                .unwrap_or_else(|| file.path.clone()),
+            title: Some(title),
+            front_matter: FrontMatter::default(),
+            breadcrumbs,
+            siblings: Siblings::default(),
+            content,
+            pdf,
+        })
+    }
+
+    pub fn render_path(&self) -> &Path {
+        &self.file.render_path
+    }
+
+    pub fn template(&self) -> Template<'_> {
+        Template {
+            kind: PageKind::Note,
+            content: self.render(),
+            title: &self.title,
+            toc: &[],
+            front_matter: &self.front_matter,
+            tags: &[],
+            breadcrumbs: &self.breadcrumbs,
+            siblings: &self.siblings,
+            stylesheet: &self.opts.stylesheet,
+            syntax_stylesheet: match self.file.renderer {
+                FileRenderer::Code => &self.opts.syntax_stylesheet,
+                _ => &None,
+            },
+            mermaid_script: None,
+            mathjax_policy: &MathjaxPolicy::Never,
+            html_policy: &self.opts.html_policy,
+            links: &self.opts.links,
+            path: Some(&self.file.render_path),
+            search_page: self.opts.search_page.as_deref(),
+            table_sort: self.is_table(),
+        }
+    }
+
+    fn content_size(&self) -> u64 {
+        self.file.path.metadata().map_or(0, |meta| meta.len())
+    }
+
+    fn is_table(&self) -> bool {
+        matches!(self.file.renderer, FileRenderer::Csv | FileRenderer::Tsv)
+    }
+
+    fn render_content(&self, raw: &str) -> Markup {
+        match self.file.renderer {
+            FileRenderer::Text => html! { pre class="file-text" { (self.content) } },
+            FileRenderer::Code => {
+                let language = self
+                    .file
+                    .path
+                    .extension()
+                    .unwrap_or_default()
+                    .to_string_lossy()
+                    .to_string();
+                let info = CodeInfo {
+                    language,
+                    line_numbers: true,
+                    ..Default::default()
+                };
+                // Code with an unknown extension has its syntax detected from its first line.
+                let mut code_block = CodeBlock::with_info(&self.opts.syntax_highlighter, info)
+                    .or_else(|_| {
+                        let info = CodeInfo {
+                            line_numbers: true,
+                            ..Default::default()
+                        };
+                        CodeBlock::with_info(&self.opts.syntax_highlighter, info)
+                    })
+                    .unwrap();
+                code_block.push(&self.content);
+                code_block.render()
+            }
+            FileRenderer::Csv => delimited_table(&self.content, ','),
+            FileRenderer::Tsv => delimited_table(&self.content, '\t'),
+            FileRenderer::Image => html! {
+                img class="file-image" src=(raw) alt=(file_name(&self.file.path));
+            },
+            FileRenderer::Pdf => html! {
+                @if let Some(ref pdf) = self.pdf {
+                    dl class="file-details" {
+                        @if let Some(ref author) = pdf.author {
+                            dt { "Author" }
+                            dd { (author) }
+                        }
+                        dt { "Pages" }
+                        dd { (pdf.pages) }
+                        dt { "Size" }
+                        dd { (format_size(self.content_size())) }
+                    }
+                }
+                iframe class="file-pdf" src=(raw) title=(file_name(&self.file.path)) {}
+            },
+        }
+    }
+}
+
+impl<'a> Render for FilePage<'a> {
+    fn render(&self) -> Markup {
+        let raw = self
+            .opts
+            .links
+            .href(Some(&self.file.render_path), &self.raw);
+        html! {
+            h1 { (self.title.as_deref().unwrap_or_default()) }
+            p class="file-raw" { a href=(raw) { "Raw file" } }
+            (self.render_content(&raw))
+            @if self.is_table() {
+                script type="text/javascript" { (PreEscaped(TABLE_SORT_SCRIPT)) }
+            }
+        }
+    }
+}
+
+/// Sortable table of delimiter separated values, where the first row is the header.
+pub fn delimited_table(text: &str, delimiter: char) -> Markup {
+    Table(parse_delimited(text, delimiter)).render()
+}
+
+/// Sortable table of rows, where the first row is the header.
+struct Table(Vec<Vec<String>>);
+
+impl Render for Table {
+    fn render(&self) -> Markup {
+        let mut rows = self.0.iter();
+        let header = rows.next();
+        html! {
+            table class="sortable" {
+                @if let Some(header) = header {
+                    thead {
+                        tr {
+                            @for cell in header {
+                                th { (cell) }
+                            }
+                        }
+                    }
+                }
+                tbody {
+                    @for row in rows {
+                        tr {
+                            @for cell in row {
+                                td { (cell) }
+                            }
+                        }
+                    }
+                }
+            }
+        }
+    }
+}
+
+/// Rows of delimiter separated values, skipping blank lines.
+///
+/// Values can be double quoted to include the delimiter, line breaks, or `""` for a quote.
+fn parse_delimited(text: &str, delimiter: char) -> Vec<Vec<String>> {
+    let mut rows = vec![];
+    let mut row = vec![];
+    let mut value = String::new();
+    let mut in_quotes = false;
+    let mut chars = text.chars().peekable();
+
+    while let Some(c) = chars.next() {
+        match c {
+            '"' if in_quotes && chars.peek() == Some(&'"') => {
+                chars.next();
+                value.push('"');
+            }
+            '"' if in_quotes => in_quotes = false,
+            '"' if value.is_empty() => in_quotes = true,
+            c if in_quotes => value.push(c),
+            c if c == delimiter => row.push(std::mem::take(&mut value)),
+            '\r' if chars.peek() == Some(&'\n') => {}
+            '\n' | '\r' => {
+                row.push(std::mem::take(&mut value));
+                if row.len() > 1 || !row[0].is_empty() {
+                    rows.push(std::mem::take(&mut row));
+                } else {
+                    row.clear();
+                }
+            }
+            c => value.push(c),
+        }
+    }
+    if !value.is_empty() || !row.is_empty() {
+        row.push(value);
+        rows.push(row);
+    }
+    rows
+}
+
+fn file_name(path: &Path) -> String {
+    path.file_name()
+        .map(PathBuf::from)
+        .unwrap_or_default()
+        .display()
+        .to_string()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn renderer_by_extension() {
+        let mut renderers = HashMap::new();
+        renderers.insert(String::from("csv"), FileRenderer::Csv);
+        renderers.insert(String::from("rs"), FileRenderer::Code);
+        assert_eq!(
+            renderer_for(Path::new("/notes/data.CSV"), &renderers),
+            Some(FileRenderer::Csv)
+        );
+        assert_eq!(
+            renderer_for(Path::new("/notes/main.rs"), &renderers),
+            Some(FileRenderer::Code)
+        );
+        assert_eq!(renderer_for(Path::new("/notes/pic.png"), &renderers), None);
+        assert_eq!(renderer_for(Path::new("/notes/Makefile"), &renderers), None);
+        assert_eq!("tsv".parse::<FileRenderer>().unwrap(), FileRenderer::Tsv);
+        assert!("zip".parse::<FileRenderer>().is_err());
+    }
+
+    #[test]
+    fn delimited_values() {
+        assert_eq!(
+            parse_delimited(
+                "name,note\r\na,\"x, y\"\n\nb,\"say \"\"hi\"\"\nthere\"",
+                ','
+            ),
+            vec![
+                vec!["name", "note"],
+                vec!["a", "x, y"],
+                vec!["b", "say \"hi\"\nthere"],
+            ]
+        );
+        assert_eq!(
+            parse_delimited("a\tb\n1\t\n", '\t'),
+            vec![vec!["a", "b"], vec!["1", ""]]
+        );
+    }
+
+    #[test]
+    fn table_with_header() {
+        let table = Table(parse_delimited("n,x\n1,<b>", ','));
+        assert_eq!(
+            table.render().into_string(),
+            "<table class=\"sortable\"><thead><tr><th>n</th><th>x</th></tr></thead>\
+             <tbody><tr><td>1</td><td>&lt;b&gt;</td></tr></tbody></table>"
+        );
+    }
+}
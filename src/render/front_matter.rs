@@ -0,0 +1,78 @@
+use serde::Serialize;
+use serde_json::{Map, Value};
+
+const DELIMITER: &str = "---";
+
+/// Metadata from the YAML block at the start of a note, delimited by `---` lines.
+#[derive(Debug, Default, Clone, PartialEq, Serialize)]
+pub struct FrontMatter(pub Map<String, Value>);
+
+impl FrontMatter {
+    /// Split a note into its front matter and markdown body.
+    ///
+    /// Returns the YAML source of the front matter, if there is any, and the offset of the body
+    /// in the note.
+    pub fn split(note: &str) -> (Option<&str>, usize) {
+        let mut lines = note.split_inclusive('\n');
+        let start = match lines.next() {
+            Some(line) if line.trim_end() == DELIMITER => line.len(),
+            _ => return (None, 0),
+        };
+
+        let mut end = start;
+        for line in lines {
+            if line.trim_end() == DELIMITER {
+                return (Some(&note[start..end]), end + line.len());
+            }
+            end += line.len();
+        }
+        (None, 0)
+    }
+
+    /// Parse the YAML source of the front matter, which must be a mapping.
+    pub fn parse(yaml: &str) -> Result<Self, String> {
+        if yaml.trim().is_empty() {
+            return Ok(FrontMatter::default());
+        }
+        match serde_yaml::from_str(yaml) {
+            Ok(Value::Object(map)) => Ok(FrontMatter(map)),
+            Ok(_) => Err(String::from("front matter should be a mapping")),
+            Err(e) => Err(format!("invalid front matter: {}", e)),
+        }
+    }
+
+    pub fn get(&self, key: &str) -> Option<&Value> {
+        self.0.get(key)
+    }
+
+    pub fn title(&self) -> Option<&str> {
+        self.get("title").and_then(Value::as_str)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn split_front_matter() {
+        let note = "---\ntitle: Note\n---\n# Header\n";
+        let (yaml, body_start) = FrontMatter::split(note);
+        assert_eq!(yaml, Some("title: Note\n"));
+        assert_eq!(&note[body_start..], "# Header\n");
+    }
+
+    #[test]
+    fn split_without_front_matter() {
+        assert_eq!(FrontMatter::split("# Header\n---\n"), (None, 0));
+        assert_eq!(FrontMatter::split("---\nnot closed\n"), (None, 0));
+    }
+
+    #[test]
+    fn parse_title() {
+        let front_matter = FrontMatter::parse("title: Some note\ntags: [a, b]\n").unwrap();
+        assert_eq!(front_matter.title(), Some("Some note"));
+        assert!(FrontMatter::parse("- a list").is_err());
+        assert_eq!(FrontMatter::parse("").unwrap(), FrontMatter::default());
+    }
+}
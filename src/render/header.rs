@@ -1,5 +1,6 @@
 use crate::render::ToHtml;
 use regex::Regex;
+use serde::Serialize;
 
 pub struct HeaderStart<'a> {
     atx_level: i32,
@@ -11,7 +12,7 @@ impl<'a> HeaderStart<'a> {
         Self { atx_level, content }
     }
 
-    fn id(&self) -> String {
+    pub fn id(&self) -> String {
         let text = self
             .content
             .trim()
@@ -29,6 +30,24 @@ impl<'a> HeaderStart<'a> {
     }
 }
 
+/// Header in a note's table of contents.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct TocEntry {
+    pub level: i32,
+    pub id: String,
+    pub title: String,
+}
+
+impl<'a> From<&HeaderStart<'a>> for TocEntry {
+    fn from(header: &HeaderStart<'a>) -> Self {
+        Self {
+            level: header.atx_level,
+            id: header.id(),
+            title: header.content.trim().to_string(),
+        }
+    }
+}
+
 impl<'a> ToHtml for HeaderStart<'a> {
     fn to_html(&self) -> String {
         let mut tag = format!("h{}", self.atx_level);
@@ -1,123 +1,671 @@
 use crate::{
-    render::{mathjax::MathjaxPolicy, template::Template, ToHtml},
+    journal::Date,
+    render::{
+        file::FileRenderer,
+        front_matter::FrontMatter,
+        mathjax::MathjaxPolicy,
+        nav::{self, PageLink, Siblings},
+        source::SourceFormat,
+        template::{PageKind, Template},
+        Rendered,
+    },
     sync::{
-        object::{DirObject, Object},
+        object::{DirObject, Object, SourceFileObject},
         SyncOpts,
     },
 };
-use maud::{html, Markup, Render};
+use maud::{html, Markup, PreEscaped, Render};
+use pulldown_cmark::{Event, Tag};
+use serde_json::Value;
 use std::{
-    cmp::{Ord, Ordering, PartialOrd},
+    cmp::{Ordering, Reverse},
     io,
     path::{Path, PathBuf},
+    str::FromStr,
+    time::SystemTime,
 };
 
-const INDEX_FILE_NAME: &str = "index.html";
+pub const INDEX_FILE_NAME: &str = "index.html";
+
+/// Notes that are rendered at the top of the index page of their directory, instead of as a page
+/// of their own. The first one in a directory is used. Sphinx projects keep theirs in `index.rst`.
+pub const INTRO_FILE_NAMES: &[&str] = &[
+    "_index.md",
+    "README.md",
+    "_index.org",
+    "README.org",
+    "_index.rst",
+    "README.rst",
+    "index.rst",
+];
+
+/// Script for the filter box, which hides entries that don't contain the filter text.
+pub const INDEX_FILTER_SCRIPT: &str = r#"
+    (function () {
+        var filter = document.querySelector(".index-filter");
+        var entries = document.querySelectorAll(".index-entries li");
+        filter.addEventListener("input", function () {
+            var text = filter.value.toLowerCase();
+            for (var i = 0; i < entries.length; i++) {
+                var matches = entries[i].textContent.toLowerCase().indexOf(text) !== -1;
+                entries[i].hidden = !matches;
+            }
+        });
+    })();
+"#;
+
+/// Fewest images in a directory for its index page to show them in a gallery.
+const GALLERY_MIN_IMAGES: usize = 4;
+
+/// Order of the entries in each group of an index page.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum IndexSort {
+    /// By file name.
+    Name,
+    /// By modified time, newest first.
+    Modified,
+    /// By title, which is the file name for anything but notes.
+    Title,
+    /// By the `date` in the front matter of notes, newest first. Entries without a date are last.
+    Date,
+}
+
+impl FromStr for IndexSort {
+    type Err = io::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "name" => Ok(IndexSort::Name),
+            "mtime" => Ok(IndexSort::Modified),
+            "title" => Ok(IndexSort::Title),
+            "date" => Ok(IndexSort::Date),
+            _ => Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                format!("unknown index sort `{}`", s),
+            )),
+        }
+    }
+}
 
 pub struct Index<'a> {
     opts: &'a SyncOpts,
     dir: &'a DirObject,
+    render_path: PathBuf,
+    title: Option<String>,
+    front_matter: FrontMatter,
+    breadcrumbs: Vec<PageLink>,
+    /// Index pages aren't navigated between, so they don't have siblings.
+    siblings: Siblings,
+    /// Note in the directory that introduces it.
+    intro: Option<Rendered>,
+    entries: Vec<IndexEntry>,
+}
+
+/// Note that is rendered at the top of the index page of the directory, if it has one.
+pub fn intro_file(dir: &DirObject) -> Option<&SourceFileObject> {
+    INTRO_FILE_NAMES.iter().find_map(|name| {
+        dir.children.iter().find_map(|child| match child {
+            Object::SourceFile(file) if file.path.file_name() == Some(name.as_ref()) => Some(file),
+            _ => None,
+        })
+    })
+}
+
+/// Children of the directory in the order they're listed in its index page, without its intro.
+pub fn ordered_children(dir: &DirObject, sort: IndexSort) -> io::Result<Vec<&Object>> {
+    let intro_path = intro_file(dir).map(|file| file.path.as_path());
+    let mut entries = dir
+        .children
+        .iter()
+        .filter(|child| Some(child.path()) != intro_path)
+        .map(|child| IndexEntry::new(child).map(|entry| (entry, child)))
+        .collect::<io::Result<Vec<_>>>()?;
+    entries.sort_by(|(a, _), (b, _)| compare_entries(a, b, sort));
+    Ok(entries.into_iter().map(|(_, child)| child).collect())
 }
 
 impl<'a> Index<'a> {
-    pub fn new(opts: &'a SyncOpts, dir: &'a DirObject) -> io::Result<Self> {
-        Ok(Self { opts, dir })
-    }
+    /// Index of the directory, below its rendered intro note if it has one.
+    pub fn new(
+        opts: &'a SyncOpts,
+        dir: &'a DirObject,
+        intro: Option<Rendered>,
+    ) -> io::Result<Self> {
+        let render_path = dir.render_path.join(INDEX_FILE_NAME);
+        let breadcrumbs = nav::breadcrumbs(
+            &opts.src_root,
+            &opts.dst_root,
+            &dir.path,
+            &opts.links,
+            Some(&render_path),
+        );
+        let intro_path = intro_file(dir).map(|file| file.path.as_path());
+        let mut entries = dir
+            .children
+            .iter()
+            .filter(|child| Some(child.path()) != intro_path)
+            .map(|child| {
+                IndexEntry::new(child).map(|entry| {
+                    entry
+                        .with_copy(child, dir, opts)
+                        .with_thumbnail(child, dir, opts)
+                })
+            })
+            .collect::<io::Result<Vec<_>>>()?;
+        sort_entries(&mut entries, opts.index_sort);
 
-    pub fn render_path(&self) -> PathBuf {
-        self.dir.render_path.join(INDEX_FILE_NAME)
+        let mut index = Self {
+            opts,
+            dir,
+            render_path,
+            title: None,
+            front_matter: FrontMatter::default(),
+            breadcrumbs,
+            siblings: Siblings::default(),
+            intro: None,
+            entries,
+        };
+        index.title = Some(index.header());
+        if let Some(mut intro) = intro {
+            index.title = intro.title.take().or(index.title);
+            index.front_matter = std::mem::take(&mut intro.front_matter);
+            index.intro = Some(intro);
+        }
+        Ok(index)
     }
 
-    fn title(&self) -> String {
-        self.header()
+    pub fn render_path(&self) -> &Path {
+        &self.render_path
     }
 
     fn header(&self) -> String {
         let relative = self.dir.path.strip_prefix(&self.opts.src_root).unwrap();
         format!("Index: /{}", relative.display())
     }
+
+    /// Link to the index page of the parent directory, unless this is the root directory.
+    fn parent_index(&self) -> Option<String> {
+        if self.dir.path == self.opts.src_root {
+            return None;
+        }
+        let parent = self.dir.render_path.parent()?;
+        Some(self.href(&parent.join(INDEX_FILE_NAME)))
+    }
+
+    /// Link from the index page to the file at `target`.
+    fn href(&self, target: &Path) -> String {
+        self.opts.links.href(Some(&self.render_path), target)
+    }
+
+    fn render_entry(&self, entry: &IndexEntry) -> Markup {
+        let details = entry.details();
+        html! {
+            li class=(entry.kind.class()) {
+                a href=(self.href(&entry.target)) { (entry.name) }
+                @if !details.is_empty() {
+                    " "
+                    span class="index-details" { (details.join(" · ")) }
+                }
+            }
+        }
+    }
+
+    fn render_image(&self, entry: &IndexEntry, thumbnail: &Path) -> Markup {
+        html! {
+            li class="index-image" {
+                a href=(self.href(&entry.target)) {
+                    img src=(self.href(thumbnail)) alt=(entry.name) loading="lazy";
+                    span class="index-image-name" { (entry.name) }
+                }
+            }
+        }
+    }
 }
 
 impl<'a> Render for Index<'a> {
     fn render(&self) -> Markup {
-        let entries = self.dir.children.iter().map(IndexEntry::from);
+        // An intro starting with an H1 already has the title of the page.
+        let has_header = self
+            .intro
+            .as_ref()
+            .and_then(|intro| intro.toc.first())
+            .is_some_and(|entry| entry.level == 1);
+        let is_gallery = is_image_heavy(&self.entries);
+        let images = self
+            .entries
+            .iter()
+            .filter_map(|entry| Some((entry, entry.thumbnail.as_deref()?)))
+            .filter(|_| is_gallery);
         html! {
-            h1 { (self.header()) }
-            ul {
-                @for entry in entries {
-                    (entry)
+            @if !has_header {
+                h1 { (self.title.as_deref().unwrap_or_default()) }
+            }
+            @if let Some(ref intro) = self.intro {
+                div class="index-intro" { (PreEscaped(&intro.content)) }
+            }
+            input class="index-filter" type="search" placeholder="Filter" aria-label="Filter";
+            ul class="index-entries" {
+                @if let Some(parent_index) = self.parent_index() {
+                    li class="index-dir" { a href=(parent_index) { ".." } }
+                }
+                @for entry in &self.entries {
+                    @if !is_gallery || entry.thumbnail.is_none() {
+                        (self.render_entry(entry))
+                    }
                 }
             }
+            @if is_gallery {
+                ul class="index-entries index-gallery" {
+                    @for (entry, thumbnail) in images {
+                        (self.render_image(entry, thumbnail))
+                    }
+                }
+            }
+            script type="text/javascript" { (PreEscaped(INDEX_FILTER_SCRIPT)) }
         }
     }
 }
 
-impl<'a> ToHtml for Index<'a> {
-    fn to_html(&self) -> String {
+impl<'a> Index<'a> {
+    pub fn template(&self) -> Template<'_> {
         Template {
+            kind: PageKind::Index,
             content: self.render(),
-            title: &Some(self.title()),
+            title: &self.title,
+            toc: self.intro.as_ref().map_or(&[], |intro| &intro.toc),
+            front_matter: &self.front_matter,
+            tags: &[],
+            breadcrumbs: &self.breadcrumbs,
+            siblings: &self.siblings,
             stylesheet: &self.opts.stylesheet,
-            mathjax_policy: &MathjaxPolicy::Never,
+            syntax_stylesheet: match self.intro {
+                Some(_) => &self.opts.syntax_stylesheet,
+                None => &None,
+            },
+            mermaid_script: match self.intro {
+                Some(ref intro) if intro.has_mermaid => {
+                    self.opts.diagrams.mermaid_script.as_deref()
+                }
+                _ => None,
+            },
+            mathjax_policy: match self.intro {
+                Some(_) => &self.opts.mathjax_policy,
+                None => &MathjaxPolicy::Never,
+            },
+            html_policy: &self.opts.html_policy,
+            links: &self.opts.links,
+            path: Some(&self.render_path),
+            search_page: self.opts.search_page.as_deref(),
+            table_sort: self.intro.as_ref().is_some_and(|intro| intro.has_tables),
         }
-        .to_html()
     }
 }
 
-#[derive(Debug, Eq)]
-pub struct IndexEntry<'a> {
-    path: &'a Path,
-    render_path: Option<PathBuf>,
+/// Group of an index entry. Groups are listed in this order.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+enum EntryKind {
+    Dir,
+    Note,
+    File,
 }
 
-impl<'a> IndexEntry<'a> {
-    fn path(&'a self) -> &'a Path {
-        if let Some(path) = &self.render_path {
-            return path;
+impl EntryKind {
+    fn class(self) -> &'static str {
+        match self {
+            EntryKind::Dir => "index-dir",
+            EntryKind::Note => "index-note",
+            EntryKind::File => "index-file",
         }
-        self.path
     }
 }
 
-impl<'a> From<&'a Object> for IndexEntry<'a> {
-    // TODO: IndexEntry::from shouldn't need to clone fild.render_path.
-    fn from(object: &'a Object) -> Self {
+#[derive(Debug)]
+struct IndexEntry {
+    kind: EntryKind,
+    file_name: String,
+    /// Link text, which is the title of notes.
+    name: String,
+    /// Rendered page, or the file itself for files that aren't rendered.
+    target: PathBuf,
+    modified: Option<SystemTime>,
+    size: Option<u64>,
+    note: Option<NoteSummary>,
+    /// Thumbnail of images, shown in the gallery of image heavy directories.
+    thumbnail: Option<PathBuf>,
+}
+
+impl IndexEntry {
+    fn new(object: &Object) -> io::Result<Self> {
         let path = object.path();
-        let render_path = match object {
-            Object::Dir(dir) => Some(dir.render_path.join(INDEX_FILE_NAME)),
-            Object::SourceFile(file) => Some(file.render_path.clone()),
-            _ => None,
+        let file_name = path
+            .file_name()
+            .unwrap_or_default()
+            .to_string_lossy()
+            .to_string();
+        let metadata = path.metadata().ok();
+        let modified = metadata.as_ref().and_then(|meta| meta.modified().ok());
+        let size = metadata.as_ref().map(|meta| meta.len());
+
+        let entry = match object {
+            Object::Dir(dir) => Self {
+                kind: EntryKind::Dir,
+                name: format!("{}/", file_name),
+                file_name,
+                target: dir.render_path.join(INDEX_FILE_NAME),
+                modified,
+                size: None,
+                note: None,
+                thumbnail: None,
+            },
+            Object::SourceFile(file) => {
+                let note = NoteSummary::read(file)?;
+                Self {
+                    kind: EntryKind::Note,
+                    name: note.title.clone().unwrap_or_else(|| file_name.clone()),
+                    file_name,
+                    target: file.render_path.clone(),
+                    modified,
+                    size,
+                    note: Some(note),
+                    thumbnail: None,
+                }
+            }
+            Object::RenderedFile(file) => Self {
+                kind: EntryKind::File,
+                name: file_name.clone(),
+                file_name,
+                target: file.render_path.clone(),
+                modified,
+                size,
+                note: None,
+                thumbnail: None,
+            },
+            Object::File(_) | Object::Symlink(_) => Self {
+                kind: EntryKind::File,
+                name: file_name.clone(),
+                file_name,
+                target: path.to_path_buf(),
+                modified,
+                size,
+                note: None,
+                thumbnail: None,
+            },
         };
-        Self { path, render_path }
+        Ok(entry)
+    }
+
+    /// Entry that links to the copy of the file in the render root, for files in `dir` that
+    /// aren't rendered as pages.
+    fn with_copy(mut self, object: &Object, dir: &DirObject, opts: &SyncOpts) -> Self {
+        let path = object.path();
+        if self.kind == EntryKind::File && self.target == path {
+            if let Some(copy) = opts.copy_of(dir, path) {
+                self.target = copy;
+            }
+        }
+        self
+    }
+
+    /// Entry with the thumbnail of the object in `dir`, if it's an image.
+    fn with_thumbnail(mut self, object: &Object, dir: &DirObject, opts: &SyncOpts) -> Self {
+        if let Object::RenderedFile(file) = object {
+            if file.renderer == FileRenderer::Image {
+                let image = opts
+                    .copy_of(dir, &file.path)
+                    .unwrap_or_else(|| file.path.clone());
+                self.thumbnail = Some(opts.thumbnails.of(&image, &file.contents_hash));
+            }
+        }
+        self
+    }
+
+    fn date(&self) -> Option<&str> {
+        self.note.as_ref().and_then(|note| note.date.as_deref())
+    }
+
+    /// Modified date, word count and size, where they're known.
+    fn details(&self) -> Vec<String> {
+        let mut details = vec![];
+        if self.kind == EntryKind::Dir {
+            return details;
+        }
+        if let Some(modified) = self.modified {
+            details.push(format_date(modified));
+        }
+        if let Some(ref note) = self.note {
+            let plural = if note.words == 1 { "" } else { "s" };
+            details.push(format!("{} word{}", note.words, plural));
+        }
+        if let Some(size) = self.size {
+            details.push(format_size(size));
+        }
+        details
     }
 }
 
-impl<'a> Render for IndexEntry<'a> {
-    fn render(&self) -> Markup {
-        html! {
-            li {
-                a href=(self.path().display()) {
-                    (self.path.display())
+/// What's shown about a note in an index page.
+#[derive(Debug, Default, PartialEq)]
+struct NoteSummary {
+    /// Title from the front matter, or the first header if it's an H1.
+    title: Option<String>,
+    /// `date` from the front matter.
+    date: Option<String>,
+    words: usize,
+}
+
+impl NoteSummary {
+    fn read(file: &SourceFileObject) -> io::Result<Self> {
+        file.read_content()
+            .map(|source| Self::parse(&source, file.format()))
+    }
+
+    fn parse(source: &str, format: SourceFormat) -> Self {
+        let parsed = format.parse(source);
+        let front_matter = parsed.front_matter.unwrap_or_default();
+
+        let mut title = front_matter.title().map(String::from);
+        let mut words = 0;
+        let mut in_title = false;
+        for (i, (event, _)) in parsed.events.into_iter().enumerate() {
+            match event {
+                Event::Start(Tag::Header(1)) if i == 0 && title.is_none() => {
+                    in_title = true;
+                    title = Some(String::new());
+                }
+                Event::End(Tag::Header(_)) => in_title = false,
+                Event::Text(text) => {
+                    words += text.split_whitespace().count();
+                    if in_title {
+                        title.get_or_insert_with(String::new).push_str(&text);
+                    }
                 }
+                _ => {}
             }
         }
+
+        let date = front_matter.get("date").and_then(|date| match date {
+            Value::String(date) => Some(date.clone()),
+            Value::Number(date) => Some(date.to_string()),
+            _ => None,
+        });
+        Self { title, date, words }
     }
 }
 
-impl<'a> Ord for IndexEntry<'a> {
-    fn cmp(&self, other: &IndexEntry) -> Ordering {
-        self.path.cmp(&other.path)
-    }
+/// Whether images make up at least half of the notes and files in a directory, so its index page
+/// shows them in a gallery instead of listing them.
+fn is_image_heavy(entries: &[IndexEntry]) -> bool {
+    let files = entries
+        .iter()
+        .filter(|entry| entry.kind != EntryKind::Dir)
+        .count();
+    let images = entries
+        .iter()
+        .filter(|entry| entry.thumbnail.is_some())
+        .count();
+    images >= GALLERY_MIN_IMAGES && images * 2 >= files
+}
+
+/// Sort entries into directories, notes and other files, and each group by `sort`.
+fn sort_entries(entries: &mut [IndexEntry], sort: IndexSort) {
+    entries.sort_by(|a, b| compare_entries(a, b, sort));
+}
+
+fn compare_entries(a: &IndexEntry, b: &IndexEntry, sort: IndexSort) -> Ordering {
+    let by_name = || a.file_name.to_lowercase().cmp(&b.file_name.to_lowercase());
+    let order = match sort {
+        IndexSort::Name => Ordering::Equal,
+        IndexSort::Modified => Reverse(a.modified).cmp(&Reverse(b.modified)),
+        IndexSort::Title => a.name.to_lowercase().cmp(&b.name.to_lowercase()),
+        IndexSort::Date => match (a.date(), b.date()) {
+            (Some(a), Some(b)) => b.cmp(a),
+            (Some(_), None) => Ordering::Less,
+            (None, Some(_)) => Ordering::Greater,
+            (None, None) => Ordering::Equal,
+        },
+    };
+    a.kind.cmp(&b.kind).then(order).then_with(by_name)
 }
 
-impl<'a> PartialOrd for IndexEntry<'a> {
-    fn partial_cmp(&self, other: &IndexEntry) -> Option<Ordering> {
-        Some(self.cmp(other))
+/// Date of the time in UTC, as `YYYY-MM-DD`.
+pub fn format_date(time: SystemTime) -> String {
+    Date::from_time(time).to_string()
+}
+
+/// Size in bytes, in the largest unit that keeps it at least 1.
+pub fn format_size(size: u64) -> String {
+    const UNITS: &[&str] = &["KB", "MB", "GB"];
+    if size < 1024 {
+        return format!("{} B", size);
+    }
+    let mut size = size as f64 / 1024.0;
+    let mut unit = 0;
+    while size >= 1024.0 && unit + 1 < UNITS.len() {
+        size /= 1024.0;
+        unit += 1;
     }
+    format!("{:.1} {}", size, UNITS[unit])
 }
 
-impl<'a> PartialEq for IndexEntry<'a> {
-    fn eq(&self, other: &IndexEntry) -> bool {
-        self.path == other.path && self.render_path == other.render_path
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::{Duration, UNIX_EPOCH};
+
+    fn entry(kind: EntryKind, file_name: &str, name: &str, modified: u64) -> IndexEntry {
+        IndexEntry {
+            kind,
+            file_name: file_name.to_string(),
+            name: name.to_string(),
+            target: PathBuf::from(file_name),
+            modified: Some(UNIX_EPOCH + Duration::from_secs(modified)),
+            size: None,
+            note: None,
+            thumbnail: None,
+        }
+    }
+
+    fn file_names(entries: &[IndexEntry]) -> Vec<&str> {
+        entries
+            .iter()
+            .map(|entry| entry.file_name.as_str())
+            .collect()
+    }
+
+    #[test]
+    fn summary_of_note() {
+        let summary = NoteSummary::parse(
+            "# The *Title*\n\nSome `code` and words.\n\n## Part\n\nMore.",
+            SourceFormat::Markdown,
+        );
+        assert_eq!(summary.title.as_deref(), Some("The Title"));
+        assert_eq!(summary.words, 8);
+
+        let summary = NoteSummary::parse(
+            "---\ntitle: Meta\ndate: 2020-01-02\n---\n# Header\n",
+            SourceFormat::Markdown,
+        );
+        assert_eq!(summary.title.as_deref(), Some("Meta"));
+        assert_eq!(summary.date.as_deref(), Some("2020-01-02"));
+
+        assert_eq!(
+            NoteSummary::parse("Intro\n\n# Later", SourceFormat::Markdown).title,
+            None
+        );
+    }
+
+    #[test]
+    fn entries_grouped_then_sorted() {
+        let mut entries = vec![
+            entry(EntryKind::File, "a.png", "a.png", 3),
+            entry(EntryKind::Note, "b.md", "Zebra", 1),
+            entry(EntryKind::Dir, "z", "z/", 0),
+            entry(EntryKind::Note, "c.md", "Aardvark", 2),
+        ];
+        sort_entries(&mut entries, IndexSort::Name);
+        assert_eq!(file_names(&entries), vec!["z", "b.md", "c.md", "a.png"]);
+        sort_entries(&mut entries, IndexSort::Title);
+        assert_eq!(file_names(&entries), vec!["z", "c.md", "b.md", "a.png"]);
+        sort_entries(&mut entries, IndexSort::Modified);
+        assert_eq!(file_names(&entries), vec!["z", "c.md", "b.md", "a.png"]);
+    }
+
+    #[test]
+    fn gallery_of_image_heavy_dir() {
+        let image = |name: &str| IndexEntry {
+            thumbnail: Some(PathBuf::from(name)),
+            ..entry(EntryKind::File, name, name, 0)
+        };
+        let mut entries = vec![
+            entry(EntryKind::Dir, "a", "a/", 0),
+            entry(EntryKind::Dir, "b", "b/", 0),
+            entry(EntryKind::Note, "c.md", "c", 0),
+            image("d.png"),
+            image("e.png"),
+            image("f.jpg"),
+        ];
+        assert!(!is_image_heavy(&entries));
+        entries.push(image("g.gif"));
+        assert!(is_image_heavy(&entries));
+        entries.extend(vec![
+            entry(EntryKind::Note, "h.md", "h", 0),
+            entry(EntryKind::File, "i.txt", "i.txt", 0),
+            entry(EntryKind::File, "j.pdf", "j.pdf", 0),
+            entry(EntryKind::Note, "k.md", "k", 0),
+        ]);
+        assert!(!is_image_heavy(&entries));
+    }
+
+    #[test]
+    fn intro_of_dir() {
+        let mut dir = DirObject::from("/notes");
+        dir.extend(vec![
+            SourceFileObject::from("/notes/README.md").into(),
+            SourceFileObject::from("/notes/note.md").into(),
+        ]);
+        assert_eq!(
+            intro_file(&dir).map(|file| file.path.as_path()),
+            Some(Path::new("/notes/README.md"))
+        );
+
+        dir.extend(vec![SourceFileObject::from("/notes/_index.md").into()]);
+        assert_eq!(
+            intro_file(&dir).map(|file| file.path.as_path()),
+            Some(Path::new("/notes/_index.md"))
+        );
+    }
+
+    #[test]
+    fn dates() {
+        assert_eq!(format_date(UNIX_EPOCH), "1970-01-01");
+        let leap_day = UNIX_EPOCH + Duration::from_secs(951_782_400);
+        assert_eq!(format_date(leap_day), "2000-02-29");
+    }
+
+    #[test]
+    fn sizes() {
+        assert_eq!(format_size(10), "10 B");
+        assert_eq!(format_size(1536), "1.5 KB");
+        assert_eq!(format_size(3 * 1024 * 1024), "3.0 MB");
     }
 }
@@ -0,0 +1,334 @@
+use crate::render::{
+    index::{INDEX_FILE_NAME, INTRO_FILE_NAMES},
+    source::SourceFormat,
+};
+use std::{
+    io,
+    path::{Component, Path, PathBuf},
+    str::FromStr,
+};
+
+/// Extension that notes in the source tree are rendered with.
+const RENDERED_EXTENSION: &str = "html";
+
+/// How links to other files in the render output are written.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum LinkStyle {
+    /// Absolute file system paths, which only work where the notes were rendered.
+    Absolute,
+    /// Paths relative to the page with the link, so the render output can be moved or served.
+    Relative,
+}
+
+impl FromStr for LinkStyle {
+    type Err = io::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "absolute" => Ok(LinkStyle::Absolute),
+            "relative" => Ok(LinkStyle::Relative),
+            _ => Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                format!("unknown link style `{}`", s),
+            )),
+        }
+    }
+}
+
+/// Writes links from a rendered page to other files.
+#[derive(Debug, Clone)]
+pub struct Links {
+    pub style: LinkStyle,
+    /// Root of the render output, if the page is part of one.
+    pub root: Option<PathBuf>,
+    /// URL that the render root is published at. Links to files in the render root use this
+    /// prefix instead of a path.
+    pub base_url: Option<String>,
+}
+
+impl Links {
+    pub fn new(style: LinkStyle, root: Option<PathBuf>, base_url: Option<String>) -> Self {
+        Self {
+            style,
+            root,
+            base_url,
+        }
+    }
+
+    /// Whether links keep working when the render output is moved or published. Links between
+    /// notes are then rewritten to the rendered notes, and assets are copied into the render root.
+    pub fn is_portable(&self) -> bool {
+        self.style == LinkStyle::Relative || self.base_url.is_some()
+    }
+
+    /// Link from the page at `page` to the file at `target`.
+    ///
+    /// Links are absolute if the location of the page isn't known.
+    pub fn href(&self, page: Option<&Path>, target: &Path) -> String {
+        let published = self.base_url.as_ref().and_then(|base_url| {
+            let root = self.root.as_ref()?;
+            let path = target.strip_prefix(root).ok()?;
+            Some(format!(
+                "{}/{}",
+                base_url.trim_end_matches('/'),
+                encode_path(path)
+            ))
+        });
+        if let Some(url) = published {
+            return url;
+        }
+
+        match (self.style, page.and_then(Path::parent)) {
+            (LinkStyle::Relative, Some(page_dir)) => encode_path(&relative_path(page_dir, target)),
+            _ => target.display().to_string(),
+        }
+    }
+
+    /// Link from the page at `page` for a relative link in a note in `base_dir`, when the page
+    /// isn't in `base_dir` so the link wouldn't work as it is.
+    ///
+    /// Returns `None` for links that aren't relative paths, or don't need to change.
+    pub fn resolve_relative(
+        &self,
+        dest: &str,
+        base_dir: &Path,
+        page: Option<&Path>,
+    ) -> Option<String> {
+        if page.and_then(Path::parent) == Some(base_dir) {
+            return None;
+        }
+        let (path, suffix) = split_path(dest)?;
+        if path.starts_with('/') {
+            return None;
+        }
+        let target = normalize_path(&base_dir.join(decode_path(path)));
+        Some(format!("{}{}", self.href(page, &target), suffix))
+    }
+}
+
+impl Default for Links {
+    fn default() -> Self {
+        Links::new(LinkStyle::Absolute, None, None)
+    }
+}
+
+/// Path to `target` from the directory `from`, where both are absolute paths.
+pub fn relative_path(from: &Path, target: &Path) -> PathBuf {
+    let from: Vec<Component> = from.components().collect();
+    let target: Vec<Component> = target.components().collect();
+    let common = from.iter().zip(&target).take_while(|(a, b)| a == b).count();
+
+    let mut path = PathBuf::new();
+    for _ in common..from.len() {
+        path.push("..");
+    }
+    for component in &target[common..] {
+        path.push(component);
+    }
+    path
+}
+
+/// Rewrite a relative link to a note so that it links to the rendered note instead.
+///
+/// Returns `None` for links that aren't to notes, such as links to other sites or to anchors in
+/// the same note.
+pub fn rewrite_note_link(dest: &str) -> Option<String> {
+    let (path, suffix) = split_path(dest)?;
+    // Intros are rendered at the top of the index page of their directory.
+    let (dir, file_name) = path.split_at(path.rfind('/').map_or(0, |slash| slash + 1));
+    if INTRO_FILE_NAMES.contains(&file_name) {
+        return Some(format!("{}{}{}", dir, INDEX_FILE_NAME, suffix));
+    }
+    let stem = SourceFormat::ALL
+        .iter()
+        .find_map(|format| path.strip_suffix(format.extension())?.strip_suffix('.'))?;
+    Some(format!("{}.{}{}", stem, RENDERED_EXTENSION, suffix))
+}
+
+/// Split a link destination into its path and its query or fragment.
+///
+/// Returns `None` for destinations without a path, or with a scheme such as `https:`.
+fn split_path(dest: &str) -> Option<(&str, &str)> {
+    let path_end = dest.find(['?', '#']).unwrap_or(dest.len());
+    let (path, suffix) = dest.split_at(path_end);
+    let has_scheme = path
+        .find(':')
+        .is_some_and(|colon| !path[..colon].contains('/'));
+    if path.is_empty() || has_scheme {
+        return None;
+    }
+    Some((path, suffix))
+}
+
+/// Path without `.` components, and with `..` components removed along with the directory
+/// before them.
+fn normalize_path(path: &Path) -> PathBuf {
+    let mut normalized = PathBuf::new();
+    for component in path.components() {
+        match component {
+            Component::CurDir => {}
+            Component::ParentDir => {
+                normalized.pop();
+            }
+            component => normalized.push(component),
+        }
+    }
+    normalized
+}
+
+/// Decode the percent encoded path of a URL.
+pub fn decode_path(path: &str) -> String {
+    let bytes = path.as_bytes();
+    let mut decoded = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+    while i < bytes.len() {
+        let escape = bytes
+            .get(i + 1..i + 3)
+            .filter(|_| bytes[i] == b'%')
+            .and_then(|hex| u8::from_str_radix(std::str::from_utf8(hex).ok()?, 16).ok());
+        match escape {
+            Some(byte) => {
+                decoded.push(byte);
+                i += 3;
+            }
+            None => {
+                decoded.push(bytes[i]);
+                i += 1;
+            }
+        }
+    }
+    String::from_utf8_lossy(&decoded).into_owned()
+}
+
+/// Percent encode the path for use in a URL.
+fn encode_path(path: &Path) -> String {
+    let path = path.to_string_lossy();
+    let mut encoded = String::with_capacity(path.len());
+    for byte in path.bytes() {
+        match byte {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'.' | b'_' | b'~' | b'/' => {
+                encoded.push(byte as char)
+            }
+            _ => encoded.push_str(&format!("%{:02X}", byte)),
+        }
+    }
+    encoded
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn links(style: LinkStyle, base_url: Option<&str>) -> Links {
+        Links::new(
+            style,
+            Some(PathBuf::from("/rendered")),
+            base_url.map(String::from),
+        )
+    }
+
+    #[test]
+    fn relative_paths() {
+        assert_eq!(
+            relative_path(
+                Path::new("/rendered/a/b"),
+                Path::new("/rendered/index.html")
+            ),
+            PathBuf::from("../../index.html")
+        );
+        assert_eq!(
+            relative_path(Path::new("/rendered"), Path::new("/rendered/a/note.html")),
+            PathBuf::from("a/note.html")
+        );
+        assert_eq!(
+            relative_path(Path::new("/rendered/a"), Path::new("/rendered/a/note.html")),
+            PathBuf::from("note.html")
+        );
+    }
+
+    #[test]
+    fn href_styles() {
+        let page = Path::new("/rendered/a/note.html");
+        let target = Path::new("/rendered/b/my note.html");
+        assert_eq!(
+            links(LinkStyle::Absolute, None).href(Some(page), target),
+            "/rendered/b/my note.html"
+        );
+        assert_eq!(
+            links(LinkStyle::Relative, None).href(Some(page), target),
+            "../b/my%20note.html"
+        );
+        assert_eq!(
+            links(LinkStyle::Relative, None).href(None, target),
+            "/rendered/b/my note.html"
+        );
+    }
+
+    #[test]
+    fn href_with_base_url() {
+        let links = links(LinkStyle::Relative, Some("https://example.com/notes/"));
+        let page = Path::new("/rendered/a/note.html");
+        assert_eq!(
+            links.href(Some(page), Path::new("/rendered/style.css")),
+            "https://example.com/notes/style.css"
+        );
+        // Files outside of the render root can't be published.
+        assert_eq!(
+            links.href(Some(page), Path::new("/elsewhere/style.css")),
+            "../../elsewhere/style.css"
+        );
+    }
+
+    #[test]
+    fn rewrite_note_links() {
+        assert_eq!(
+            rewrite_note_link("../other.md#part"),
+            Some(String::from("../other.html#part"))
+        );
+        assert_eq!(
+            rewrite_note_link("note.md"),
+            Some(String::from("note.html"))
+        );
+        assert_eq!(
+            rewrite_note_link("notes/todo.org?x"),
+            Some(String::from("notes/todo.html?x"))
+        );
+        assert_eq!(
+            rewrite_note_link("sub/README.md#setup"),
+            Some(String::from("sub/index.html#setup"))
+        );
+        assert_eq!(
+            rewrite_note_link("_index.md"),
+            Some(String::from("index.html"))
+        );
+        assert_eq!(rewrite_note_link("https://example.com/readme.md"), None);
+        assert_eq!(rewrite_note_link("#part"), None);
+        assert_eq!(rewrite_note_link("image.png"), None);
+    }
+
+    #[test]
+    fn resolve_relative_links() {
+        let links = Links::default();
+        let base_dir = Path::new("/notes/a");
+        assert_eq!(
+            links.resolve_relative("../img/my%20pic.png#x", base_dir, None),
+            Some(String::from("/notes/img/my pic.png#x"))
+        );
+        assert_eq!(
+            links.resolve_relative("pic.png", base_dir, Some(Path::new("/notes/a/note.html"))),
+            None
+        );
+        assert_eq!(
+            links.resolve_relative("https://x.org", base_dir, None),
+            None
+        );
+        assert_eq!(links.resolve_relative("#part", base_dir, None), None);
+        assert_eq!(links.resolve_relative("/abs.png", base_dir, None), None);
+
+        let links = Links::new(LinkStyle::Relative, None, None);
+        assert_eq!(
+            links.resolve_relative("pic.png", base_dir, Some(Path::new("/out/note.html"))),
+            Some(String::from("../notes/a/pic.png"))
+        );
+    }
+}
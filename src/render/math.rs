@@ -0,0 +1,302 @@
+use crate::config::RenderConfig;
+use std::{
+    collections::hash_map::DefaultHasher,
+    fs,
+    hash::{Hash, Hasher},
+    io,
+    path::{Path, PathBuf},
+    process::Command,
+};
+
+/// Elements whose text isn't searched for math, as MathJax skips them too.
+const SKIPPED_ELEMENTS: &[&str] = &["code", "pre", "script", "style", "textarea", "title"];
+
+/// Renders TeX math in a page to SVG ahead of time, so the page doesn't need MathJax.
+///
+/// Math is delimited as MathJax is configured in [`MATHJAX_CONFIG`], with `$...$` for inline
+/// math, `$$...$$` for display math and `\$` for a dollar sign.
+///
+/// [`MATHJAX_CONFIG`]: crate::render::mathjax::MATHJAX_CONFIG
+pub struct MathRenderer {
+    /// Command that takes TeX as its argument, and `--inline` for inline math, and writes the
+    /// math as SVG, such as `tex2svg` from `mathjax-node-cli`.
+    pub command: Option<PathBuf>,
+    /// Directory to cache rendered math in.
+    pub cache_dir: Option<PathBuf>,
+}
+
+/// Page with its math rendered.
+pub struct PrerenderedMath {
+    pub html: String,
+    /// Math that couldn't be rendered, which is left for MathJax.
+    pub failed: usize,
+    pub errors: Vec<String>,
+}
+
+impl MathRenderer {
+    /// Render the math in the text of the HTML, outside of code and scripts.
+    pub fn prerender(&self, html: &str) -> PrerenderedMath {
+        let mut prerendered = PrerenderedMath {
+            html: String::with_capacity(html.len()),
+            failed: 0,
+            errors: vec![],
+        };
+        let mut skipped = vec![];
+        let mut rest = html;
+        while !rest.is_empty() {
+            if rest.starts_with('<') {
+                let end = rest.find('>').map_or(rest.len(), |i| i + 1);
+                let tag = &rest[..end];
+                track_skipped(tag, &mut skipped);
+                prerendered.html.push_str(tag);
+                rest = &rest[end..];
+                continue;
+            }
+
+            let end = rest.find('<').unwrap_or(rest.len());
+            let text = &rest[..end];
+            if skipped.is_empty() {
+                self.prerender_text(text, &mut prerendered);
+            } else {
+                prerendered.html.push_str(text);
+            }
+            rest = &rest[end..];
+        }
+        prerendered
+    }
+
+    fn prerender_text(&self, text: &str, prerendered: &mut PrerenderedMath) {
+        let mut rest = text;
+        while let Some((start, math)) = find_math(rest) {
+            prerendered.html.push_str(&rest[..start]);
+            let tex = unescape_html(math.tex);
+            match self.render_tex(&tex, math.display) {
+                Ok(svg) => {
+                    let class = if math.display {
+                        "math math-display"
+                    } else {
+                        "math"
+                    };
+                    prerendered
+                        .html
+                        .push_str(&format!("<span class=\"{}\">{}</span>", class, svg));
+                }
+                Err(e) => {
+                    prerendered.failed += 1;
+                    prerendered
+                        .errors
+                        .push(format!("failed to render math `{}`: {}", tex, e));
+                    prerendered.html.push_str(&rest[start..start + math.len]);
+                }
+            }
+            rest = &rest[start + math.len..];
+        }
+        prerendered.html.push_str(rest);
+    }
+
+    pub fn render_tex(&self, tex: &str, display: bool) -> io::Result<String> {
+        let command = self
+            .command
+            .as_ref()
+            .ok_or_else(|| io::Error::new(io::ErrorKind::NotFound, "rendering math is disabled"))?;
+
+        let cache_path = self
+            .cache_dir
+            .as_ref()
+            .map(|dir| math_cache_path(dir, tex, display));
+        if let Some(svg) = cache_path
+            .as_ref()
+            .and_then(|path| fs::read_to_string(path).ok())
+        {
+            return Ok(svg);
+        }
+
+        let svg = run_command(command, tex, display)?;
+        if let Some(path) = cache_path {
+            fs::create_dir_all(path.parent().unwrap())
+                .and_then(|_| fs::write(&path, &svg))
+                .unwrap_or_else(|e| eprintln!("failed to cache math at {}: {}", path.display(), e));
+        }
+        Ok(svg)
+    }
+}
+
+impl From<&RenderConfig> for MathRenderer {
+    fn from(config: &RenderConfig) -> Self {
+        Self {
+            command: config.math_command.clone(),
+            cache_dir: config.cache_dir.as_ref().map(|dir| dir.join("math")),
+        }
+    }
+}
+
+/// Update the stack of skipped elements that the page is in with a tag.
+fn track_skipped<'a>(tag: &'a str, skipped: &mut Vec<&'a str>) {
+    let (is_end, tag) = match tag.strip_prefix("</") {
+        Some(tag) => (true, tag),
+        None => (false, &tag[1..]),
+    };
+    let name_end = tag
+        .find(|c: char| !c.is_ascii_alphanumeric())
+        .unwrap_or(tag.len());
+    let name = &tag[..name_end];
+    if !SKIPPED_ELEMENTS
+        .iter()
+        .any(|skipped| skipped.eq_ignore_ascii_case(name))
+    {
+        return;
+    }
+    if is_end {
+        if let Some(i) = skipped
+            .iter()
+            .rposition(|open| open.eq_ignore_ascii_case(name))
+        {
+            skipped.truncate(i);
+        }
+    } else {
+        skipped.push(name);
+    }
+}
+
+/// Math found in text.
+#[derive(Debug, PartialEq)]
+struct Math<'a> {
+    tex: &'a str,
+    display: bool,
+    /// Length of the math along with its delimiters.
+    len: usize,
+}
+
+/// First math in the text, along with its offset.
+fn find_math(text: &str) -> Option<(usize, Math<'_>)> {
+    let bytes = text.as_bytes();
+    let mut i = 0;
+    while i < bytes.len() {
+        match bytes[i] {
+            b'\\' => i += 2,
+            b'$' => {
+                let display = bytes.get(i + 1) == Some(&b'$');
+                let delimiter = if display { "$$" } else { "$" };
+                let start = i + delimiter.len();
+                if let Some(len) = find_closing(&text[start..], delimiter) {
+                    let tex = &text[start..start + len];
+                    let is_math = !tex.trim().is_empty()
+                        && (display || !tex.starts_with(' ') && !tex.ends_with(' '));
+                    if is_math {
+                        let math = Math {
+                            tex,
+                            display,
+                            len: len + 2 * delimiter.len(),
+                        };
+                        return Some((i, math));
+                    }
+                }
+                i = start;
+            }
+            _ => i += 1,
+        }
+    }
+    None
+}
+
+/// Offset of the closing delimiter, skipping escaped characters.
+fn find_closing(text: &str, delimiter: &str) -> Option<usize> {
+    let mut i = 0;
+    while i < text.len() {
+        if text[i..].starts_with('\\') {
+            i += 2;
+        } else if text[i..].starts_with(delimiter) {
+            return Some(i);
+        } else {
+            i += text[i..].chars().next().map_or(1, char::len_utf8);
+        }
+    }
+    None
+}
+
+fn unescape_html(text: &str) -> String {
+    text.replace("&lt;", "<")
+        .replace("&gt;", ">")
+        .replace("&quot;", "\"")
+        .replace("&#39;", "'")
+        .replace("&amp;", "&")
+}
+
+fn math_cache_path(cache_dir: &Path, tex: &str, display: bool) -> PathBuf {
+    let mut hasher = DefaultHasher::new();
+    tex.hash(&mut hasher);
+    display.hash(&mut hasher);
+    cache_dir.join(format!("{:016x}.svg", hasher.finish()))
+}
+
+fn run_command(command: &Path, tex: &str, display: bool) -> io::Result<String> {
+    let mut cmd = Command::new(command);
+    if !display {
+        cmd.arg("--inline");
+    }
+    let output = cmd.arg(tex).output().map_err(|e| {
+        io::Error::new(
+            e.kind(),
+            format!("failed to run {}: {}", command.display(), e),
+        )
+    })?;
+    if !output.status.success() {
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidData,
+            format!(
+                "{} failed: {}",
+                command.display(),
+                stderr.lines().next().unwrap_or_default()
+            ),
+        ));
+    }
+
+    let svg = String::from_utf8_lossy(&output.stdout);
+    let start = svg.find("<svg").unwrap_or(0);
+    Ok(svg[start..].trim_end().to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_util::temp_dir;
+
+    #[test]
+    fn find_math_delimiters() {
+        assert_eq!(
+            find_math("costs \\$5, so $x^2$ and"),
+            Some((
+                14,
+                Math {
+                    tex: "x^2",
+                    display: false,
+                    len: 5
+                }
+            ))
+        );
+        assert_eq!(
+            find_math("$$ a $$").map(|(_, math)| math.display),
+            Some(true)
+        );
+        assert_eq!(find_math("$5 and $6"), None);
+        assert_eq!(find_math("no math"), None);
+    }
+
+    #[test]
+    fn prerender_from_cache() {
+        let cache_dir = temp_dir("math-cache");
+        fs::write(math_cache_path(&cache_dir, "a<b", false), "<svg>a</svg>").unwrap();
+
+        let renderer = MathRenderer {
+            command: Some(PathBuf::from("/nonexistent/tex2svg")),
+            cache_dir: Some(cache_dir),
+        };
+        let prerendered = renderer.prerender("<p>Where $a&lt;b$ <code>$a&lt;b$</code> and $c$</p>");
+        assert_eq!(
+            prerendered.html,
+            "<p>Where <span class=\"math\"><svg>a</svg></span> <code>$a&lt;b$</code> and $c$</p>"
+        );
+        assert_eq!(prerendered.failed, 1);
+    }
+}
@@ -1,5 +1,6 @@
 use crate::render::ToHtml;
 use maud::{html, Markup, PreEscaped, Render};
+use std::{io, str::FromStr};
 
 pub const MATHJAX_CONFIG: &str = "
     MathJax.Hub.Config({
@@ -20,6 +21,21 @@ pub enum MathjaxPolicy {
     Never,
 }
 
+impl FromStr for MathjaxPolicy {
+    type Err = io::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "always" => Ok(MathjaxPolicy::Always),
+            "never" => Ok(MathjaxPolicy::Never),
+            _ => Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                format!("unknown mathjax policy `{}`", s),
+            )),
+        }
+    }
+}
+
 // TODO: MathjaxPolicy::inclusion implement the `Auto` policy
 impl MathjaxPolicy {
     pub fn inclusion(&self) -> bool {
@@ -0,0 +1,202 @@
+use crate::render::{index::INDEX_FILE_NAME, links::Links};
+use maud::{html, Markup, Render};
+use serde::Serialize;
+use std::path::Path;
+
+/// Link to another page of the rendered notes.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct PageLink {
+    pub name: String,
+    pub href: String,
+}
+
+/// Links from `page` to the index page of each directory from `src_root` down to `dir`, which
+/// is a directory in `src_root`.
+pub fn breadcrumbs(
+    src_root: &Path,
+    dst_root: &Path,
+    dir: &Path,
+    links: &Links,
+    page: Option<&Path>,
+) -> Vec<PageLink> {
+    let root_name = src_root
+        .file_name()
+        .map(|name| name.to_string_lossy().to_string())
+        .unwrap_or_else(|| String::from("/"));
+    let mut trail = vec![PageLink {
+        name: root_name,
+        href: links.href(page, &dst_root.join(INDEX_FILE_NAME)),
+    }];
+
+    let relative = dir.strip_prefix(src_root).unwrap_or_else(|_| Path::new(""));
+    let mut render_dir = dst_root.to_path_buf();
+    for component in relative.components() {
+        render_dir.push(component);
+        trail.push(PageLink {
+            name: component.as_os_str().to_string_lossy().to_string(),
+            href: links.href(page, &render_dir.join(INDEX_FILE_NAME)),
+        });
+    }
+    trail
+}
+
+/// Trail of breadcrumbs, separated by slashes.
+///
+/// On index pages the last breadcrumb is the page itself, so it isn't a link.
+pub struct Breadcrumbs<'a> {
+    pub links: &'a [PageLink],
+    pub is_index: bool,
+}
+
+impl<'a> Render for Breadcrumbs<'a> {
+    fn render(&self) -> Markup {
+        let last = self.links.len().saturating_sub(1);
+        html! {
+            @if !self.links.is_empty() {
+                nav class="breadcrumbs" {
+                    @for (i, link) in self.links.iter().enumerate() {
+                        @if i > 0 {
+                            " / "
+                        }
+                        @if self.is_index && i == last {
+                            span { (link.name) }
+                        } @else {
+                            a href=(link.href) { (link.name) }
+                        }
+                    }
+                }
+            }
+        }
+    }
+}
+
+/// Links to the notes before and after a note in its directory.
+#[derive(Debug, Default, Clone, PartialEq, Serialize)]
+pub struct Siblings {
+    pub previous: Option<PageLink>,
+    pub next: Option<PageLink>,
+}
+
+impl Siblings {
+    /// Siblings of the note at `index` in the ordered notes of a directory.
+    pub fn of(notes: &[PageLink], index: usize) -> Self {
+        Self {
+            previous: index.checked_sub(1).and_then(|i| notes.get(i)).cloned(),
+            next: notes.get(index + 1).cloned(),
+        }
+    }
+}
+
+impl Render for Siblings {
+    fn render(&self) -> Markup {
+        html! {
+            @if self.previous.is_some() || self.next.is_some() {
+                nav class="siblings" {
+                    @if let Some(ref previous) = self.previous {
+                        a class="previous" rel="prev" href=(previous.href) {
+                            "← " (previous.name)
+                        }
+                    }
+                    @if let Some(ref next) = self.next {
+                        a class="next" rel="next" href=(next.href) {
+                            (next.name) " →"
+                        }
+                    }
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::render::links::LinkStyle;
+    use std::path::PathBuf;
+
+    fn link(name: &str) -> PageLink {
+        PageLink {
+            name: name.to_string(),
+            href: format!("/rendered/{}.html", name),
+        }
+    }
+
+    #[test]
+    fn breadcrumbs_to_nested_dir() {
+        let trail = breadcrumbs(
+            Path::new("/notes"),
+            Path::new("/rendered"),
+            Path::new("/notes/a/b"),
+            &Links::default(),
+            None,
+        );
+        let names: Vec<&str> = trail.iter().map(|crumb| crumb.name.as_str()).collect();
+        assert_eq!(names, vec!["notes", "a", "b"]);
+        assert_eq!(trail[2].href, "/rendered/a/b/index.html");
+    }
+
+    #[test]
+    fn breadcrumbs_to_root() {
+        let trail = breadcrumbs(
+            Path::new("/notes"),
+            Path::new("/rendered"),
+            Path::new("/notes"),
+            &Links::default(),
+            None,
+        );
+        assert_eq!(trail.len(), 1);
+        assert_eq!(trail[0].href, "/rendered/index.html");
+    }
+
+    #[test]
+    fn relative_breadcrumbs() {
+        let links = Links::new(LinkStyle::Relative, Some(PathBuf::from("/rendered")), None);
+        let trail = breadcrumbs(
+            Path::new("/notes"),
+            Path::new("/rendered"),
+            Path::new("/notes/a"),
+            &links,
+            Some(Path::new("/rendered/a/note.html")),
+        );
+        assert_eq!(trail[0].href, "../index.html");
+        assert_eq!(trail[1].href, "index.html");
+    }
+
+    #[test]
+    fn render_index_breadcrumbs() {
+        let links = vec![link("a"), link("b")];
+        let html = Breadcrumbs {
+            links: &links,
+            is_index: true,
+        }
+        .render()
+        .into_string();
+        assert_eq!(
+            html,
+            "<nav class=\"breadcrumbs\"><a href=\"/rendered/a.html\">a</a> / <span>b</span></nav>"
+        );
+    }
+
+    #[test]
+    fn siblings_of_notes() {
+        let notes = vec![link("a"), link("b"), link("c")];
+        assert_eq!(
+            Siblings::of(&notes, 0),
+            Siblings {
+                previous: None,
+                next: Some(link("b")),
+            }
+        );
+        assert_eq!(
+            Siblings::of(&notes, 2),
+            Siblings {
+                previous: Some(link("b")),
+                next: None,
+            }
+        );
+        assert!(Siblings::of(&notes[..1], 0)
+            .render()
+            .into_string()
+            .is_empty());
+    }
+}
@@ -1,25 +1,58 @@
-use crate::render::{mathjax::MathjaxPolicy, stylesheet::Stylesheet, template::Template, ToHtml};
+use crate::render::{
+    front_matter::FrontMatter,
+    header::TocEntry,
+    links::Links,
+    mathjax::MathjaxPolicy,
+    nav::{PageLink, Siblings},
+    sanitize::HtmlPolicy,
+    stylesheet::Stylesheet,
+    template::{PageKind, Template},
+};
 use maud::{html, Markup, PreEscaped, Render};
+use std::path::Path;
 
 pub struct Note<'a> {
-    rendered_html: &'a str,
-    title: &'a Option<String>,
-    stylesheet: &'a Option<Stylesheet>,
-    mathjax_policy: &'a MathjaxPolicy,
+    pub rendered_html: &'a str,
+    pub title: &'a Option<String>,
+    pub toc: &'a [TocEntry],
+    pub front_matter: &'a FrontMatter,
+    /// Links to the pages of the note's tags.
+    pub tags: &'a [PageLink],
+    pub breadcrumbs: &'a [PageLink],
+    pub siblings: &'a Siblings,
+    pub stylesheet: &'a Option<Stylesheet>,
+    pub syntax_stylesheet: &'a Option<Stylesheet>,
+    pub mermaid_script: Option<&'a Path>,
+    pub mathjax_policy: &'a MathjaxPolicy,
+    pub html_policy: &'a HtmlPolicy,
+    pub links: &'a Links,
+    /// Path the note is rendered to.
+    pub path: Option<&'a Path>,
+    pub search_page: Option<&'a Path>,
+    /// Whether the note has sortable tables, with the script that sorts them.
+    pub table_sort: bool,
 }
 
 impl<'a> Note<'a> {
-    pub fn new(
-        rendered_html: &'a str,
-        title: &'a Option<String>,
-        stylesheet: &'a Option<Stylesheet>,
-        mathjax_policy: &'a MathjaxPolicy,
-    ) -> Self {
-        Self {
-            rendered_html,
-            title,
-            stylesheet,
-            mathjax_policy,
+    pub fn template(&self) -> Template<'_> {
+        Template {
+            kind: PageKind::Note,
+            content: self.render(),
+            title: self.title,
+            toc: self.toc,
+            front_matter: self.front_matter,
+            tags: self.tags,
+            breadcrumbs: self.breadcrumbs,
+            siblings: self.siblings,
+            stylesheet: self.stylesheet,
+            syntax_stylesheet: self.syntax_stylesheet,
+            mermaid_script: self.mermaid_script,
+            mathjax_policy: self.mathjax_policy,
+            html_policy: self.html_policy,
+            links: self.links,
+            path: self.path,
+            search_page: self.search_page,
+            table_sort: self.table_sort,
         }
     }
 }
@@ -29,15 +62,3 @@ impl<'a> Render for Note<'a> {
         html! { (PreEscaped(self.rendered_html)) }
     }
 }
-
-impl<'a> ToHtml for Note<'a> {
-    fn to_html(&self) -> String {
-        Template {
-            content: self.render(),
-            title: self.title,
-            stylesheet: self.stylesheet,
-            mathjax_policy: self.mathjax_policy,
-        }
-        .to_html()
-    }
-}
@@ -0,0 +1,667 @@
+use crate::render::{front_matter::FrontMatter, header::HeaderStart, source::ParsedSource};
+use pulldown_cmark::{Alignment, CowStr, Event, LinkType, Tag};
+use serde_json::{Map, Value};
+use std::ops::Range;
+
+/// Deepest heading level, as there are only six levels of HTML headings.
+const MAX_HEADING_LEVEL: usize = 6;
+
+/// Link targets that are shown as images when the link doesn't have a description.
+const IMAGE_EXTENSIONS: &[&str] = &["gif", "jpeg", "jpg", "png", "svg", "webp"];
+
+/// Characters that can come before the start of emphasis markup.
+const MARKUP_PRE: &str = "-({'\"";
+/// Characters that can come after the end of emphasis markup.
+const MARKUP_POST: &str = "-.,;:!?'\")}[";
+
+/// Parse an Org note into the events that a markdown note with the same content would have.
+///
+/// The `#+KEYWORD: value` lines at the start of the note are its front matter, where `#+TITLE`
+/// is the title, `#+DATE` the date and `#+FILETAGS` the tags. The body covers headings,
+/// paragraphs with emphasis and links, plain and checkbox lists, tables, rules, and `src`,
+/// `example` and `quote` blocks. Other keywords and comments aren't rendered.
+pub fn parse(source: &str) -> ParsedSource<'_> {
+    let (front_matter, body_start) = keywords(source);
+    let mut parser = Parser::default();
+    parser.blocks(&source[body_start..], 0);
+    ParsedSource {
+        front_matter: Ok(front_matter),
+        body_start,
+        events: parser.events,
+        warnings: vec![],
+    }
+}
+
+/// Front matter from the keywords at the start of the note, and the offset of the body.
+fn keywords(source: &str) -> (FrontMatter, usize) {
+    let mut map = Map::new();
+    let mut body_start = 0;
+    let mut offset = 0;
+    for line in source.split_inclusive('\n') {
+        offset += line.len();
+        let trimmed = line.trim();
+        if trimmed.is_empty() || is_comment(trimmed) {
+            continue;
+        }
+        let (key, value) = match keyword(trimmed) {
+            Some(keyword) if block_begin(trimmed).is_none() => keyword,
+            _ => break,
+        };
+        body_start = offset;
+
+        let key = key.to_lowercase();
+        let value = match key.as_str() {
+            "filetags" => {
+                let tags = value
+                    .split(':')
+                    .filter(|tag| !tag.trim().is_empty())
+                    .map(|tag| Value::String(tag.trim().to_string()))
+                    .collect();
+                map.insert(String::from("tags"), Value::Array(tags));
+                continue;
+            }
+            "date" => value
+                .trim_matches(|c| c == '<' || c == '>' || c == '[' || c == ']')
+                .split_whitespace()
+                .next()
+                .unwrap_or_default(),
+            _ => value,
+        };
+        map.entry(key)
+            .or_insert_with(|| Value::String(value.to_string()));
+    }
+    (FrontMatter(map), body_start)
+}
+
+/// Key and value of a `#+KEY: value` line.
+fn keyword(line: &str) -> Option<(&str, &str)> {
+    let (key, value) = line.strip_prefix("#+")?.split_once(':')?;
+    if key.is_empty() || key.contains(char::is_whitespace) {
+        None
+    } else {
+        Some((key, value.trim()))
+    }
+}
+
+fn is_comment(line: &str) -> bool {
+    line == "#" || line.starts_with("# ")
+}
+
+/// Name and parameters of a `#+BEGIN_NAME parameters` line.
+fn block_begin(line: &str) -> Option<(String, &str)> {
+    let rest = line.get(..8)?;
+    if !rest.eq_ignore_ascii_case("#+begin_") {
+        return None;
+    }
+    let rest = &line[8..];
+    let end = rest.find(char::is_whitespace).unwrap_or(rest.len());
+    Some((rest[..end].to_lowercase(), rest[end..].trim()))
+}
+
+fn is_block_end(line: &str, name: &str) -> bool {
+    let end = format!("#+end_{}", name);
+    line.trim().eq_ignore_ascii_case(&end)
+}
+
+/// Level of a `*** heading` line.
+fn heading_level(line: &str) -> Option<usize> {
+    let stars = line.len() - line.trim_start_matches('*').len();
+    if stars > 0 && line[stars..].starts_with(' ') {
+        Some(stars)
+    } else {
+        None
+    }
+}
+
+fn is_rule(line: &str) -> bool {
+    line.len() >= 5 && line.chars().all(|c| c == '-')
+}
+
+/// List item at the start of a line, as its indent, its number if it's ordered, and the offset
+/// of its content.
+fn list_item(line: &str) -> Option<(usize, Option<usize>, usize)> {
+    let indent = line.len() - line.trim_start().len();
+    let rest = &line[indent..];
+    for bullet in &["- ", "+ ", "* "] {
+        // Unindented stars are headings.
+        if rest.starts_with(bullet) && (indent > 0 || *bullet != "* ") {
+            return Some((indent, None, indent + 2));
+        }
+    }
+    let digits = rest.len() - rest.trim_start_matches(|c: char| c.is_ascii_digit()).len();
+    let after = &rest[digits..];
+    if digits > 0 && (after.starts_with(". ") || after.starts_with(") ")) {
+        let number = rest[..digits].parse().ok();
+        return Some((indent, number.or(Some(1)), indent + digits + 2));
+    }
+    None
+}
+
+fn indent(line: &str) -> usize {
+    line.len() - line.trim_start().len()
+}
+
+/// Lines of the text without their line endings, along with their offsets.
+fn lines(text: &str) -> Vec<(usize, &str)> {
+    let mut lines = vec![];
+    let mut offset = 0;
+    for line in text.split_inclusive('\n') {
+        lines.push((offset, line.trim_end_matches(['\n', '\r'])));
+        offset += line.len();
+    }
+    lines
+}
+
+#[derive(Default)]
+struct Parser<'a> {
+    events: Vec<(Event<'a>, Range<usize>)>,
+}
+
+impl<'a> Parser<'a> {
+    fn push(&mut self, event: Event<'a>, range: Range<usize>) {
+        self.events.push((event, range));
+    }
+
+    /// Parse the blocks in the text, which is at `base` in the body.
+    fn blocks(&mut self, text: &'a str, base: usize) {
+        let lines = lines(text);
+        let mut i = 0;
+        while i < lines.len() {
+            let (start, line) = lines[i];
+            let trimmed = line.trim();
+            let range = base + start..base + start + line.len();
+            if trimmed.is_empty() {
+                i += 1;
+            } else if let Some(level) = heading_level(line) {
+                self.heading(level, line, base + start);
+                i += 1;
+            } else if let Some((name, parameters)) = block_begin(trimmed) {
+                let end = (i + 1..lines.len())
+                    .find(|&j| is_block_end(lines[j].1, &name))
+                    .unwrap_or(lines.len());
+                let content_start = lines.get(i + 1).map_or(text.len(), |line| line.0);
+                let content_end = lines.get(end).map_or(text.len(), |line| line.0);
+                let content = &text[content_start.min(content_end)..content_end];
+                let range =
+                    range.start..base + lines.get(end).map_or(text.len(), |l| l.0 + l.1.len());
+                self.block(&name, parameters, content, base + content_start, range);
+                i = end + 1;
+            } else if keyword(trimmed).is_some() || is_comment(trimmed) {
+                i += 1;
+            } else if is_rule(trimmed) {
+                self.push(Event::Start(Tag::Rule), range.clone());
+                self.push(Event::End(Tag::Rule), range);
+                i += 1;
+            } else if trimmed.starts_with('|') {
+                let end = (i..lines.len())
+                    .find(|&j| !lines[j].1.trim().starts_with('|'))
+                    .unwrap_or(lines.len());
+                self.table(&lines[i..end], base);
+                i = end;
+            } else if list_item(line).is_some() {
+                i = self.list(text, &lines, i, base);
+            } else {
+                let end = (i + 1..lines.len())
+                    .find(|&j| !is_paragraph_line(lines[j].1))
+                    .unwrap_or(lines.len());
+                let (last_start, last_line) = lines[end - 1];
+                let range = base + start..base + last_start + last_line.len();
+                self.push(Event::Start(Tag::Paragraph), range.clone());
+                self.inline(&text[start..last_start + last_line.len()], base + start);
+                self.push(Event::End(Tag::Paragraph), range);
+                i = end;
+            }
+        }
+    }
+
+    fn heading(&mut self, level: usize, line: &'a str, offset: usize) {
+        let level = level.min(MAX_HEADING_LEVEL) as i32;
+        let mut title = line.trim_start_matches('*').trim();
+        // Tags at the end of a heading, like `:tag:other:`.
+        if let Some(tags_start) = title.rfind(char::is_whitespace) {
+            let tags = &title[tags_start..].trim();
+            if tags.len() > 2 && tags.starts_with(':') && tags.ends_with(':') {
+                title = title[..tags_start].trim_end();
+            }
+        }
+        let title_start = offset + (title.as_ptr() as usize - line.as_ptr() as usize);
+        let range = offset..offset + line.len();
+        self.push(Event::Start(Tag::Header(level)), range.clone());
+        self.inline(title, title_start);
+        self.push(Event::End(Tag::Header(level)), range);
+    }
+
+    fn block(
+        &mut self,
+        name: &str,
+        parameters: &str,
+        content: &'a str,
+        content_start: usize,
+        range: Range<usize>,
+    ) {
+        match name {
+            "src" | "example" => {
+                let language = match name {
+                    "src" => parameters.split_whitespace().next().unwrap_or_default(),
+                    _ => "",
+                };
+                let tag = Tag::CodeBlock(CowStr::Boxed(language.into()));
+                self.push(Event::Start(tag.clone()), range.clone());
+                let code_range = content_start..content_start + content.len();
+                self.push(Event::Text(dedent(content)), code_range);
+                self.push(Event::End(tag), range);
+            }
+            "quote" => {
+                self.push(Event::Start(Tag::BlockQuote), range.clone());
+                self.blocks(content, content_start);
+                self.push(Event::End(Tag::BlockQuote), range);
+            }
+            _ => self.blocks(content, content_start),
+        }
+    }
+
+    /// Parse the list starting at line `i` of the text, returning the line after it.
+    fn list(
+        &mut self,
+        text: &'a str,
+        lines: &[(usize, &'a str)],
+        mut i: usize,
+        base: usize,
+    ) -> usize {
+        let (list_indent, list_number, _) = list_item(lines[i].1).unwrap();
+        let list_start = base + lines[i].0;
+        self.push(
+            Event::Start(Tag::List(list_number)),
+            list_start..list_start + lines[i].1.len(),
+        );
+
+        while i < lines.len() {
+            let (start, line) = lines[i];
+            let (item_indent, _, content_offset) = match list_item(line) {
+                Some(item)
+                    if item.0 == list_indent && item.1.is_some() == list_number.is_some() =>
+                {
+                    item
+                }
+                _ => break,
+            };
+            let item_range = base + start..base + start + line.len();
+            self.push(Event::Start(Tag::Item), item_range.clone());
+
+            let mut content = &line[content_offset..];
+            let mut content_start = base + start + content_offset;
+            for (marker, checked) in &[("[ ] ", false), ("[X] ", true), ("[x] ", true)] {
+                if let Some(rest) = content.strip_prefix(marker) {
+                    self.push(
+                        Event::TaskListMarker(*checked),
+                        content_start..content_start + 3,
+                    );
+                    content = rest;
+                    content_start += marker.len();
+                }
+            }
+
+            // Lines indented under the item continue it.
+            let mut end = i + 1;
+            while end < lines.len() {
+                let next = lines[end].1;
+                if next.trim().is_empty()
+                    || indent(next) <= item_indent
+                    || list_item(next).is_some()
+                {
+                    break;
+                }
+                end += 1;
+            }
+            let (last_start, last_line) = lines[end - 1];
+            let content_end = last_start + last_line.len();
+            if content_start - base < content_end {
+                self.inline(&text[content_start - base..content_end], content_start);
+            }
+            i = end;
+
+            // Lists nested in the item, which can be separated from it by blank lines.
+            loop {
+                let next = next_non_blank(lines, i);
+                match next.and_then(|j| list_item(lines[j].1).map(|item| (j, item))) {
+                    Some((j, (nested_indent, ..))) if nested_indent > item_indent => {
+                        i = self.list(text, lines, j, base);
+                    }
+                    _ => break,
+                }
+            }
+            self.push(Event::End(Tag::Item), item_range);
+
+            match next_non_blank(lines, i) {
+                Some(j) if list_item(lines[j].1).is_some_and(|item| item.0 == list_indent) => i = j,
+                _ => break,
+            }
+        }
+
+        let list_end = base
+            + lines
+                .get(i)
+                .map_or(lines[i - 1].0 + lines[i - 1].1.len(), |l| l.0);
+        self.push(Event::End(Tag::List(list_number)), list_start..list_end);
+        i
+    }
+
+    fn table(&mut self, rows: &[(usize, &'a str)], base: usize) {
+        let cells: Vec<Vec<(usize, &'a str)>> = rows
+            .iter()
+            .filter(|(_, line)| !line.trim().starts_with("|-"))
+            .map(|&(start, line)| table_cells(line, base + start))
+            .collect();
+        let columns = cells.iter().map(Vec::len).max().unwrap_or(0);
+        let range = base + rows[0].0..base + rows[rows.len() - 1].0 + rows[rows.len() - 1].1.len();
+
+        self.push(
+            Event::Start(Tag::Table(vec![Alignment::None; columns])),
+            range.clone(),
+        );
+        // The first row is the head, as a table always has one when it's rendered.
+        for (i, row) in cells.iter().enumerate() {
+            let row_tag = if i == 0 {
+                Tag::TableHead
+            } else {
+                Tag::TableRow
+            };
+            self.push(Event::Start(row_tag.clone()), range.clone());
+            for column in 0..columns {
+                let (offset, cell) = row.get(column).copied().unwrap_or((range.end, ""));
+                self.push(Event::Start(Tag::TableCell), offset..offset + cell.len());
+                self.inline(cell, offset);
+                self.push(Event::End(Tag::TableCell), offset..offset + cell.len());
+            }
+            self.push(Event::End(row_tag), range.clone());
+        }
+        self.push(Event::End(Tag::Table(vec![])), range);
+    }
+
+    /// Parse the inline markup in the text, which is at `base` in the body.
+    fn inline(&mut self, text: &'a str, base: usize) {
+        let mut pos = 0;
+        let mut text_start = 0;
+        while pos < text.len() {
+            let rest = &text[pos..];
+            if rest.starts_with('\n') {
+                let line = &text[text_start..pos];
+                let (line, is_hard) = match line.trim_end().strip_suffix("\\\\") {
+                    Some(line) => (line, true),
+                    None => (line.trim_end(), false),
+                };
+                self.text(line, base + text_start);
+                let event = if is_hard {
+                    Event::HardBreak
+                } else {
+                    Event::SoftBreak
+                };
+                self.push(event, base + pos..base + pos + 1);
+                pos += 1;
+                pos += text[pos..].len() - text[pos..].trim_start_matches([' ', '\t']).len();
+                text_start = pos;
+                continue;
+            }
+            if rest.starts_with("[[") {
+                if let Some(len) = rest.find("]]").map(|end| end + 2) {
+                    self.text(&text[text_start..pos], base + text_start);
+                    self.link(&rest[..len], base + pos);
+                    pos += len;
+                    text_start = pos;
+                    continue;
+                }
+            }
+            let marker = rest.chars().next().unwrap();
+            if "*/=~+".contains(marker) && can_open(text, pos) {
+                if let Some(end) = find_close(text, pos, marker) {
+                    self.text(&text[text_start..pos], base + text_start);
+                    let inner = &text[pos + 1..end];
+                    let range = base + pos..base + end + 1;
+                    match marker {
+                        '=' | '~' => {
+                            self.push(Event::Start(Tag::Code), range.clone());
+                            self.push(Event::Text(inner.into()), base + pos + 1..base + end);
+                            self.push(Event::End(Tag::Code), range);
+                        }
+                        _ => {
+                            let tag = match marker {
+                                '*' => Tag::Strong,
+                                '/' => Tag::Emphasis,
+                                _ => Tag::Strikethrough,
+                            };
+                            self.push(Event::Start(tag.clone()), range.clone());
+                            self.inline(inner, base + pos + 1);
+                            self.push(Event::End(tag), range);
+                        }
+                    }
+                    pos = end + 1;
+                    text_start = pos;
+                    continue;
+                }
+            }
+            pos += marker.len_utf8();
+        }
+        self.text(&text[text_start..], base + text_start);
+    }
+
+    fn text(&mut self, text: &'a str, offset: usize) {
+        if !text.is_empty() {
+            self.push(Event::Text(text.into()), offset..offset + text.len());
+        }
+    }
+
+    /// Link written as `[[target][description]]` or `[[target]]`.
+    fn link(&mut self, link: &'a str, offset: usize) {
+        let inner = &link[2..link.len() - 2];
+        let (target, description) = match inner.split_once("][") {
+            Some((target, description)) => (target, Some((description, target.len() + 4))),
+            None => (inner, None),
+        };
+        let range = offset..offset + link.len();
+
+        let dest: CowStr<'a> = match target.strip_prefix('*') {
+            Some(heading) => format!("#{}", HeaderStart::new(1, heading).id()).into(),
+            None => {
+                let target = target.strip_prefix("file:").unwrap_or(target);
+                // Search options after a file link, like `file:note.org::*Heading`.
+                target.split("::").next().unwrap_or_default().into()
+            }
+        };
+        let is_image = IMAGE_EXTENSIONS.iter().any(|extension| {
+            dest.rsplit('.')
+                .next()
+                .is_some_and(|e| e.eq_ignore_ascii_case(extension))
+        });
+        match description {
+            None if is_image => {
+                let tag = Tag::Image(LinkType::Inline, dest, "".into());
+                self.push(Event::Start(tag.clone()), range.clone());
+                self.push(Event::End(tag), range);
+            }
+            _ => {
+                let tag = Tag::Link(LinkType::Inline, dest, "".into());
+                self.push(Event::Start(tag.clone()), range.clone());
+                match description {
+                    Some((description, start)) => self.inline(description, offset + start),
+                    None => self.text(target, offset + 2),
+                }
+                self.push(Event::End(tag), range);
+            }
+        }
+    }
+}
+
+/// Whether a line continues a paragraph rather than starting another block.
+fn is_paragraph_line(line: &str) -> bool {
+    let trimmed = line.trim();
+    !trimmed.is_empty()
+        && heading_level(line).is_none()
+        && block_begin(trimmed).is_none()
+        && keyword(trimmed).is_none()
+        && !is_comment(trimmed)
+        && !is_rule(trimmed)
+        && !trimmed.starts_with('|')
+        && list_item(line).is_none()
+}
+
+fn next_non_blank(lines: &[(usize, &str)], from: usize) -> Option<usize> {
+    (from..lines.len()).find(|&i| !lines[i].1.trim().is_empty())
+}
+
+/// Cells of a `| a | b |` table row, with their offsets.
+fn table_cells(line: &str, offset: usize) -> Vec<(usize, &str)> {
+    let mut cells = vec![];
+    let start = line.find('|').map_or(0, |i| i + 1);
+    let mut cell_start = start;
+    for (i, c) in line[start..].char_indices() {
+        if c == '|' {
+            cells.push(trimmed_cell(line, cell_start, start + i, offset));
+            cell_start = start + i + 1;
+        }
+    }
+    if !line[cell_start..].trim().is_empty() {
+        cells.push(trimmed_cell(line, cell_start, line.len(), offset));
+    }
+    cells
+}
+
+fn trimmed_cell(line: &str, start: usize, end: usize, offset: usize) -> (usize, &str) {
+    let cell = &line[start..end];
+    let leading = cell.len() - cell.trim_start().len();
+    (offset + start + leading, cell.trim())
+}
+
+fn can_open(text: &str, pos: usize) -> bool {
+    let before = text[..pos].chars().next_back();
+    let after = text[pos + 1..].chars().next();
+    before.is_none_or(|c| c.is_whitespace() || MARKUP_PRE.contains(c))
+        && after.is_some_and(|c| !c.is_whitespace())
+}
+
+/// Offset of the marker that closes markup opened at `pos`.
+fn find_close(text: &str, pos: usize, marker: char) -> Option<usize> {
+    let mut search = pos + 2;
+    while let Some(i) = text.get(search..)?.find(marker) {
+        let end = search + i;
+        let before = text[..end].chars().next_back();
+        let after = text[end + 1..].chars().next();
+        if before.is_some_and(|c| !c.is_whitespace())
+            && after.is_none_or(|c| c.is_whitespace() || MARKUP_POST.contains(c))
+        {
+            return Some(end);
+        }
+        search = end + 1;
+    }
+    None
+}
+
+/// Code with the indentation that all its lines have removed.
+fn dedent(code: &str) -> CowStr<'_> {
+    let indent = code
+        .lines()
+        .filter(|line| !line.trim().is_empty())
+        .map(indent)
+        .min()
+        .unwrap_or(0);
+    if indent == 0 {
+        return code.into();
+    }
+    let dedented: String = code
+        .split_inclusive('\n')
+        .map(|line| {
+            line.get(indent..)
+                .unwrap_or_else(|| line.trim_start_matches(' '))
+        })
+        .collect();
+    CowStr::Boxed(dedented.into_boxed_str())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use pulldown_cmark::html;
+
+    fn to_html(source: &str) -> String {
+        let mut html = String::new();
+        html::push_html(
+            &mut html,
+            parse(source).events.into_iter().map(|(event, _)| event),
+        );
+        html
+    }
+
+    #[test]
+    fn keywords_are_front_matter() {
+        let parsed =
+            parse("#+TITLE: A Note\n#+DATE: <2020-01-02 Thu>\n#+FILETAGS: :a:b:\n\nBody\n");
+        let front_matter = parsed.front_matter.unwrap();
+        assert_eq!(front_matter.title(), Some("A Note"));
+        assert_eq!(
+            front_matter.get("date"),
+            Some(&Value::String(String::from("2020-01-02")))
+        );
+        assert_eq!(
+            front_matter.get("tags"),
+            Some(&Value::Array(vec![Value::from("a"), Value::from("b")]))
+        );
+        assert_eq!(parsed.body_start, 59);
+    }
+
+    #[test]
+    fn headings_and_inline_markup() {
+        assert_eq!(
+            to_html("* Top :tag:\n** /Sub/ heading\nSome *bold*, =code= and +gone+ text\nnext line.\n"),
+            "<h1>Top</h1>\n<h2><em>Sub</em> heading</h2>\n\
+             <p>Some <strong>bold</strong>, <code>code</code> and <del>gone</del> text\nnext line.</p>\n"
+        );
+        assert_eq!(to_html("a/b/c and 2*3*4\n"), "<p>a/b/c and 2*3*4</p>\n");
+    }
+
+    #[test]
+    fn links_and_images() {
+        assert_eq!(
+            to_html("See [[file:other.org][the other *note*]], [[https://x.org]] and [[*Some Heading][here]].\n[[./pic.png]]\n"),
+            "<p>See <a href=\"other.org\">the other <strong>note</strong></a>, \
+             <a href=\"https://x.org\">https://x.org</a> and <a href=\"#some-heading\">here</a>.\n\
+             <img src=\"./pic.png\" alt=\"\" /></p>\n"
+        );
+    }
+
+    #[test]
+    fn nested_lists() {
+        assert_eq!(
+            to_html("- one\n  continued\n  1. nested\n  2. [X] done\n\n- two\nAfter\n"),
+            "<ul>\n<li>one\ncontinued\n<ol>\n<li>nested</li>\n\
+             <li><input disabled=\"\" type=\"checkbox\" checked=\"\"/>\ndone</li>\n</ol>\n</li>\n\
+             <li>two</li>\n</ul>\n<p>After</p>\n"
+        );
+    }
+
+    #[test]
+    fn src_blocks_and_tables() {
+        let source = "#+BEGIN_SRC rust :results none\n  fn main() {}\n#+END_SRC\n\
+                      | a | b |\n|---+---|\n| 1 | =2= |\n";
+        assert_eq!(
+            to_html(source),
+            "<pre><code class=\"language-rust\">fn main() {}\n</code></pre>\n\
+             <table><thead><tr><th>a</th><th>b</th></tr></thead>\
+             <tbody>\n<tr><td>1</td><td><code>2</code></td></tr>\n</tbody></table>\n"
+        );
+    }
+
+    #[test]
+    fn event_ranges_are_in_the_body() {
+        let source = "#+TITLE: T\n* Heading\n";
+        let parsed = parse(source);
+        let body = &source[parsed.body_start..];
+        let text_range = parsed
+            .events
+            .iter()
+            .find_map(|(event, range)| match event {
+                Event::Text(_) => Some(range.clone()),
+                _ => None,
+            })
+            .unwrap();
+        assert_eq!(&body[text_range], "Heading");
+    }
+}
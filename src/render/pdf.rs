@@ -0,0 +1,289 @@
+use flate2::read::ZlibDecoder;
+use regex::bytes::Regex;
+use std::{collections::HashMap, io::Read};
+
+/// Metadata of a PDF, parsed from the file without any external tools.
+///
+/// Only what's needed for the page of a PDF is parsed: the document information dictionary and
+/// the page objects. Objects in compressed object streams are found too, but encrypted PDFs only
+/// have their page count.
+#[derive(Debug, Default, PartialEq)]
+pub struct PdfInfo {
+    pub title: Option<String>,
+    pub author: Option<String>,
+    pub pages: usize,
+}
+
+impl PdfInfo {
+    pub fn parse(pdf: &[u8]) -> Self {
+        let objects = objects(pdf);
+        let re_page = Regex::new(r"(?-u)/Type\s*/Page\b").unwrap();
+        let pages = objects
+            .values()
+            .filter(|body| re_page.is_match(dictionary(body)))
+            .count();
+
+        // The trailer, or the cross-reference stream in newer PDFs, refers to the information
+        // dictionary. The last one is from the latest update of the file.
+        let re_info = Regex::new(r"(?-u)/Info\s+(\d+)\s+\d+\s+R").unwrap();
+        let info = re_info
+            .captures_iter(pdf)
+            .last()
+            .and_then(|captures| parse_number(&captures[1]))
+            .and_then(|number| objects.get(&number));
+
+        Self {
+            title: info.and_then(|info| string_value(info, "Title")),
+            author: info.and_then(|info| string_value(info, "Author")),
+            pages,
+        }
+    }
+}
+
+/// Bodies of the objects in the PDF by object number. Objects that are defined again by a later
+/// update of the file replace the earlier definition.
+fn objects(pdf: &[u8]) -> HashMap<u32, Vec<u8>> {
+    let re_object = Regex::new(r"(?s-u)\b(\d+)\s+\d+\s+obj\b(.*?)\bendobj\b").unwrap();
+    let mut objects = HashMap::new();
+    for captures in re_object.captures_iter(pdf) {
+        let number = match parse_number(&captures[1]) {
+            Some(number) => number,
+            None => continue,
+        };
+        let body = &captures[2];
+        objects.insert(number, body.to_vec());
+    }
+
+    let streams: Vec<Vec<(u32, Vec<u8>)>> = objects
+        .values()
+        .filter_map(|body| object_stream(body))
+        .collect();
+    for stream in streams {
+        for (number, body) in stream {
+            objects.entry(number).or_insert(body);
+        }
+    }
+    objects
+}
+
+/// Objects in the body of a compressed object stream, or `None` if it isn't one.
+///
+/// The decompressed stream starts with pairs of object numbers and offsets, and the objects
+/// start at the `/First` offset.
+fn object_stream(body: &[u8]) -> Option<Vec<(u32, Vec<u8>)>> {
+    let re_dict = Regex::new(r"(?-u)/Type\s*/ObjStm\b").unwrap();
+    let dict = dictionary(body);
+    let stream_start = dict.len();
+    if stream_start == body.len()
+        || !re_dict.is_match(dict)
+        || !Regex::new(r"(?-u)/FlateDecode\b").unwrap().is_match(dict)
+    {
+        return None;
+    }
+    let count = dict_number(dict, "N")?;
+    let first = dict_number(dict, "First")? as usize;
+
+    let mut data = &body[stream_start + b"stream".len()..];
+    while data.first().is_some_and(|b| *b == b'\r' || *b == b'\n') {
+        data = &data[1..];
+    }
+    let mut decoded = vec![];
+    // Anything after the end of the compressed data, such as `endstream`, is an error that still
+    // leaves the decoded data.
+    let _ = ZlibDecoder::new(data).read_to_end(&mut decoded);
+    if decoded.len() < first {
+        return None;
+    }
+
+    let header = String::from_utf8_lossy(&decoded[..first]);
+    let numbers: Vec<usize> = header
+        .split_whitespace()
+        .filter_map(|n| n.parse().ok())
+        .collect();
+    let pairs: Vec<(u32, usize)> = numbers
+        .chunks(2)
+        .take(count as usize)
+        .filter(|pair| pair.len() == 2)
+        .map(|pair| (pair[0] as u32, first + pair[1]))
+        .collect();
+    let objects = pairs
+        .iter()
+        .enumerate()
+        .filter_map(|(i, &(number, start))| {
+            let end = pairs.get(i + 1).map_or(decoded.len(), |&(_, end)| end);
+            let body = decoded.get(start..end.max(start))?;
+            Some((number, body.to_vec()))
+        })
+        .collect();
+    Some(objects)
+}
+
+/// Dictionary of an object, without the data of its stream if it has one.
+fn dictionary(body: &[u8]) -> &[u8] {
+    match find(body, b"stream") {
+        Some(stream_start) => &body[..stream_start],
+        None => body,
+    }
+}
+
+/// Integer value of the key in a dictionary.
+fn dict_number(dict: &[u8], key: &str) -> Option<u32> {
+    let re = Regex::new(&format!(r"(?-u)/{}\s+(\d+)", key)).unwrap();
+    re.captures(dict)
+        .and_then(|captures| parse_number(&captures[1]))
+}
+
+/// String value of the key in a dictionary, which is either a literal `(string)` or a
+/// `<hex string>`.
+fn string_value(dict: &[u8], key: &str) -> Option<String> {
+    let re = Regex::new(&format!(r"(?-u)/{}\s*([(<])", key)).unwrap();
+    let start = re.captures(dict)?.get(1)?.start();
+    let bytes = match dict[start] {
+        b'(' => literal_string(&dict[start + 1..]),
+        _ => hex_string(&dict[start + 1..]),
+    };
+    Some(decode_text(&bytes)).filter(|text| !text.trim().is_empty())
+}
+
+/// Bytes of a literal string up to its closing parenthesis, with escapes replaced.
+fn literal_string(data: &[u8]) -> Vec<u8> {
+    let mut bytes = vec![];
+    let mut depth = 0;
+    let mut i = 0;
+    while i < data.len() {
+        match data[i] {
+            b'\\' if i + 1 < data.len() => {
+                i += 1;
+                match data[i] {
+                    b'n' => bytes.push(b'\n'),
+                    b'r' => bytes.push(b'\r'),
+                    b't' => bytes.push(b'\t'),
+                    b'b' => bytes.push(8),
+                    b'f' => bytes.push(12),
+                    // A backslash at the end of a line continues the string on the next line.
+                    b'\r' if data.get(i + 1) == Some(&b'\n') => i += 1,
+                    b'\r' | b'\n' => {}
+                    b'0'..=b'7' => {
+                        let digits = data[i..]
+                            .iter()
+                            .take(3)
+                            .take_while(|b| (b'0'..=b'7').contains(*b))
+                            .count();
+                        let octal = std::str::from_utf8(&data[i..i + digits]).unwrap();
+                        bytes.push(u8::from_str_radix(octal, 8).unwrap_or(0));
+                        i += digits - 1;
+                    }
+                    other => bytes.push(other),
+                }
+            }
+            b'(' => {
+                depth += 1;
+                bytes.push(b'(');
+            }
+            b')' if depth == 0 => break,
+            b')' => {
+                depth -= 1;
+                bytes.push(b')');
+            }
+            other => bytes.push(other),
+        }
+        i += 1;
+    }
+    bytes
+}
+
+/// Bytes of a hex string up to its closing angle bracket.
+fn hex_string(data: &[u8]) -> Vec<u8> {
+    let end = data.iter().position(|b| *b == b'>').unwrap_or(data.len());
+    let mut digits: Vec<u8> = data[..end]
+        .iter()
+        .filter(|b| b.is_ascii_hexdigit())
+        .copied()
+        .collect();
+    // A missing last digit is zero.
+    if digits.len() % 2 == 1 {
+        digits.push(b'0');
+    }
+    digits
+        .chunks(2)
+        .map(|pair| u8::from_str_radix(std::str::from_utf8(pair).unwrap(), 16).unwrap())
+        .collect()
+}
+
+/// Text of a string, which is UTF-16 when it starts with a byte order mark and otherwise in
+/// PDFDocEncoding, which is close enough to Latin-1 for metadata.
+fn decode_text(bytes: &[u8]) -> String {
+    match bytes {
+        [0xfe, 0xff, rest @ ..] => {
+            let units: Vec<u16> = rest
+                .chunks_exact(2)
+                .map(|pair| u16::from_be_bytes([pair[0], pair[1]]))
+                .collect();
+            String::from_utf16_lossy(&units)
+        }
+        [0xef, 0xbb, 0xbf, rest @ ..] => String::from_utf8_lossy(rest).into_owned(),
+        _ => bytes.iter().map(|&b| b as char).collect(),
+    }
+}
+
+fn parse_number(digits: &[u8]) -> Option<u32> {
+    std::str::from_utf8(digits).ok()?.parse().ok()
+}
+
+fn find(haystack: &[u8], needle: &[u8]) -> Option<usize> {
+    haystack
+        .windows(needle.len())
+        .position(|window| window == needle)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use flate2::{write::ZlibEncoder, Compression};
+    use std::io::Write;
+
+    #[test]
+    fn info_and_pages() {
+        let pdf = b"%PDF-1.4\n\
+            1 0 obj << /Type /Catalog /Pages 2 0 R >> endobj\n\
+            2 0 obj << /Type /Pages /Kids [3 0 R 4 0 R] /Count 2 >> endobj\n\
+            3 0 obj << /Type /Page /Parent 2 0 R >> endobj\n\
+            4 0 obj << /Type/Page /Parent 2 0 R >> endobj\n\
+            5 0 obj << /Title (Notes \\(draft\\)\\051 on caf\\351) /Author <FEFF0041006E006E> >> \
+            endobj\n\
+            trailer << /Root 1 0 R /Info 5 0 R >>\n%%EOF";
+        assert_eq!(
+            PdfInfo::parse(pdf),
+            PdfInfo {
+                title: Some(String::from("Notes (draft)) on café")),
+                author: Some(String::from("Ann")),
+                pages: 2,
+            }
+        );
+    }
+
+    #[test]
+    fn objects_in_object_stream() {
+        let objects = b"3 0 4 32 << /Type /Page /Parent 2 0 R >> << /Title (Packed) >>";
+        let mut encoder = ZlibEncoder::new(vec![], Compression::default());
+        encoder.write_all(objects).unwrap();
+        let compressed = encoder.finish().unwrap();
+
+        let mut pdf = b"%PDF-1.5\n\
+            2 0 obj << /Type /Pages /Kids [3 0 R] /Count 1 >> endobj\n\
+            5 0 obj << /Type /ObjStm /N 2 /First 9 /Filter /FlateDecode >>\nstream\n"
+            .to_vec();
+        pdf.extend(compressed);
+        pdf.extend(b"\nendstream\nendobj\n6 0 obj << /Type /XRef /Info 4 0 R >> endobj\n");
+
+        let info = PdfInfo::parse(&pdf);
+        assert_eq!(info.pages, 1);
+        assert_eq!(info.title.as_deref(), Some("Packed"));
+        assert_eq!(info.author, None);
+    }
+
+    #[test]
+    fn not_a_pdf() {
+        assert_eq!(PdfInfo::parse(b"\x89PNG"), PdfInfo::default());
+    }
+}
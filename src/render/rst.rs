@@ -0,0 +1,1194 @@
+use crate::render::{front_matter::FrontMatter, header::HeaderStart, source::ParsedSource};
+use pulldown_cmark::{Alignment, CowStr, Event, LinkType, Tag};
+use serde_json::{Map, Value};
+use std::{collections::HashMap, ops::Range};
+
+/// Deepest heading level, as there are only six levels of HTML headings.
+const MAX_HEADING_LEVEL: usize = 6;
+
+/// Characters that section titles and transitions are drawn with.
+const ADORNMENT_CHARS: &str = "!\"#$%&'()*+,-./:;<=>?@[\\]^_`{|}~";
+
+/// Characters that can come before the start of inline markup.
+const MARKUP_PRE: &str = "-:/'\"<([{";
+/// Characters that can come after the end of inline markup.
+const MARKUP_POST: &str = "-.,:;!?\\/'\")]}>";
+
+/// Directives that are rendered as callouts of the same kind.
+const ADMONITIONS: &[&str] = &[
+    "attention",
+    "caution",
+    "danger",
+    "error",
+    "hint",
+    "important",
+    "note",
+    "seealso",
+    "tip",
+    "warning",
+];
+
+/// Roles whose text is rendered as inline code.
+const CODE_ROLES: &[&str] = &[
+    "code", "command", "file", "kbd", "literal", "program", "samp",
+];
+
+/// Parse a reStructuredText note into the events that a markdown note with the same content would
+/// have.
+///
+/// A field list at the start of the note is its front matter, where `:tags:` is a comma separated
+/// list. The body covers sections, transitions, paragraphs with inline markup, roles and
+/// hyperlinks, bullet and enumerated lists, literal blocks, block quotes, grid and simple tables,
+/// and the admonition, `code-block`, `highlight`, `image`, `figure`, `math` and `raw` directives.
+/// Other directives, footnotes and substitutions are left out with a warning.
+pub fn parse(source: &str) -> ParsedSource<'_> {
+    let (front_matter, body_start) = field_list(source);
+    let body = &source[body_start..];
+    let lines = lines(body);
+    let mut parser = Parser {
+        body,
+        targets: targets(&lines),
+        styles: vec![],
+        highlight: "",
+        events: vec![],
+        warnings: vec![],
+    };
+    parser.blocks(&lines);
+    ParsedSource {
+        front_matter: Ok(front_matter),
+        body_start,
+        events: parser.events,
+        warnings: parser.warnings,
+    }
+}
+
+/// Front matter from the field list at the start of the note, and the offset of the body.
+fn field_list(source: &str) -> (FrontMatter, usize) {
+    let mut map = Map::new();
+    let mut body_start = 0;
+    let mut offset = 0;
+    for line in source.split_inclusive('\n') {
+        offset += line.len();
+        if line.trim().is_empty() {
+            continue;
+        }
+        let (key, value) = match field(line.trim_end()) {
+            Some(field) => field,
+            None => break,
+        };
+        body_start = offset;
+
+        let key = key.to_lowercase();
+        let value = match key.as_str() {
+            "tags" => Value::Array(
+                value
+                    .split(',')
+                    .map(str::trim)
+                    .filter(|tag| !tag.is_empty())
+                    .map(|tag| Value::String(tag.to_string()))
+                    .collect(),
+            ),
+            _ => Value::String(value.to_string()),
+        };
+        map.entry(key).or_insert(value);
+    }
+    (FrontMatter(map), body_start)
+}
+
+/// Name and value of a `:name: value` field.
+fn field(line: &str) -> Option<(&str, &str)> {
+    let rest = line.strip_prefix(':')?;
+    let end = rest.find(':')?;
+    let (name, value) = (&rest[..end], &rest[end + 1..]);
+    // A role such as :code:`x` isn't a field.
+    if name.is_empty() || name.starts_with(' ') || !(value.is_empty() || value.starts_with(' ')) {
+        return None;
+    }
+    Some((name, value.trim()))
+}
+
+/// Lines of the text without their line endings.
+fn lines(text: &str) -> Vec<&str> {
+    text.split_inclusive('\n')
+        .map(|line| line.trim_end_matches(['\n', '\r']))
+        .collect()
+}
+
+fn indent(line: &str) -> usize {
+    line.len() - line.trim_start().len()
+}
+
+fn is_blank(line: &str) -> bool {
+    line.trim().is_empty()
+}
+
+/// Line after the indented block starting at `from`, which doesn't include trailing blank lines.
+fn indented_end(lines: &[&str], from: usize) -> usize {
+    let mut end = from;
+    for (i, line) in lines.iter().enumerate().skip(from) {
+        if is_blank(line) {
+            continue;
+        }
+        if indent(line) == 0 {
+            break;
+        }
+        end = i + 1;
+    }
+    end
+}
+
+/// Lines with the indentation that all of them have removed.
+fn dedent_lines<'a>(lines: &[&'a str]) -> Vec<&'a str> {
+    let common = lines
+        .iter()
+        .filter(|line| !is_blank(line))
+        .map(|line| indent(line))
+        .min()
+        .unwrap_or(0);
+    lines
+        .iter()
+        .map(|line| &line[indent(line).min(common)..])
+        .collect()
+}
+
+fn next_non_blank(lines: &[&str], from: usize) -> Option<usize> {
+    (from..lines.len()).find(|&i| !is_blank(lines[i]))
+}
+
+/// Character of a line that only repeats one punctuation character.
+fn adornment(line: &str) -> Option<char> {
+    let line = line.trim_end();
+    let first = line.chars().next()?;
+    if line.len() >= 2 && ADORNMENT_CHARS.contains(first) && line.chars().all(|c| c == first) {
+        Some(first)
+    } else {
+        None
+    }
+}
+
+/// Section title at line `i`, as the style of its adornment, the title, and the number of lines
+/// it takes.
+fn section<'a>(lines: &[&'a str], i: usize) -> Option<((char, bool), &'a str, usize)> {
+    let line = *lines.get(i)?;
+    let adornment_at = |j: usize| lines.get(j).and_then(|line| adornment(line));
+    match adornment(line) {
+        Some(c) => {
+            let title = lines.get(i + 1)?.trim();
+            if !title.is_empty() && adornment(title).is_none() && adornment_at(i + 2) == Some(c) {
+                Some(((c, true), title, 3))
+            } else {
+                None
+            }
+        }
+        None if is_blank(line) || indent(line) > 0 => None,
+        None => adornment_at(i + 1).map(|c| ((c, false), line.trim_end(), 2)),
+    }
+}
+
+/// Whether line `i` is a transition, which is a line of punctuation between blank lines.
+fn is_transition(lines: &[&str], i: usize) -> bool {
+    let around_blank =
+        (i == 0 || is_blank(lines[i - 1])) && lines.get(i + 1).is_none_or(|line| is_blank(line));
+    adornment(lines[i]).is_some() && lines[i].trim_end().len() >= 4 && around_blank
+}
+
+fn is_simple_table_border(line: &str) -> bool {
+    let line = line.trim_end();
+    line.starts_with('=') && line.contains(' ') && line.chars().all(|c| c == '=' || c == ' ')
+}
+
+/// List item at the start of a line, as its number if it's enumerated, the character that
+/// tells it apart from other kinds of items, and the column of its content.
+fn list_item(line: &str) -> Option<(Option<usize>, char, usize)> {
+    let first = line.chars().next()?;
+    let (number, kind, marker_len) = if "-*+•".contains(first) {
+        (None, first, first.len_utf8())
+    } else {
+        let (open, rest) = match line.strip_prefix('(') {
+            Some(rest) => (true, rest),
+            None => (false, line),
+        };
+        let digits = match rest.strip_prefix('#') {
+            Some(_) => 1,
+            None => rest.len() - rest.trim_start_matches(|c: char| c.is_ascii_digit()).len(),
+        };
+        let suffix = rest[digits..].chars().next()?;
+        let valid = if open {
+            suffix == ')'
+        } else {
+            suffix == '.' || suffix == ')'
+        };
+        if digits == 0 || !valid {
+            return None;
+        }
+        let number = rest[..digits].parse().unwrap_or(1);
+        let kind = if open { '(' } else { suffix };
+        (Some(number), kind, open as usize + digits + 1)
+    };
+    let rest = &line[marker_len..];
+    if !rest.is_empty() && !rest.starts_with(' ') {
+        return None;
+    }
+    let content = if rest.trim().is_empty() {
+        marker_len + 1
+    } else {
+        marker_len + indent(rest)
+    };
+    Some((number, kind, content))
+}
+
+/// Name and argument of a `name:: argument` directive, after the `.. ` that starts it.
+fn directive(text: &str) -> Option<(&str, &str)> {
+    let (name, argument) = text.split_once("::")?;
+    let is_name = |c: char| c.is_alphanumeric() || "-_:.+".contains(c);
+    if !name.is_empty() && name.chars().all(is_name) {
+        Some((name, argument.trim()))
+    } else {
+        None
+    }
+}
+
+/// Name of a hyperlink target, as its references are looked up.
+fn normalize(name: &str) -> String {
+    let words: Vec<&str> = name.split_whitespace().collect();
+    words.join(" ").to_lowercase()
+}
+
+/// Destinations of the named hyperlink targets and sections, by their normalized names.
+///
+/// Targets without a URL, like `.. _name:`, link to the section after them.
+fn targets(lines: &[&str]) -> HashMap<String, String> {
+    let mut targets = HashMap::new();
+    for (i, line) in lines.iter().enumerate() {
+        if let Some((_, title, _)) = section(lines, i) {
+            targets
+                .entry(normalize(title))
+                .or_insert_with(|| format!("#{}", HeaderStart::new(1, title).id()));
+        }
+        let target = match line.trim_start().strip_prefix(".. _") {
+            Some(target) if !target.starts_with('_') => target,
+            _ => continue,
+        };
+        let split = match target.strip_prefix('`') {
+            Some(target) => target.split_once("`:"),
+            None => target.split_once(':'),
+        };
+        let (name, url) = match split {
+            Some((name, url)) => (normalize(name), url.trim()),
+            None => continue,
+        };
+        let dest = match url {
+            "" => (i + 1..lines.len())
+                .filter(|&j| !is_blank(lines[j]) && !lines[j].starts_with(".. _"))
+                .find_map(|j| section(lines, j))
+                .map(|(_, title, _)| format!("#{}", HeaderStart::new(1, title).id())),
+            url => Some(url.to_string()),
+        };
+        if let Some(dest) = dest {
+            targets.insert(name, dest);
+        }
+    }
+
+    // Targets that refer to other targets, like `.. _a: b_`.
+    let aliases: Vec<(String, String)> = targets
+        .iter()
+        .filter_map(|(name, dest)| {
+            let alias = dest.strip_suffix('_')?.trim_matches('`');
+            let dest = targets.get(&normalize(alias))?;
+            Some((name.clone(), dest.clone()))
+        })
+        .collect();
+    targets.extend(aliases);
+    targets
+}
+
+/// Title and target of `title <target>` in a role or reference.
+fn split_title(text: &str) -> (&str, &str) {
+    if let Some(inner) = text.strip_suffix('>') {
+        if let Some(open) = inner.rfind('<') {
+            let (title, target) = (inner[..open].trim(), &inner[open + 1..]);
+            return if title.is_empty() {
+                (target, target)
+            } else {
+                (title, target)
+            };
+        }
+    }
+    (text, text)
+}
+
+/// Inline markup, by what it contains.
+enum Markup<'a> {
+    Literal(&'a str),
+    Strong(&'a str),
+    Emphasis(&'a str),
+    /// Interpreted text with its role, which is empty for the default role.
+    Role(&'a str, &'a str),
+    Reference(&'a str),
+    Url(&'a str),
+}
+
+/// Inline markup starting at `pos`, and the offset after it.
+fn markup(text: &str, pos: usize) -> Option<(Markup<'_>, usize)> {
+    let rest = &text[pos..];
+    let before = text[..pos].chars().next_back();
+    if !before.is_none_or(|c| c.is_whitespace() || MARKUP_PRE.contains(c)) {
+        return None;
+    }
+
+    if rest.starts_with("``") {
+        let end = find_end(text, pos + 2, "``")?;
+        return Some((Markup::Literal(&text[pos + 2..end]), end + 2));
+    }
+    if rest.starts_with("**") {
+        let end = find_end(text, pos + 2, "**")?;
+        return Some((Markup::Strong(&text[pos + 2..end]), end + 2));
+    }
+    if rest.starts_with('*') {
+        let end = find_end(text, pos + 1, "*")?;
+        return Some((Markup::Emphasis(&text[pos + 1..end]), end + 1));
+    }
+    if rest.starts_with('`') {
+        let (inner, end, is_reference) = backquoted(text, pos + 1)?;
+        return if is_reference {
+            Some((Markup::Reference(inner), end))
+        } else {
+            Some((Markup::Role("", inner), end))
+        };
+    }
+    if rest.starts_with(':') {
+        let name_end = rest.find(":`")?;
+        let role = &rest[1..name_end];
+        if role.is_empty()
+            || !role
+                .chars()
+                .all(|c| c.is_alphanumeric() || "-_:.+".contains(c))
+        {
+            return None;
+        }
+        return match backquoted(text, pos + name_end + 2)? {
+            (inner, end, false) => Some((Markup::Role(role, inner), end)),
+            _ => None,
+        };
+    }
+    if rest.starts_with("http://") || rest.starts_with("https://") {
+        let len = rest
+            .find(|c: char| c.is_whitespace() || c == '<' || c == '>')
+            .unwrap_or(rest.len());
+        let url = rest[..len].trim_end_matches(|c: char| ".,:;!?'\")".contains(c));
+        return Some((Markup::Url(url), pos + url.len()));
+    }
+
+    // A reference to a target by a single word, like `name_`.
+    let len = rest
+        .find(|c: char| !(c.is_alphanumeric() || "-_.:+".contains(c)))
+        .unwrap_or(rest.len());
+    let word = rest[..len].trim_end_matches(['.', ':', '+']);
+    let name = word.strip_suffix('_')?;
+    let is_name = |c: Option<char>| c.is_some_and(char::is_alphanumeric);
+    if is_name(name.chars().next()) && is_name(name.chars().next_back()) {
+        Some((Markup::Reference(name), pos + word.len()))
+    } else {
+        None
+    }
+}
+
+/// Offset of the end string of inline markup with content starting at `from`.
+fn find_end(text: &str, from: usize, end_string: &str) -> Option<usize> {
+    let first = text.get(from..)?.chars().next()?;
+    if first.is_whitespace() {
+        return None;
+    }
+    let mut search = from + first.len_utf8();
+    while let Some(i) = text[search..].find(end_string) {
+        let end = search + i;
+        let before = text[..end].chars().next_back();
+        let after = text[end + end_string.len()..].chars().next();
+        if before.is_some_and(|c| !c.is_whitespace())
+            && after.is_none_or(|c| c.is_whitespace() || MARKUP_POST.contains(c))
+        {
+            return Some(end);
+        }
+        search = end + 1;
+    }
+    None
+}
+
+/// Content of backquoted text starting at `from`, the offset after it, and whether it's a
+/// reference, which is followed by `_` or `__`.
+fn backquoted(text: &str, from: usize) -> Option<(&str, usize, bool)> {
+    let first = text.get(from..)?.chars().next()?;
+    if first.is_whitespace() {
+        return None;
+    }
+    let mut search = from + first.len_utf8();
+    while let Some(i) = text[search..].find('`') {
+        let end = search + i;
+        let suffix = &text[end + 1..];
+        let suffix_len = suffix.len() - suffix.trim_start_matches('_').len();
+        let after = suffix[suffix_len..].chars().next();
+        let before = text[..end].chars().next_back();
+        if suffix_len <= 2
+            && before.is_some_and(|c| !c.is_whitespace())
+            && after.is_none_or(|c| c.is_whitespace() || MARKUP_POST.contains(c))
+        {
+            return Some((&text[from..end], end + 1 + suffix_len, suffix_len > 0));
+        }
+        search = end + 1;
+    }
+    None
+}
+
+/// Lines without the blank lines at their start and end.
+fn trim_blank<'a, 'b>(lines: &'b [&'a str]) -> &'b [&'a str] {
+    let start = lines
+        .iter()
+        .position(|line| !is_blank(line))
+        .unwrap_or(lines.len());
+    let end = lines
+        .iter()
+        .rposition(|line| !is_blank(line))
+        .map_or(start, |i| i + 1);
+    &lines[start..end]
+}
+
+/// Code of a literal block from its lines, without blank lines around it.
+fn code_text<'a>(lines: &[&str]) -> CowStr<'a> {
+    let mut code = String::new();
+    for line in trim_blank(lines) {
+        code.push_str(line);
+        code.push('\n');
+    }
+    CowStr::Boxed(code.into_boxed_str())
+}
+
+/// Info string of a code block from the options of a `code-block` directive.
+fn code_info(language: &str, options: &[(&str, &str)]) -> String {
+    let mut info = language.to_string();
+    // The attributes would be taken as the language without one.
+    if language.is_empty() {
+        return info;
+    }
+    for (name, value) in options {
+        let attribute = match *name {
+            "linenos" => String::from("linenos"),
+            "lineno-start" => format!("start={}", value),
+            "emphasize-lines" => format!("hl={}", value.replace(',', ";").replace(' ', "")),
+            "caption" => format!("title=\"{}\"", value.replace('"', "'")),
+            _ => continue,
+        };
+        info.push(' ');
+        info.push_str(&attribute);
+    }
+    info
+}
+
+/// Row of a table, as the pieces of text in each cell, which may span several lines.
+type Row<'a> = Vec<Vec<&'a str>>;
+
+/// Cell between the character columns `from` and `to` of the line, which goes to the end of the
+/// line without `to`.
+fn cell(line: &str, from: usize, to: Option<usize>) -> &str {
+    let byte = |column: usize| {
+        line.char_indices()
+            .nth(column)
+            .map_or(line.len(), |(i, _)| i)
+    };
+    let start = byte(from);
+    let end = to.map_or(line.len(), byte);
+    line.get(start..end).unwrap_or_default().trim()
+}
+
+struct Parser<'a> {
+    body: &'a str,
+    targets: HashMap<String, String>,
+    /// Adornment styles of the section titles in the order they were first seen, which gives
+    /// their levels.
+    styles: Vec<(char, bool)>,
+    /// Language of literal blocks, set with the `highlight` directive.
+    highlight: &'a str,
+    events: Vec<(Event<'a>, Range<usize>)>,
+    warnings: Vec<(usize, String)>,
+}
+
+impl<'a> Parser<'a> {
+    fn push(&mut self, event: Event<'a>, range: Range<usize>) {
+        self.events.push((event, range));
+    }
+
+    fn warn(&mut self, offset: usize, message: String) {
+        self.warnings.push((offset, message));
+    }
+
+    /// Range in the body of text that's a slice of it.
+    fn range(&self, text: &str) -> Range<usize> {
+        let start = text.as_ptr() as usize - self.body.as_ptr() as usize;
+        start..start + text.len()
+    }
+
+    /// Range from the start of the first line to the end of the last.
+    fn span(&self, lines: &[&str]) -> Range<usize> {
+        match lines {
+            [] => 0..0,
+            [first, .., last] => self.range(first).start..self.range(last).end,
+            [line] => self.range(line),
+        }
+    }
+
+    fn blocks(&mut self, lines: &[&'a str]) {
+        let mut i = 0;
+        while i < lines.len() {
+            let line = lines[i];
+            if is_blank(line) {
+                i += 1;
+            } else if indent(line) > 0 {
+                let end = indented_end(lines, i);
+                let range = self.span(&lines[i..end]);
+                self.push(Event::Start(Tag::BlockQuote), range.clone());
+                self.blocks(&dedent_lines(&lines[i..end]));
+                self.push(Event::End(Tag::BlockQuote), range);
+                i = end;
+            } else if let Some((style, title, len)) = section(lines, i) {
+                self.section(style, title, lines[i + len - 1]);
+                i += len;
+            } else if is_transition(lines, i) {
+                let range = self.range(line);
+                self.push(Event::Start(Tag::Rule), range.clone());
+                self.push(Event::End(Tag::Rule), range);
+                i += 1;
+            } else if line.trim_end() == ".." || line.starts_with(".. ") {
+                i = self.explicit_markup(lines, i);
+            } else if line.starts_with("+-") {
+                let end = (i..lines.len())
+                    .find(|&j| !lines[j].starts_with(['+', '|']))
+                    .unwrap_or(lines.len());
+                self.grid_table(&lines[i..end]);
+                i = end;
+            } else if is_simple_table_border(line) {
+                i = self.simple_table(lines, i);
+            } else if list_item(line).is_some() {
+                i = self.list(lines, i);
+            } else if line.starts_with(">>>") {
+                let end = (i..lines.len())
+                    .find(|&j| is_blank(lines[j]))
+                    .unwrap_or(lines.len());
+                self.code_block(String::from("python"), &lines[i..end]);
+                i = end;
+            } else {
+                i = self.paragraph(lines, i);
+            }
+        }
+    }
+
+    fn section(&mut self, style: (char, bool), title: &'a str, underline: &str) {
+        let level = match self.styles.iter().position(|s| *s == style) {
+            Some(level) => level + 1,
+            None => {
+                self.styles.push(style);
+                self.styles.len()
+            }
+        };
+        if underline.trim_end().chars().count() < title.chars().count() {
+            let offset = self.range(underline).start;
+            self.warn(
+                offset,
+                String::from("title underline is shorter than the title"),
+            );
+        }
+        let level = level.min(MAX_HEADING_LEVEL) as i32;
+        let range = self.range(title);
+        self.push(Event::Start(Tag::Header(level)), range.clone());
+        self.inline(title);
+        self.push(Event::End(Tag::Header(level)), range);
+    }
+
+    /// Parse the paragraph starting at line `i`, and the literal block after it if it ends with
+    /// `::`, returning the line after them.
+    fn paragraph(&mut self, lines: &[&'a str], i: usize) -> usize {
+        let mut end = i + 1;
+        while end < lines.len()
+            && !is_blank(lines[end])
+            && indent(lines[end]) == 0
+            && section(lines, end).is_none()
+        {
+            end += 1;
+        }
+
+        let range = self.span(&lines[i..end]);
+        let mut text = &self.body[range.clone()];
+        let is_literal = text.trim_end().ends_with("::");
+        if is_literal {
+            // `text::` keeps one colon, while `text ::` and `::` on its own don't keep any.
+            let trimmed = text.trim_end();
+            let is_separate = trimmed == "::" || trimmed.ends_with(" ::");
+            text = trimmed[..trimmed.len() - if is_separate { 2 } else { 1 }].trim_end();
+        }
+        if !text.is_empty() {
+            self.push(Event::Start(Tag::Paragraph), range.clone());
+            self.inline(text);
+            self.push(Event::End(Tag::Paragraph), range);
+        }
+
+        match next_non_blank(lines, end) {
+            Some(j) if is_literal && indent(lines[j]) > 0 => {
+                let block_end = indented_end(lines, j);
+                let info = self.highlight.to_string();
+                self.code_block(info, &dedent_lines(&lines[j..block_end]));
+                block_end
+            }
+            _ => end,
+        }
+    }
+
+    fn code_block(&mut self, info: String, lines: &[&'a str]) {
+        let tag = Tag::CodeBlock(info.into());
+        let range = self.span(trim_blank(lines));
+        self.push(Event::Start(tag.clone()), range.clone());
+        self.push(Event::Text(code_text(lines)), range.clone());
+        self.push(Event::End(tag), range);
+    }
+
+    /// Parse the list starting at line `i`, returning the line after it.
+    fn list(&mut self, lines: &[&'a str], mut i: usize) -> usize {
+        let (number, kind, _) = list_item(lines[i]).unwrap();
+        let list_start = self.range(lines[i]).start;
+        let start_range = self.range(lines[i]);
+        self.push(Event::Start(Tag::List(number)), start_range);
+
+        loop {
+            let line = lines[i];
+            let (_, _, content) = list_item(line).unwrap();
+            // The item continues on the lines indented under it, which can be separated by blank
+            // lines.
+            let end = indented_end(lines, i + 1);
+            let mut item_lines = vec![line.get(content..).unwrap_or_default()];
+            item_lines.extend(
+                lines[i + 1..end]
+                    .iter()
+                    .map(|line| &line[indent(line).min(content)..]),
+            );
+
+            let item_range = self.range(line);
+            self.push(Event::Start(Tag::Item), item_range.clone());
+            let first_event = self.events.len();
+            self.blocks(&item_lines);
+            self.tighten(first_event);
+            self.push(Event::End(Tag::Item), item_range);
+            i = end;
+
+            match next_non_blank(lines, i) {
+                Some(j) if list_item(lines[j]).is_some_and(|item| item.1 == kind) => i = j,
+                _ => break,
+            }
+        }
+
+        let list_end = self.range(lines[i - 1]).end;
+        self.push(Event::End(Tag::List(number)), list_start..list_end);
+        i
+    }
+
+    /// Remove the paragraph around the content of a list item when it's the only one, so
+    /// simple lists are as compact as tight markdown lists.
+    fn tighten(&mut self, from: usize) {
+        let mut depth = 0;
+        let mut paragraphs = vec![];
+        for (i, (event, _)) in self.events[from..].iter().enumerate() {
+            match event {
+                Event::Start(tag) => {
+                    if depth == 0 && *tag == Tag::Paragraph {
+                        paragraphs.push(from + i);
+                    }
+                    depth += 1;
+                }
+                Event::End(tag) => {
+                    depth -= 1;
+                    if depth == 0 && *tag == Tag::Paragraph {
+                        paragraphs.push(from + i);
+                    }
+                }
+                _ => {}
+            }
+        }
+        if let [start, end] = paragraphs[..] {
+            self.events.remove(end);
+            self.events.remove(start);
+        }
+    }
+
+    fn grid_table(&mut self, lines: &[&'a str]) {
+        let columns: Vec<usize> = lines[0]
+            .chars()
+            .enumerate()
+            .filter(|(_, c)| *c == '+')
+            .map(|(i, _)| i)
+            .collect();
+        let mut rows: Vec<Row<'a>> = vec![];
+        let mut head_len = None;
+        let mut row: Option<Row<'a>> = None;
+        for line in &lines[1..] {
+            if line.starts_with('+') {
+                rows.extend(row.take());
+                if line.contains('=') {
+                    head_len = Some(rows.len());
+                }
+                continue;
+            }
+            let row = row.get_or_insert_with(|| vec![vec![]; columns.len() - 1]);
+            for (cells, bounds) in row.iter_mut().zip(columns.windows(2)) {
+                let text = cell(line, bounds[0] + 1, Some(bounds[1]));
+                if !text.is_empty() {
+                    cells.push(text);
+                }
+            }
+        }
+        rows.extend(row);
+        let range = self.span(lines);
+        self.table(rows, head_len.unwrap_or(1), range);
+    }
+
+    /// Parse the simple table with its top border at line `i`, returning the line after it.
+    fn simple_table(&mut self, lines: &[&'a str], i: usize) -> usize {
+        let border = lines[i];
+        let columns: Vec<usize> = border
+            .char_indices()
+            .filter(|&(j, c)| c == '=' && (j == 0 || border[..j].ends_with(' ')))
+            .map(|(j, _)| border[..j].chars().count())
+            .collect();
+
+        let mut rows: Vec<Row<'a>> = vec![];
+        let mut borders = 0;
+        let mut head_len = None;
+        let mut end = i + 1;
+        while end < lines.len() && !is_blank(lines[end]) {
+            let line = lines[end];
+            end += 1;
+            if is_simple_table_border(line) {
+                borders += 1;
+                head_len.get_or_insert(rows.len());
+                continue;
+            }
+            let cells: Row<'a> = (0..columns.len())
+                .map(|k| {
+                    let text = cell(line, columns[k], columns.get(k + 1).copied());
+                    if text.is_empty() {
+                        vec![]
+                    } else {
+                        vec![text]
+                    }
+                })
+                .collect();
+            // A row without text in its first column continues the row before it.
+            match rows.last_mut() {
+                Some(last) if cells[0].is_empty() => {
+                    for (last, cell) in last.iter_mut().zip(cells) {
+                        last.extend(cell);
+                    }
+                }
+                _ => rows.push(cells),
+            }
+        }
+
+        // Without a border between the head and the body, the first row is the head.
+        let head_len = match borders {
+            0 | 1 => 1,
+            _ => head_len.unwrap_or(1),
+        };
+        let range = self.span(&lines[i..end]);
+        self.table(rows, head_len, range);
+        end
+    }
+
+    /// Table with the first `head_len` rows merged into its head, as a table always has one row
+    /// in its head when it's rendered.
+    fn table(&mut self, rows: Vec<Row<'a>>, head_len: usize, range: Range<usize>) {
+        let columns = rows.iter().map(Vec::len).max().unwrap_or(0);
+        if columns == 0 {
+            return;
+        }
+        let head_len = head_len.clamp(1, rows.len());
+        let mut head: Row<'a> = vec![vec![]; columns];
+        for row in &rows[..head_len] {
+            for (cells, text) in head.iter_mut().zip(row) {
+                cells.extend(text);
+            }
+        }
+
+        self.push(
+            Event::Start(Tag::Table(vec![Alignment::None; columns])),
+            range.clone(),
+        );
+        self.row(Tag::TableHead, &head, columns, &range);
+        for row in &rows[head_len..] {
+            self.row(Tag::TableRow, row, columns, &range);
+        }
+        self.push(Event::End(Tag::Table(vec![])), range);
+    }
+
+    fn row(&mut self, tag: Tag<'a>, row: &[Vec<&'a str>], columns: usize, range: &Range<usize>) {
+        self.push(Event::Start(tag.clone()), range.clone());
+        for column in 0..columns {
+            let pieces = row.get(column).map_or(&[][..], Vec::as_slice);
+            let cell_range = match pieces {
+                [] => range.clone(),
+                pieces => self.span(pieces),
+            };
+            self.push(Event::Start(Tag::TableCell), cell_range.clone());
+            for (i, piece) in pieces.iter().enumerate() {
+                if i > 0 {
+                    self.push(Event::SoftBreak, cell_range.clone());
+                }
+                self.inline(piece);
+            }
+            self.push(Event::End(Tag::TableCell), cell_range);
+        }
+        self.push(Event::End(tag), range.clone());
+    }
+
+    /// Parse the explicit markup starting with `..` at line `i`, which is a directive, a target
+    /// or a comment, returning the line after it.
+    fn explicit_markup(&mut self, lines: &[&'a str], i: usize) -> usize {
+        let line = lines[i];
+        let end = indented_end(lines, i + 1);
+        let text = line.get(3..).unwrap_or_default();
+        let offset = self.range(line).start;
+        if text.starts_with('_') {
+            // Targets are looked up by their references.
+        } else if text.starts_with('[') {
+            self.warn(
+                offset,
+                String::from("footnotes and citations aren't supported"),
+            );
+        } else if text.starts_with('|') {
+            self.warn(offset, String::from("substitutions aren't supported"));
+        } else if let Some((name, argument)) = directive(text) {
+            self.directive(name, argument, &lines[i + 1..end], offset);
+        }
+        end
+    }
+
+    fn directive(&mut self, name: &'a str, argument: &'a str, body: &[&'a str], offset: usize) {
+        let body = dedent_lines(body);
+        // Options are the field list at the start of the body.
+        let options_len = body
+            .iter()
+            .position(|line| field(line).is_none())
+            .unwrap_or(body.len());
+        let options: Vec<(&str, &str)> = body[..options_len]
+            .iter()
+            .filter_map(|line| field(line))
+            .collect();
+        let content = &body[options_len..];
+
+        match name {
+            "code-block" | "code" | "sourcecode" => {
+                let language = match argument {
+                    "" => self.highlight,
+                    language => language,
+                };
+                self.code_block(code_info(language, &options), content);
+            }
+            "highlight" => self.highlight = argument,
+            "image" | "figure" => {
+                let alt = options
+                    .iter()
+                    .find(|(name, _)| *name == "alt")
+                    .map_or("", |(_, alt)| alt);
+                let range = offset..offset + argument.len();
+                let tag = Tag::Image(LinkType::Inline, argument.into(), "".into());
+                self.push(Event::Start(Tag::Paragraph), range.clone());
+                self.push(Event::Start(tag.clone()), range.clone());
+                if !alt.is_empty() {
+                    self.push(Event::Text(alt.into()), range.clone());
+                }
+                self.push(Event::End(tag), range.clone());
+                self.push(Event::End(Tag::Paragraph), range);
+                // The caption and legend of a figure.
+                self.blocks(content);
+            }
+            "math" => {
+                let mut math = vec![argument];
+                math.extend(content);
+                let tex = code_text(&math);
+                let range = self.span(content);
+                self.push(Event::Start(Tag::Paragraph), range.clone());
+                let text = format!("$${}$$", tex.trim());
+                self.push(Event::Text(CowStr::Boxed(text.into())), range.clone());
+                self.push(Event::End(Tag::Paragraph), range);
+            }
+            "raw" => {
+                if argument.eq_ignore_ascii_case("html") {
+                    let range = self.span(content);
+                    self.push(Event::Html(code_text(content)), range);
+                }
+            }
+            "admonition" => self.admonition("note", argument, &[], content),
+            _ if ADMONITIONS.contains(&name) => {
+                // The argument is the start of the first paragraph.
+                let mut lines = vec![argument];
+                lines.extend(content);
+                let title = match name {
+                    "seealso" => "See also",
+                    _ => "",
+                };
+                self.admonition(name, title, &lines, &[]);
+            }
+            _ => self.warn(
+                offset,
+                format!("unsupported directive `{}` isn't rendered", name),
+            ),
+        }
+    }
+
+    /// Blockquote with the marker of a callout, followed by its content.
+    fn admonition(&mut self, kind: &str, title: &str, first: &[&'a str], rest: &[&'a str]) {
+        let range = self.span(if first.is_empty() { rest } else { first });
+        let marker = format!("[!{}] {}", kind.to_uppercase(), title);
+        self.push(Event::Start(Tag::BlockQuote), range.clone());
+        self.push(Event::Start(Tag::Paragraph), range.clone());
+        self.push(
+            Event::Text(CowStr::Boxed(marker.trim_end().into())),
+            range.clone(),
+        );
+        self.push(Event::End(Tag::Paragraph), range.clone());
+        self.blocks(first);
+        self.blocks(rest);
+        self.push(Event::End(Tag::BlockQuote), range);
+    }
+
+    /// Parse the inline markup in the text, which is a slice of the body.
+    fn inline(&mut self, text: &'a str) {
+        let mut pos = 0;
+        let mut text_start = 0;
+        while pos < text.len() {
+            let rest = &text[pos..];
+            let c = rest.chars().next().unwrap();
+            if c == '\n' {
+                self.text(text[text_start..pos].trim_end());
+                let range = self.range(&rest[..1]);
+                self.push(Event::SoftBreak, range);
+                pos += 1;
+                pos += text[pos..].len() - text[pos..].trim_start_matches([' ', '\t']).len();
+                text_start = pos;
+                continue;
+            }
+            if c == '\\' {
+                self.text(&text[text_start..pos]);
+                pos += 1;
+                text_start = pos;
+                match text[pos..].chars().next() {
+                    // An escaped space is removed.
+                    Some(' ') => {
+                        pos += 1;
+                        text_start = pos;
+                    }
+                    Some('\n') | None => {}
+                    Some(c) => pos += c.len_utf8(),
+                }
+                continue;
+            }
+            match markup(text, pos) {
+                Some((markup, end)) => {
+                    self.text(&text[text_start..pos]);
+                    self.markup(markup, &text[pos..end]);
+                    pos = end;
+                    text_start = pos;
+                }
+                None => pos += c.len_utf8(),
+            }
+        }
+        self.text(&text[text_start..]);
+    }
+
+    fn text(&mut self, text: &'a str) {
+        if !text.is_empty() {
+            let range = self.range(text);
+            self.push(Event::Text(text.into()), range);
+        }
+    }
+
+    fn markup(&mut self, markup: Markup<'a>, whole: &'a str) {
+        let range = self.range(whole);
+        match markup {
+            Markup::Literal(code) => self.wrap(Tag::Code, code, range),
+            Markup::Strong(text) => {
+                self.push(Event::Start(Tag::Strong), range.clone());
+                self.inline(text);
+                self.push(Event::End(Tag::Strong), range);
+            }
+            Markup::Emphasis(text) => {
+                self.push(Event::Start(Tag::Emphasis), range.clone());
+                self.inline(text);
+                self.push(Event::End(Tag::Emphasis), range);
+            }
+            Markup::Role(role, text) => self.role(role, text, range),
+            Markup::Reference(text) => {
+                let (title, target) = split_title(text);
+                // An embedded target is a URL, unless it's a reference like `<name_>`.
+                let dest = match target.strip_suffix('_') {
+                    Some(name) if target != title => self.targets.get(&normalize(name)).cloned(),
+                    _ if target != title => Some(target.to_string()),
+                    _ => self.targets.get(&normalize(target)).cloned(),
+                };
+                match dest {
+                    Some(dest) => self.link(dest.into(), title, range),
+                    None => {
+                        self.warn(range.start, format!("unknown link target `{}`", target));
+                        self.text(title);
+                    }
+                }
+            }
+            Markup::Url(url) => self.link(url.into(), url, range),
+        }
+    }
+
+    /// Interpreted text with its role, such as :code:`x` or :doc:`other`.
+    fn role(&mut self, role: &str, text: &'a str, range: Range<usize>) {
+        let (title, target) = split_title(text);
+        match role {
+            "" | "emphasis" | "title-reference" | "t" => self.wrap(Tag::Emphasis, text, range),
+            "strong" => self.wrap(Tag::Strong, text, range),
+            "math" => {
+                let math = format!("${}$", text);
+                self.push(Event::Text(CowStr::Boxed(math.into())), range);
+            }
+            "doc" => {
+                let dest = if target.rsplit('/').next().unwrap_or_default().contains('.') {
+                    target.to_string()
+                } else {
+                    format!("{}.rst", target)
+                };
+                self.link(dest.into(), title, range);
+            }
+            "ref" => match self.targets.get(&normalize(target)).cloned() {
+                Some(dest) => self.link(dest.into(), title, range),
+                None => {
+                    self.warn(range.start, format!("unknown link target `{}`", target));
+                    self.text(title);
+                }
+            },
+            _ if CODE_ROLES.contains(&role) => self.wrap(Tag::Code, text, range),
+            _ => self.text(title),
+        }
+    }
+
+    /// Text inside a tag, without looking for markup in it.
+    fn wrap(&mut self, tag: Tag<'a>, text: &'a str, range: Range<usize>) {
+        self.push(Event::Start(tag.clone()), range.clone());
+        self.text(text);
+        self.push(Event::End(tag), range);
+    }
+
+    fn link(&mut self, dest: CowStr<'a>, title: &'a str, range: Range<usize>) {
+        let tag = Tag::Link(LinkType::Inline, dest, "".into());
+        self.wrap(tag, title, range);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use pulldown_cmark::html;
+
+    fn to_html(source: &str) -> String {
+        let mut html = String::new();
+        html::push_html(
+            &mut html,
+            parse(source).events.into_iter().map(|(event, _)| event),
+        );
+        html
+    }
+
+    #[test]
+    fn field_list_is_front_matter() {
+        let parsed = parse(":title: A Note\n:tags: a, b\n\nBody\n");
+        let front_matter = parsed.front_matter.unwrap();
+        assert_eq!(front_matter.title(), Some("A Note"));
+        assert_eq!(
+            front_matter.get("tags"),
+            Some(&Value::Array(vec![Value::from("a"), Value::from("b")]))
+        );
+        assert_eq!(parsed.body_start, 27);
+    }
+
+    #[test]
+    fn sections_and_inline_markup() {
+        let source = "=====\nTitle\n=====\n\nPart\n----\n\nSome **bold**, *it*, ``co*de`` \
+                      and `cited`\ntext with \\*stars\\*.\n\nOther\n-----\n";
+        assert_eq!(
+            to_html(source),
+            "<h1>Title</h1>\n<h2>Part</h2>\n<p>Some <strong>bold</strong>, <em>it</em>, \
+             <code>co*de</code> and <em>cited</em>\ntext with *stars*.</p>\n<h2>Other</h2>\n"
+        );
+        let warnings = parse("Long title\n---\n").warnings;
+        assert_eq!(warnings.len(), 1);
+    }
+
+    #[test]
+    fn lists_and_literal_blocks() {
+        let source = "- one\n  continued\n\n  #. nested\n  #. two\n\n- three\n\nCode::\n\n    \
+                      fn main() {}\n\n.. highlight:: rust\n\n::\n\n  let x = 1;\n";
+        assert_eq!(
+            to_html(source),
+            "<ul>\n<li>one\ncontinued\n<ol>\n<li>nested</li>\n<li>two</li>\n</ol>\n</li>\n\
+             <li>three</li>\n</ul>\n<p>Code:</p>\n<pre><code>fn main() {}\n</code></pre>\n\
+             <pre><code class=\"language-rust\">let x = 1;\n</code></pre>\n"
+        );
+    }
+
+    #[test]
+    fn hyperlinks() {
+        let source = "See `the site <https://x.org>`_, Python_, `Part`_, :doc:`guide` and \
+                      https://y.org.\n\n.. _Python: https://python.org\n\nPart\n====\n";
+        assert_eq!(
+            to_html(source),
+            "<p>See <a href=\"https://x.org\">the site</a>, \
+             <a href=\"https://python.org\">Python</a>, <a href=\"#part\">Part</a>, \
+             <a href=\"guide.rst\">guide</a> and <a href=\"https://y.org\">https://y.org</a>.</p>\n\
+             <h1>Part</h1>\n"
+        );
+        let parsed = parse("A missing_ link.\n");
+        assert_eq!(
+            parsed.warnings,
+            vec![(2, String::from("unknown link target `missing`"))]
+        );
+    }
+
+    #[test]
+    fn tables() {
+        let grid = "+-----+-------+\n| A   | B     |\n+=====+=======+\n| 1   | two   |\n\
+                    |     | lines |\n+-----+-------+\n";
+        assert_eq!(
+            to_html(grid),
+            "<table><thead><tr><th>A</th><th>B</th></tr></thead><tbody>\n\
+             <tr><td>1</td><td>two\nlines</td></tr>\n</tbody></table>\n"
+        );
+        let simple = "===  ===\nA    B\n===  ===\n1    ``2``\n===  ===\n";
+        assert_eq!(
+            to_html(simple),
+            "<table><thead><tr><th>A</th><th>B</th></tr></thead><tbody>\n\
+             <tr><td>1</td><td><code>2</code></td></tr>\n</tbody></table>\n"
+        );
+    }
+
+    #[test]
+    fn directives() {
+        let source =
+            ".. note:: Read this\n   first.\n\n.. code-block:: python\n   :linenos:\n\n   \
+                      print(1)\n\n.. toctree::\n   :maxdepth: 2\n\n.. a comment\n";
+        let parsed = parse(source);
+        let mut html = String::new();
+        html::push_html(&mut html, parsed.events.into_iter().map(|(event, _)| event));
+        assert_eq!(
+            html,
+            "<blockquote>\n<p>[!NOTE]</p>\n<p>Read this\nfirst.</p>\n</blockquote>\n\
+             <pre><code class=\"language-python\">print(1)\n</code></pre>\n"
+        );
+        assert_eq!(
+            parsed.warnings,
+            vec![(
+                81,
+                String::from("unsupported directive `toctree` isn't rendered")
+            )]
+        );
+    }
+}
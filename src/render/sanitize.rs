@@ -0,0 +1,470 @@
+use pulldown_cmark::Event;
+use std::{io, str::FromStr};
+
+/// Elements that are kept, with the attributes allowed on them, when sanitizing raw HTML.
+const ALLOWED_TAGS: &[(&str, &[&str])] = &[
+    ("a", &["href", "name"]),
+    ("abbr", &[]),
+    ("b", &[]),
+    ("blockquote", &["cite"]),
+    ("br", &[]),
+    ("caption", &[]),
+    ("cite", &[]),
+    ("code", &[]),
+    ("col", &["span"]),
+    ("colgroup", &["span"]),
+    ("dd", &[]),
+    ("del", &["cite"]),
+    ("details", &["open"]),
+    ("dfn", &[]),
+    ("div", &[]),
+    ("dl", &[]),
+    ("dt", &[]),
+    ("em", &[]),
+    ("figcaption", &[]),
+    ("figure", &[]),
+    ("h1", &[]),
+    ("h2", &[]),
+    ("h3", &[]),
+    ("h4", &[]),
+    ("h5", &[]),
+    ("h6", &[]),
+    ("hr", &[]),
+    ("i", &[]),
+    ("img", &["src", "alt", "width", "height"]),
+    ("ins", &["cite"]),
+    ("kbd", &[]),
+    ("li", &["value"]),
+    ("mark", &[]),
+    ("ol", &["start", "reversed", "type"]),
+    ("p", &[]),
+    ("pre", &[]),
+    ("q", &["cite"]),
+    ("s", &[]),
+    ("samp", &[]),
+    ("small", &[]),
+    ("span", &[]),
+    ("strong", &[]),
+    ("sub", &[]),
+    ("summary", &[]),
+    ("sup", &[]),
+    ("table", &[]),
+    ("tbody", &[]),
+    ("td", &["colspan", "rowspan", "align"]),
+    ("tfoot", &[]),
+    ("th", &["colspan", "rowspan", "align"]),
+    ("thead", &[]),
+    ("time", &["datetime"]),
+    ("tr", &[]),
+    ("u", &[]),
+    ("ul", &[]),
+    ("var", &[]),
+];
+
+/// Attributes allowed on any of the allowed elements.
+const GLOBAL_ATTRIBUTES: &[&str] = &["class", "dir", "id", "lang", "title"];
+
+/// Attributes with URLs, which are only kept with a relative URL or an allowed scheme.
+const URL_ATTRIBUTES: &[&str] = &["cite", "href", "src"];
+const URL_SCHEMES: &[&str] = &["http", "https", "mailto"];
+
+/// Elements that are removed along with their content.
+const DROPPED_TAGS: &[&str] = &[
+    "embed", "iframe", "math", "noscript", "object", "script", "style", "svg", "template",
+    "textarea", "title", "xmp",
+];
+
+/// How raw HTML in notes is rendered.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum HtmlPolicy {
+    /// Raw HTML is rendered as is.
+    Trust,
+    /// Raw HTML is reduced to an allowlist of elements and attributes, which excludes scripts
+    /// and event handlers.
+    Sanitize,
+    /// Raw HTML is rendered as text.
+    Escape,
+}
+
+impl HtmlPolicy {
+    /// Apply the policy to an event from the markdown parser.
+    ///
+    /// Only raw HTML events are changed. The sanitizer keeps state between events since raw
+    /// HTML blocks are split into several events.
+    pub fn apply<'a>(&self, sanitizer: &mut Sanitizer, event: Event<'a>) -> Event<'a> {
+        match (self, event) {
+            (HtmlPolicy::Sanitize, Event::Html(html)) => {
+                Event::Html(sanitizer.sanitize(&html).into())
+            }
+            (HtmlPolicy::Sanitize, Event::InlineHtml(html)) => {
+                Event::InlineHtml(sanitizer.sanitize(&html).into())
+            }
+            (HtmlPolicy::Escape, Event::Html(html))
+            | (HtmlPolicy::Escape, Event::InlineHtml(html)) => Event::Text(html),
+            (_, event) => event,
+        }
+    }
+}
+
+impl FromStr for HtmlPolicy {
+    type Err = io::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "trust" => Ok(HtmlPolicy::Trust),
+            "sanitize" => Ok(HtmlPolicy::Sanitize),
+            "escape" => Ok(HtmlPolicy::Escape),
+            _ => Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                format!("unknown html policy `{}`", s),
+            )),
+        }
+    }
+}
+
+/// Allowlist sanitizer for raw HTML.
+#[derive(Debug, Default)]
+pub struct Sanitizer {
+    /// Dropped element whose content is being skipped, e.g. `script`.
+    dropping: Option<String>,
+    /// Whether a comment is being skipped.
+    in_comment: bool,
+}
+
+impl Sanitizer {
+    /// Whether the content of a dropped element is being skipped.
+    pub fn is_dropping(&self) -> bool {
+        self.dropping.is_some()
+    }
+
+    /// Forget elements and comments left open at the end of a block, so that an unclosed
+    /// `<style>` doesn't drop the rest of the note.
+    pub fn end_block(&mut self) {
+        self.dropping = None;
+        self.in_comment = false;
+    }
+
+    pub fn sanitize(&mut self, html: &str) -> String {
+        let mut sanitized = String::new();
+        let mut rest = html;
+        while !rest.is_empty() {
+            if self.in_comment {
+                match rest.find("-->") {
+                    Some(end) => {
+                        self.in_comment = false;
+                        rest = &rest[end + 3..];
+                    }
+                    None => rest = "",
+                }
+                continue;
+            }
+
+            let start = match rest.find('<') {
+                Some(start) => start,
+                None => {
+                    if self.dropping.is_none() {
+                        sanitized.push_str(rest);
+                    }
+                    break;
+                }
+            };
+            if self.dropping.is_none() {
+                sanitized.push_str(&rest[..start]);
+            }
+            rest = &rest[start..];
+
+            if rest.starts_with("<!--") {
+                self.in_comment = true;
+                rest = &rest[4..];
+                continue;
+            }
+            match parse_tag(rest) {
+                Some((tag, len)) => {
+                    self.push_tag(&mut sanitized, &tag);
+                    rest = &rest[len..];
+                }
+                None => {
+                    if self.dropping.is_none() {
+                        sanitized.push_str("&lt;");
+                    }
+                    rest = &rest[1..];
+                }
+            }
+        }
+        sanitized
+    }
+
+    fn push_tag(&mut self, sanitized: &mut String, tag: &HtmlTag) {
+        if let Some(ref dropping) = self.dropping {
+            if tag.closing && tag.name == *dropping {
+                self.dropping = None;
+            }
+            return;
+        }
+        if DROPPED_TAGS.contains(&tag.name.as_str()) {
+            if !tag.closing && !tag.self_closing {
+                self.dropping = Some(tag.name.clone());
+            }
+            return;
+        }
+
+        let allowed_attributes = match ALLOWED_TAGS.iter().find(|(name, _)| *name == tag.name) {
+            Some((_, attributes)) => attributes,
+            None => return,
+        };
+        if tag.closing {
+            sanitized.push_str(&format!("</{}>", tag.name));
+            return;
+        }
+
+        sanitized.push('<');
+        sanitized.push_str(&tag.name);
+        for (name, value) in &tag.attributes {
+            let name = name.as_str();
+            if !allowed_attributes.contains(&name) && !GLOBAL_ATTRIBUTES.contains(&name) {
+                continue;
+            }
+            match value {
+                Some(value) if URL_ATTRIBUTES.contains(&name) && !is_safe_url(value) => continue,
+                Some(value) => sanitized.push_str(&format!(
+                    " {}=\"{}\"",
+                    name,
+                    value
+                        .replace('"', "&quot;")
+                        .replace('<', "&lt;")
+                        .replace('>', "&gt;")
+                )),
+                None => sanitized.push_str(&format!(" {}", name)),
+            }
+        }
+        if tag.self_closing {
+            sanitized.push_str(" /");
+        }
+        sanitized.push('>');
+    }
+}
+
+#[derive(Debug, PartialEq)]
+struct HtmlTag {
+    /// Lowercase name of the element.
+    name: String,
+    attributes: Vec<(String, Option<String>)>,
+    closing: bool,
+    self_closing: bool,
+}
+
+/// Parse the tag at the start of `html`, along with its length.
+///
+/// Doctypes and processing instructions are parsed as tags without a name, so they're dropped.
+fn parse_tag(html: &str) -> Option<(HtmlTag, usize)> {
+    let mut chars = html.char_indices().skip(1).peekable();
+    let mut tag = HtmlTag {
+        name: String::new(),
+        attributes: vec![],
+        closing: false,
+        self_closing: false,
+    };
+
+    match chars.peek() {
+        Some((_, '/')) => {
+            tag.closing = true;
+            chars.next();
+        }
+        Some((_, '!')) | Some((_, '?')) => {
+            let end = html.find('>')?;
+            return Some((tag, end + 1));
+        }
+        _ => {}
+    }
+    while let Some(&(_, c)) = chars.peek() {
+        if !(c.is_ascii_alphanumeric() || c == '-')
+            || (tag.name.is_empty() && !c.is_ascii_alphabetic())
+        {
+            break;
+        }
+        tag.name.push(c.to_ascii_lowercase());
+        chars.next();
+    }
+    if tag.name.is_empty() {
+        return None;
+    }
+
+    loop {
+        while chars.peek().is_some_and(|(_, c)| c.is_whitespace()) {
+            chars.next();
+        }
+        let (i, c) = chars.next()?;
+        match c {
+            '>' => return Some((tag, i + 1)),
+            '/' => {
+                if let Some(&(i, '>')) = chars.peek() {
+                    tag.self_closing = true;
+                    return Some((tag, i + 1));
+                }
+            }
+            _ => {
+                let mut name = c.to_ascii_lowercase().to_string();
+                while let Some(&(_, c)) = chars.peek() {
+                    if c.is_whitespace() || c == '=' || c == '>' || c == '/' {
+                        break;
+                    }
+                    name.push(c.to_ascii_lowercase());
+                    chars.next();
+                }
+                while chars.peek().is_some_and(|(_, c)| c.is_whitespace()) {
+                    chars.next();
+                }
+                if chars.peek().map(|(_, c)| *c) != Some('=') {
+                    tag.attributes.push((name, None));
+                    continue;
+                }
+                chars.next();
+                while chars.peek().is_some_and(|(_, c)| c.is_whitespace()) {
+                    chars.next();
+                }
+
+                let mut value = String::new();
+                match chars.peek().map(|(_, c)| *c) {
+                    Some(quote) if quote == '"' || quote == '\'' => {
+                        chars.next();
+                        loop {
+                            let (_, c) = chars.next()?;
+                            if c == quote {
+                                break;
+                            }
+                            value.push(c);
+                        }
+                    }
+                    _ => {
+                        while let Some(&(_, c)) = chars.peek() {
+                            if c.is_whitespace() || c == '>' {
+                                break;
+                            }
+                            value.push(c);
+                            chars.next();
+                        }
+                    }
+                }
+                tag.attributes.push((name, Some(value)));
+            }
+        }
+    }
+}
+
+/// Whether the URL is relative or has an allowed scheme.
+///
+/// Browsers ignore whitespace and decode character references in URLs, so a URL with either
+/// before its path is rejected rather than trying to decode it.
+fn is_safe_url(url: &str) -> bool {
+    let prefix_end = url.find(['/', '?', '#']).unwrap_or(url.len());
+    let prefix = &url[..prefix_end];
+    if prefix.contains('&') || prefix.chars().any(|c| c.is_whitespace() || c.is_control()) {
+        return false;
+    }
+    match prefix.find(':') {
+        Some(colon) => URL_SCHEMES.contains(&prefix[..colon].to_lowercase().as_str()),
+        None => true,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::render::{
+        code::SyntaxHighlighter, diagram::Diagrams, mathjax::MathjaxPolicy,
+        syntax_assets::SyntaxAssets, template::Templates, RenderOpts,
+    };
+
+    fn sanitize(html: &str) -> String {
+        Sanitizer::default().sanitize(html)
+    }
+
+    #[test]
+    fn keeps_allowed_tags_and_attributes() {
+        assert_eq!(
+            sanitize("<p class=\"lead\">Some <B>bold</B> text</p>"),
+            "<p class=\"lead\">Some <b>bold</b> text</p>"
+        );
+        assert_eq!(
+            sanitize("<img src='a.png' alt=\"A\" onerror=\"alert(1)\"/>"),
+            "<img src=\"a.png\" alt=\"A\" />"
+        );
+    }
+
+    #[test]
+    fn drops_scripts_with_their_content() {
+        assert_eq!(
+            sanitize("before<script>alert('<b>')</script>after"),
+            "beforeafter"
+        );
+        assert_eq!(sanitize("<iframe src=\"x\"></iframe><!-- a -->b"), "b");
+    }
+
+    #[test]
+    fn drops_unknown_tags_but_keeps_content() {
+        assert_eq!(sanitize("<marquee>text</marquee>"), "text");
+    }
+
+    #[test]
+    fn keeps_state_between_events() {
+        let mut sanitizer = Sanitizer::default();
+        assert_eq!(sanitizer.sanitize("<script>\n"), "");
+        assert_eq!(sanitizer.sanitize("alert(1)\n"), "");
+        assert_eq!(sanitizer.sanitize("</script>\n"), "\n");
+        assert_eq!(sanitizer.sanitize("<!-- start\n"), "");
+        assert_eq!(sanitizer.sanitize("end -->text\n"), "text\n");
+    }
+
+    #[test]
+    fn unclosed_tags_end_with_their_block() {
+        let highlighter =
+            SyntaxHighlighter::with_assets(SyntaxAssets::defaults(), "base16-ocean.dark").unwrap();
+        let diagrams = Diagrams {
+            mermaid_script: None,
+            dot_command: None,
+            cache_dir: None,
+        };
+        let templates = Templates::builtin();
+        let rendered = RenderOpts::new(
+            &None,
+            &None,
+            &highlighter,
+            &diagrams,
+            &MathjaxPolicy::Never,
+            &HtmlPolicy::Sanitize,
+            &templates,
+        )
+        .render("Some <style>p {}\n\n```\nlet code;\n```\n\n<div><title>\n\nAfter\n")
+        .unwrap();
+        assert!(!rendered.content.contains("p {}"));
+        assert!(rendered.content.contains("let code;"));
+        assert!(rendered.content.contains("After"));
+    }
+
+    #[test]
+    fn escapes_unparsed_tags() {
+        assert_eq!(sanitize("1 < 2 <img src=\"a"), "1 &lt; 2 &lt;img src=\"a");
+    }
+
+    #[test]
+    fn rejects_unsafe_urls() {
+        assert_eq!(
+            sanitize("<a href=\"javascript:alert(1)\" title=\"t\">x</a>"),
+            "<a title=\"t\">x</a>"
+        );
+        assert!(is_safe_url("https://example.com/a?b=c&d=e"));
+        assert!(is_safe_url("../notes/a.html#section"));
+        assert!(is_safe_url("mailto:me@example.com"));
+        assert!(!is_safe_url("JavaScript:alert(1)"));
+        assert!(!is_safe_url("java\tscript:alert(1)"));
+        assert!(!is_safe_url("javascript&colon;alert(1)"));
+        assert!(!is_safe_url("data:text/html,<script>"));
+    }
+
+    #[test]
+    fn escape_policy_renders_text() {
+        let mut sanitizer = Sanitizer::default();
+        let event = HtmlPolicy::Escape.apply(&mut sanitizer, Event::InlineHtml("<b>".into()));
+        assert_eq!(event, Event::Text("<b>".into()));
+    }
+}
@@ -0,0 +1,313 @@
+use crate::{
+    render::{
+        front_matter::FrontMatter,
+        mathjax::MathjaxPolicy,
+        nav::Siblings,
+        source::SourceFormat,
+        tags,
+        template::{PageKind, Template},
+    },
+    sync::SyncOpts,
+};
+use maud::{html, Markup, Render};
+use pulldown_cmark::{Event, Tag};
+use serde::{Deserialize, Serialize};
+use std::{
+    collections::HashSet,
+    path::{Path, PathBuf},
+};
+
+/// File name of the search page, relative to the render root.
+pub const SEARCH_PAGE_FILE_NAME: &str = "search.html";
+/// File name of the search index, relative to the render root.
+///
+/// The index is JSON assigned to a global in a script, as pages opened from `file://` can load
+/// scripts but can't fetch JSON.
+pub const SEARCH_INDEX_FILE_NAME: &str = "search-index.js";
+/// File name of the script for the search page, relative to the render root.
+pub const SEARCH_SCRIPT_FILE_NAME: &str = "search.js";
+
+/// Script for the search page, which searches the notes in the search index for the `q` query
+/// parameter.
+///
+/// Query terms match words by prefix, and every term has to match. Matches in titles rank above
+/// matches in headings and tags, which rank above matches in the body.
+pub const SEARCH_SCRIPT: &str = r#"(function () {
+    var index = window.carbonSearchIndex || { notes: [] };
+    var input = document.querySelector(".search-page input[name=q]");
+    var results = document.querySelector(".search-results");
+
+    function tokenize(text) {
+        return text.toLowerCase().split(/[^\p{L}\p{N}]+/u).filter(function (token) {
+            return token.length > 1;
+        });
+    }
+
+    function hasPrefix(tokens, term) {
+        return tokens.some(function (token) {
+            return token.indexOf(term) === 0;
+        });
+    }
+
+    function score(note, terms) {
+        var total = 0;
+        for (var i = 0; i < terms.length; i++) {
+            var term = terms[i];
+            var points = 0;
+            if (hasPrefix(tokenize(note.title), term)) points += 10;
+            if (hasPrefix(tokenize(note.headings.join(" ")), term)) points += 5;
+            if (hasPrefix(tokenize(note.tags.join(" ")), term)) points += 5;
+            if (hasPrefix(note.words.split(" "), term)) points += 1;
+            if (points === 0) return 0;
+            total += points;
+        }
+        return total;
+    }
+
+    function search(query) {
+        results.textContent = "";
+        var terms = tokenize(query);
+        if (terms.length === 0) return;
+        var matches = index.notes
+            .map(function (note) { return { note: note, score: score(note, terms) }; })
+            .filter(function (match) { return match.score > 0; })
+            .sort(function (a, b) { return b.score - a.score; });
+        if (matches.length === 0) {
+            var none = document.createElement("li");
+            none.textContent = "No notes found";
+            results.appendChild(none);
+        }
+        matches.forEach(function (match) {
+            var item = document.createElement("li");
+            item.className = "index-note";
+            var link = document.createElement("a");
+            link.href = match.note.path;
+            link.textContent = match.note.title;
+            var details = document.createElement("span");
+            details.className = "index-details";
+            details.textContent = match.note.path;
+            item.appendChild(link);
+            item.appendChild(document.createTextNode(" "));
+            item.appendChild(details);
+            results.appendChild(item);
+        });
+    }
+
+    input.value = new URLSearchParams(window.location.search).get("q") || "";
+    input.addEventListener("input", function () { search(input.value); });
+    search(input.value);
+})();
+"#;
+
+/// Note in the search index.
+#[derive(Debug, Default, Clone, PartialEq, Serialize, Deserialize)]
+pub struct SearchEntry {
+    pub title: String,
+    /// Rendered note, relative to the render root.
+    pub path: String,
+    pub headings: Vec<String>,
+    pub tags: Vec<String>,
+    /// Distinct words in the note, separated by spaces.
+    pub words: String,
+}
+
+impl SearchEntry {
+    /// Entry for a note, where `path` is the rendered note relative to the render root and
+    /// `name` is used as the title if the note doesn't have one.
+    pub fn parse(source: &str, format: SourceFormat, path: String, name: &str) -> Self {
+        let parsed = format.parse(source);
+        let front_matter = parsed.front_matter.unwrap_or_default();
+
+        let mut title = front_matter.title().map(String::from);
+        let mut headings = vec![];
+        let mut note_tags = tags::from_front_matter(&front_matter);
+        let mut words = Words::default();
+        let mut header = None;
+        let mut in_code = false;
+        for (i, (event, _)) in parsed.events.into_iter().enumerate() {
+            match event {
+                Event::Start(Tag::Header(level)) => header = Some((level, i, String::new())),
+                Event::End(Tag::Header(_)) => {
+                    if let Some((level, start, text)) = header.take() {
+                        if level == 1 && start == 0 && title.is_none() {
+                            title = Some(text);
+                        } else {
+                            headings.push(text);
+                        }
+                    }
+                }
+                Event::Start(Tag::Code) | Event::Start(Tag::CodeBlock(_)) => in_code = true,
+                Event::End(Tag::Code) | Event::End(Tag::CodeBlock(_)) => in_code = false,
+                Event::Text(text) => {
+                    if let Some((_, _, ref mut header_text)) = header {
+                        header_text.push_str(&text);
+                    }
+                    if !in_code {
+                        note_tags.extend(tags::from_text(&text));
+                    }
+                    words.extend(&text);
+                }
+                _ => {}
+            }
+        }
+        note_tags.sort();
+        note_tags.dedup();
+
+        Self {
+            title: title.unwrap_or_else(|| name.to_string()),
+            path,
+            headings,
+            tags: note_tags,
+            words: words.0.join(" "),
+        }
+    }
+}
+
+/// Distinct words, in the order they're first seen.
+#[derive(Default)]
+struct Words(Vec<String>, HashSet<String>);
+
+impl Words {
+    fn extend(&mut self, text: &str) {
+        for token in tokenize(text) {
+            if self.1.insert(token.clone()) {
+                self.0.push(token);
+            }
+        }
+    }
+}
+
+/// Lowercase words in the text, without punctuation or single characters.
+pub fn tokenize(text: &str) -> impl Iterator<Item = String> + '_ {
+    tokens(text).map(|(_, token)| token)
+}
+
+/// Words in the text as with [`tokenize`], along with their byte offsets in the text.
+pub fn tokens(text: &str) -> impl Iterator<Item = (usize, String)> + '_ {
+    text.split(|c: char| !c.is_alphanumeric())
+        .filter(|token| token.chars().count() > 1)
+        .map(move |token| {
+            let offset = token.as_ptr() as usize - text.as_ptr() as usize;
+            (offset, token.to_lowercase())
+        })
+}
+
+/// Search box that opens the search page with the query.
+pub struct SearchBox(pub String);
+
+impl Render for SearchBox {
+    fn render(&self) -> Markup {
+        html! {
+            form class="search" action=(self.0) method="get" role="search" {
+                input type="search" name="q" placeholder="Search" aria-label="Search";
+            }
+        }
+    }
+}
+
+/// Page that searches the notes in the search index.
+pub struct SearchPage<'a> {
+    opts: &'a SyncOpts,
+    render_path: PathBuf,
+    title: Option<String>,
+    front_matter: FrontMatter,
+    siblings: Siblings,
+}
+
+impl<'a> SearchPage<'a> {
+    pub fn new(opts: &'a SyncOpts) -> Self {
+        Self {
+            opts,
+            render_path: opts.dst_root.join(SEARCH_PAGE_FILE_NAME),
+            title: Some(String::from("Search")),
+            front_matter: FrontMatter::default(),
+            siblings: Siblings::default(),
+        }
+    }
+
+    pub fn render_path(&self) -> &Path {
+        &self.render_path
+    }
+
+    fn href(&self, file_name: &str) -> String {
+        let target = self.opts.dst_root.join(file_name);
+        self.opts.links.href(Some(&self.render_path), &target)
+    }
+
+    pub fn template(&self) -> Template<'_> {
+        Template {
+            kind: PageKind::Index,
+            content: self.render(),
+            title: &self.title,
+            toc: &[],
+            front_matter: &self.front_matter,
+            tags: &[],
+            breadcrumbs: &[],
+            siblings: &self.siblings,
+            stylesheet: &self.opts.stylesheet,
+            syntax_stylesheet: &None,
+            mermaid_script: None,
+            mathjax_policy: &MathjaxPolicy::Never,
+            html_policy: &self.opts.html_policy,
+            links: &self.opts.links,
+            path: Some(&self.render_path),
+            search_page: Some(&self.render_path),
+            table_sort: false,
+        }
+    }
+}
+
+impl<'a> Render for SearchPage<'a> {
+    fn render(&self) -> Markup {
+        html! {
+            h1 { "Search" }
+            form class="search-page" role="search" {
+                input type="search" name="q" placeholder="Search" aria-label="Search" autofocus?;
+            }
+            ul class="index-entries search-results" {}
+            script type="text/javascript" src=(self.href(SEARCH_INDEX_FILE_NAME)) {}
+            script type="text/javascript" src=(self.href(SEARCH_SCRIPT_FILE_NAME)) {}
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn entry_of_note() {
+        let markdown = "---\ntags: [notes]\n---\n# Title\n\nSome words, some #rust.\n\n\
+                        ## First Part\n\n```\nlet x = 1;\n```\n";
+        let entry = SearchEntry::parse(
+            markdown,
+            SourceFormat::Markdown,
+            String::from("a/note.html"),
+            "note",
+        );
+        assert_eq!(entry.title, "Title");
+        assert_eq!(entry.headings, vec!["First Part"]);
+        assert_eq!(entry.tags, vec!["notes", "rust"]);
+        assert_eq!(entry.words, "title some words rust first part let");
+    }
+
+    #[test]
+    fn token_offsets() {
+        let tokens: Vec<(usize, String)> = tokens("A cat, the Dog").collect();
+        assert_eq!(
+            tokens,
+            vec![
+                (2, String::from("cat")),
+                (7, String::from("the")),
+                (11, String::from("dog"))
+            ]
+        );
+    }
+
+    #[test]
+    fn untitled_note() {
+        let entry = SearchEntry::parse("Just text", SourceFormat::Org, String::from("a.html"), "a");
+        assert_eq!(entry.title, "a");
+        assert!(entry.headings.is_empty());
+    }
+}
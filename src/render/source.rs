@@ -0,0 +1,112 @@
+use crate::render::{front_matter::FrontMatter, org, rst};
+use pulldown_cmark::{Event, Options, Parser};
+use std::{ops::Range, path::Path};
+
+/// Format that a note is written in, which is known by its file extension.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum SourceFormat {
+    #[default]
+    Markdown,
+    Org,
+    Rst,
+}
+
+/// Note parsed from its source into the events that are rendered.
+pub struct ParsedSource<'a> {
+    /// Metadata at the start of the note, or why it couldn't be parsed.
+    pub front_matter: Result<FrontMatter, String>,
+    /// Offset of the body in the note, which the ranges of the events are relative to.
+    pub body_start: usize,
+    pub events: Vec<(Event<'a>, Range<usize>)>,
+    /// Problems found in the body, at their offsets in the body.
+    pub warnings: Vec<(usize, String)>,
+}
+
+impl SourceFormat {
+    pub const ALL: &'static [SourceFormat] =
+        &[SourceFormat::Markdown, SourceFormat::Org, SourceFormat::Rst];
+
+    /// Format of the note at the path, or `None` if it isn't a note.
+    pub fn from_path(path: &Path) -> Option<Self> {
+        let extension = path.extension()?;
+        Self::ALL
+            .iter()
+            .copied()
+            .find(|format| extension == format.extension())
+    }
+
+    pub fn extension(self) -> &'static str {
+        match self {
+            SourceFormat::Markdown => "md",
+            SourceFormat::Org => "org",
+            SourceFormat::Rst => "rst",
+        }
+    }
+
+    pub fn parse(self, source: &str) -> ParsedSource<'_> {
+        match self {
+            SourceFormat::Markdown => parse_markdown(source),
+            SourceFormat::Org => org::parse(source),
+            SourceFormat::Rst => rst::parse(source),
+        }
+    }
+}
+
+/// Extensions to CommonMark that notes are parsed with.
+pub fn markdown_options() -> Options {
+    let mut options = Options::empty();
+    options.insert(Options::ENABLE_TABLES);
+    options.insert(Options::ENABLE_FOOTNOTES);
+    options.insert(Options::ENABLE_STRIKETHROUGH);
+    options.insert(Options::ENABLE_TASKLISTS);
+    options
+}
+
+fn parse_markdown(markdown: &str) -> ParsedSource<'_> {
+    let (yaml, body_start) = FrontMatter::split(markdown);
+    let front_matter = yaml
+        .map(FrontMatter::parse)
+        .transpose()
+        .map(Option::unwrap_or_default);
+    // TODO: source::parse_markdown should include broken link callback
+    let events =
+        Parser::new_with_broken_link_callback(&markdown[body_start..], markdown_options(), None)
+            .into_offset_iter()
+            .collect();
+    ParsedSource {
+        front_matter,
+        body_start,
+        events,
+        warnings: vec![],
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn format_from_path() {
+        assert_eq!(
+            SourceFormat::from_path(Path::new("a/note.md")),
+            Some(SourceFormat::Markdown)
+        );
+        assert_eq!(
+            SourceFormat::from_path(Path::new("note.org")),
+            Some(SourceFormat::Org)
+        );
+        assert_eq!(
+            SourceFormat::from_path(Path::new("doc/index.rst")),
+            Some(SourceFormat::Rst)
+        );
+        assert_eq!(SourceFormat::from_path(Path::new("data.csv")), None);
+        assert_eq!(SourceFormat::from_path(Path::new("md")), None);
+    }
+
+    #[test]
+    fn markdown_front_matter_error() {
+        let parsed = SourceFormat::Markdown.parse("---\n- a\n---\nBody");
+        assert!(parsed.front_matter.is_err());
+        assert_eq!(parsed.body_start, 12);
+    }
+}
@@ -0,0 +1,283 @@
+use std::{
+    collections::{hash_map::DefaultHasher, HashSet},
+    fs,
+    hash::{Hash, Hasher},
+    io,
+    path::{Path, PathBuf},
+    sync::OnceLock,
+};
+use syntect::{
+    dumps::{dump_to_file, from_dump_file},
+    highlighting::ThemeSet,
+    parsing::SyntaxSet,
+    LoadingError,
+};
+
+const SYNTAX_EXTENSION: &str = "sublime-syntax";
+const THEME_EXTENSION: &str = "tmTheme";
+
+/// Syntaxes and themes used for highlighting code blocks.
+///
+/// These are the defaults bundled with syntect, extended with any `.sublime-syntax` and `.tmTheme`
+/// files found in the custom syntax and theme directories.
+pub struct SyntaxAssets {
+    pub syntax_set: SyntaxSet,
+    pub theme_set: ThemeSet,
+}
+
+impl SyntaxAssets {
+    pub fn defaults() -> Self {
+        Self {
+            syntax_set: SyntaxSet::load_defaults_newlines(),
+            theme_set: ThemeSet::load_defaults(),
+        }
+    }
+
+    /// Load the default assets along with custom syntaxes and themes.
+    ///
+    /// Compiling syntaxes is slow, so the compiled sets are cached in `cache_dir` as binary dumps.
+    /// The dumps are keyed by a fingerprint of the custom files, so any change to them results in
+    /// the sets being compiled again.
+    pub fn load(
+        syntax_dirs: &[PathBuf],
+        theme_dirs: &[PathBuf],
+        cache_dir: Option<&Path>,
+    ) -> io::Result<Self> {
+        let syntax_files = find_files(syntax_dirs, SYNTAX_EXTENSION);
+        let theme_files = find_files(theme_dirs, THEME_EXTENSION);
+        if syntax_files.is_empty() && theme_files.is_empty() {
+            return Ok(SyntaxAssets::defaults());
+        }
+
+        let syntax_set = match cache_dir {
+            Some(cache_dir) => cached(cache_dir, "syntaxes", &syntax_files, || {
+                load_syntax_set(syntax_dirs)
+            })?,
+            None => load_syntax_set(syntax_dirs)?,
+        };
+        let theme_set = match cache_dir {
+            Some(cache_dir) => cached(cache_dir, "themes", &theme_files, || {
+                load_theme_set(theme_dirs)
+            })?,
+            None => load_theme_set(theme_dirs)?,
+        };
+        Ok(Self {
+            syntax_set,
+            theme_set,
+        })
+    }
+
+    /// Names of syntaxes that aren't bundled with syntect.
+    pub fn custom_syntaxes(&self) -> Vec<&str> {
+        let defaults = &default_names().0;
+        let mut names: Vec<&str> = self
+            .syntax_set
+            .syntaxes()
+            .iter()
+            .map(|syntax| syntax.name.as_str())
+            .filter(|name| !defaults.contains(*name))
+            .collect();
+        names.sort();
+        names
+    }
+
+    /// Names of themes that aren't bundled with syntect.
+    pub fn custom_themes(&self) -> Vec<&str> {
+        let defaults = &default_names().1;
+        self.theme_set
+            .themes
+            .keys()
+            .map(String::as_str)
+            .filter(|name| !defaults.contains(*name))
+            .collect()
+    }
+}
+
+/// Names of the syntaxes and themes bundled with syntect, which are only loaded the first time
+/// they're needed.
+fn default_names() -> &'static (HashSet<String>, HashSet<String>) {
+    static NAMES: OnceLock<(HashSet<String>, HashSet<String>)> = OnceLock::new();
+    NAMES.get_or_init(|| {
+        let syntaxes = SyntaxSet::load_defaults_newlines()
+            .syntaxes()
+            .iter()
+            .map(|syntax| syntax.name.clone())
+            .collect();
+        let themes = ThemeSet::load_defaults().themes.into_keys().collect();
+        (syntaxes, themes)
+    })
+}
+
+fn load_syntax_set(dirs: &[PathBuf]) -> io::Result<SyntaxSet> {
+    let mut builder = SyntaxSet::load_defaults_newlines().into_builder();
+    for dir in dirs.iter().filter(|dir| dir.is_dir()) {
+        builder
+            .add_from_folder(dir, true)
+            .map_err(|e| loading_error(dir, e))?;
+    }
+    Ok(builder.build())
+}
+
+fn load_theme_set(dirs: &[PathBuf]) -> io::Result<ThemeSet> {
+    let mut theme_set = ThemeSet::load_defaults();
+    for dir in dirs.iter().filter(|dir| dir.is_dir()) {
+        theme_set
+            .add_from_folder(dir)
+            .map_err(|e| loading_error(dir, e))?;
+    }
+    Ok(theme_set)
+}
+
+/// Read a binary dump from the cache, or build and cache it if there isn't a dump matching the
+/// fingerprint of `files`.
+fn cached<T, F>(cache_dir: &Path, name: &str, files: &[PathBuf], build: F) -> io::Result<T>
+where
+    T: serde::Serialize + serde::de::DeserializeOwned,
+    F: FnOnce() -> io::Result<T>,
+{
+    let dump_path = cache_dir.join(format!("{}-{:016x}.bin", name, fingerprint(files)));
+    if let Ok(value) = from_dump_file(&dump_path) {
+        return Ok(value);
+    }
+
+    // Failing to cache only makes the next startup slower, so it shouldn't fail highlighting.
+    let value = build()?;
+    let cache = fs::create_dir_all(cache_dir)
+        .and_then(|_| remove_stale_dumps(cache_dir, name))
+        .and_then(|_| dump_to_file(&value, &dump_path).map_err(io::Error::other));
+    if let Err(e) = cache {
+        eprintln!("failed to cache {} at {}: {}", name, dump_path.display(), e);
+    }
+    Ok(value)
+}
+
+fn remove_stale_dumps(cache_dir: &Path, name: &str) -> io::Result<()> {
+    let prefix = format!("{}-", name);
+    for entry in fs::read_dir(cache_dir)?.filter_map(Result::ok) {
+        let file_name = entry.file_name();
+        let file_name = file_name.to_string_lossy();
+        if file_name.starts_with(&prefix) && file_name.ends_with(".bin") {
+            fs::remove_file(entry.path())?;
+        }
+    }
+    Ok(())
+}
+
+/// Hash of the path, size and modified time of each file.
+fn fingerprint(files: &[PathBuf]) -> u64 {
+    let mut hasher = DefaultHasher::new();
+    for file in files {
+        file.hash(&mut hasher);
+        if let Ok(metadata) = file.metadata() {
+            metadata.len().hash(&mut hasher);
+            metadata.modified().ok().hash(&mut hasher);
+        }
+    }
+    hasher.finish()
+}
+
+/// Files with the extension in any of the directories, recursively and in a stable order.
+fn find_files(dirs: &[PathBuf], extension: &str) -> Vec<PathBuf> {
+    let mut files = vec![];
+    let mut unseen_dirs: Vec<PathBuf> = dirs.iter().filter(|dir| dir.is_dir()).cloned().collect();
+    while let Some(dir) = unseen_dirs.pop() {
+        let entries = match fs::read_dir(&dir) {
+            Ok(entries) => entries,
+            Err(_) => continue,
+        };
+        for path in entries.filter_map(Result::ok).map(|entry| entry.path()) {
+            if path.is_dir() {
+                unseen_dirs.push(path);
+            } else if path.extension().unwrap_or_default() == extension {
+                files.push(path);
+            }
+        }
+    }
+    files.sort();
+    files
+}
+
+fn loading_error(dir: &Path, e: LoadingError) -> io::Error {
+    io::Error::new(
+        io::ErrorKind::InvalidData,
+        format!("failed to load from {}: {}", dir.display(), e),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_util::temp_dir;
+
+    const THEME: &str = r#"<?xml version="1.0" encoding="UTF-8"?>
+<!DOCTYPE plist PUBLIC "-//Apple//DTD PLIST 1.0//EN" "http://www.apple.com/DTDs/PropertyList-1.0.dtd">
+<plist version="1.0">
+<dict>
+  <key>name</key>
+  <string>Team</string>
+  <key>settings</key>
+  <array>
+    <dict>
+      <key>settings</key>
+      <dict>
+        <key>background</key>
+        <string>#ffffff</string>
+        <key>foreground</key>
+        <string>#000000</string>
+      </dict>
+    </dict>
+  </array>
+</dict>
+</plist>
+"#;
+
+    const SYNTAX: &str = "%YAML 1.2
+---
+name: Carbon DSL
+file_extensions: [cdsl]
+scope: source.cdsl
+contexts:
+  main:
+    - match: '\\b(note|tag)\\b'
+      scope: keyword.control.cdsl
+";
+
+    #[test]
+    fn load_without_custom_files() {
+        let dirs = vec![temp_dir("syntax-assets-empty")];
+        let assets = SyntaxAssets::load(&dirs, &dirs, None).unwrap();
+        assert!(assets.custom_syntaxes().is_empty());
+        assert!(assets.custom_themes().is_empty());
+    }
+
+    #[test]
+    fn load_custom_files_and_cache() {
+        let dir = temp_dir("syntax-assets-custom");
+        let cache_dir = dir.join("cache");
+        fs::write(dir.join("team.tmTheme"), THEME).unwrap();
+        fs::write(dir.join("cdsl.sublime-syntax"), SYNTAX).unwrap();
+
+        let dirs = vec![dir];
+        let assets = SyntaxAssets::load(&dirs, &dirs, Some(&cache_dir)).unwrap();
+        assert_eq!(assets.custom_syntaxes(), vec!["Carbon DSL"]);
+        assert_eq!(assets.custom_themes(), vec!["team"]);
+        assert!(assets.syntax_set.find_syntax_by_token("cdsl").is_some());
+        assert_eq!(fs::read_dir(&cache_dir).unwrap().count(), 2);
+
+        let cached = SyntaxAssets::load(&dirs, &dirs, Some(&cache_dir)).unwrap();
+        assert_eq!(cached.custom_syntaxes(), vec!["Carbon DSL"]);
+    }
+
+    #[test]
+    fn load_with_unwritable_cache() {
+        let dir = temp_dir("syntax-assets-unwritable");
+        fs::write(dir.join("cdsl.sublime-syntax"), SYNTAX).unwrap();
+        // The cache directory can't be created inside a file.
+        fs::write(dir.join("file"), "").unwrap();
+        let cache_dir = dir.join("file/cache");
+
+        let dirs = vec![dir];
+        let assets = SyntaxAssets::load(&dirs, &dirs, Some(&cache_dir)).unwrap();
+        assert_eq!(assets.custom_syntaxes(), vec!["Carbon DSL"]);
+    }
+}
@@ -0,0 +1,333 @@
+use crate::{
+    render::{
+        front_matter::FrontMatter,
+        index::INDEX_FILE_NAME,
+        links::Links,
+        mathjax::MathjaxPolicy,
+        nav::{PageLink, Siblings},
+        template::{PageKind, Template},
+    },
+    sync::SyncOpts,
+};
+use maud::{html, Markup, Render};
+use serde_json::Value;
+use std::path::{Path, PathBuf};
+
+/// Directory in the render root with the tag pages.
+pub const TAGS_DIR_NAME: &str = "_tags";
+
+/// Directory in the tags directory that the tag pages are in, so a tag can't be named like the
+/// index page of the tags.
+pub const TAG_PAGES_DIR_NAME: &str = "tag";
+
+/// Tag in the canonical form used for tag pages: lowercase, with `-` instead of spaces, and `/`
+/// between the levels of hierarchical tags.
+///
+/// Returns `None` if nothing is left of the tag.
+pub fn normalize(tag: &str) -> Option<String> {
+    let levels: Vec<String> = tag
+        .trim_start_matches('#')
+        .split('/')
+        .map(|level| {
+            level
+                .trim()
+                .to_lowercase()
+                .chars()
+                .map(|c| if c.is_whitespace() { '-' } else { c })
+                .filter(|&c| c.is_alphanumeric() || c == '-' || c == '_')
+                .collect::<String>()
+        })
+        .filter(|level| !level.is_empty())
+        .collect();
+    if levels.is_empty() {
+        return None;
+    }
+    Some(levels.join("/"))
+}
+
+/// Tags in the `tags` of the front matter, which is a list or a comma separated string.
+pub fn from_front_matter(front_matter: &FrontMatter) -> Vec<String> {
+    match front_matter.get("tags") {
+        Some(Value::Array(tags)) => tags
+            .iter()
+            .filter_map(|tag| match tag {
+                Value::String(tag) => normalize(tag),
+                Value::Number(tag) => normalize(&tag.to_string()),
+                _ => None,
+            })
+            .collect(),
+        Some(Value::String(tags)) => tags.split(',').filter_map(normalize).collect(),
+        _ => vec![],
+    }
+}
+
+/// Inline `#tags` in text. A tag starts with a letter after whitespace or the start of the
+/// text, so `#1` and URL fragments aren't tags.
+pub fn from_text(text: &str) -> Vec<String> {
+    let mut tags = vec![];
+    let mut previous = None;
+    let mut chars = text.char_indices().peekable();
+    while let Some((i, c)) = chars.next() {
+        let at_word_start = previous.is_none_or(char::is_whitespace);
+        previous = Some(c);
+        if c != '#' || !at_word_start {
+            continue;
+        }
+        match chars.peek() {
+            Some(&(_, next)) if next.is_alphabetic() => {}
+            _ => continue,
+        }
+        let start = i + 1;
+        let mut end = start;
+        while let Some(&(j, c)) = chars.peek() {
+            if !(c.is_alphanumeric() || c == '-' || c == '_' || c == '/') {
+                break;
+            }
+            end = j + c.len_utf8();
+            previous = Some(c);
+            chars.next();
+        }
+        tags.extend(normalize(&text[start..end]));
+    }
+    tags
+}
+
+/// The tag and the tags it's nested in, outermost first. `project/carbon` is in `project`.
+pub fn with_ancestors(tag: &str) -> impl Iterator<Item = &str> {
+    tag.match_indices('/')
+        .map(move |(i, _)| &tag[..i])
+        .chain(std::iter::once(tag))
+}
+
+/// Page for the tag in the tags directory.
+pub fn page_path(tags_dir: &Path, tag: &str) -> PathBuf {
+    tags_dir
+        .join(TAG_PAGES_DIR_NAME)
+        .join(format!("{}.html", tag))
+}
+
+/// Links to the pages of `tags` from the page at `page`.
+pub fn page_links(
+    links: &Links,
+    tags_dir: &Path,
+    page: Option<&Path>,
+    tags: &[String],
+) -> Vec<PageLink> {
+    tags.iter()
+        .map(|tag| PageLink {
+            name: tag.clone(),
+            href: links.href(page, &page_path(tags_dir, tag)),
+        })
+        .collect()
+}
+
+/// Tags of a note, shown as chips linking to the tag pages.
+pub struct TagChips<'a>(pub &'a [PageLink]);
+
+impl<'a> Render for TagChips<'a> {
+    fn render(&self) -> Markup {
+        html! {
+            @if !self.0.is_empty() {
+                nav class="tags" {
+                    @for tag in self.0 {
+                        a class="tag" href=(tag.href) { "#" (tag.name) }
+                    }
+                }
+            }
+        }
+    }
+}
+
+/// Note listed on a tag page.
+pub struct TaggedNote {
+    pub title: String,
+    pub render_path: PathBuf,
+    /// Path of the note in the notes directory.
+    pub path: String,
+}
+
+/// Page listing the notes with a tag, or with the tags index when `tag` is `None`.
+pub struct TagPage<'a> {
+    opts: &'a SyncOpts,
+    tag: Option<&'a str>,
+    render_path: PathBuf,
+    title: Option<String>,
+    front_matter: FrontMatter,
+    breadcrumbs: Vec<PageLink>,
+    siblings: Siblings,
+    /// Tags nested in the tag, or all tags on the tags index, with the number of notes for each.
+    tags: Vec<(PageLink, usize)>,
+    /// Links to the notes with the tag, along with their paths.
+    notes: Vec<(PageLink, String)>,
+}
+
+impl<'a> TagPage<'a> {
+    /// Tag page, where `tags` are the tags to list along with their number of notes.
+    pub fn new(
+        opts: &'a SyncOpts,
+        tag: Option<&'a str>,
+        tags: &[(String, usize)],
+        notes: Vec<TaggedNote>,
+    ) -> Self {
+        let tags_dir = opts.dst_root.join(TAGS_DIR_NAME);
+        let render_path = match tag {
+            Some(tag) => page_path(&tags_dir, tag),
+            None => tags_dir.join(INDEX_FILE_NAME),
+        };
+        let href = |target: &Path| opts.links.href(Some(&render_path), target);
+
+        let mut breadcrumbs = vec![PageLink {
+            name: String::from("tags"),
+            href: href(&tags_dir.join(INDEX_FILE_NAME)),
+        }];
+        for ancestor in tag.into_iter().flat_map(with_ancestors) {
+            breadcrumbs.push(PageLink {
+                name: ancestor.rsplit('/').next().unwrap_or(ancestor).to_string(),
+                href: href(&page_path(&tags_dir, ancestor)),
+            });
+        }
+        let tags = tags
+            .iter()
+            .map(|(name, count)| {
+                let link = PageLink {
+                    name: name.clone(),
+                    href: href(&page_path(&tags_dir, name)),
+                };
+                (link, *count)
+            })
+            .collect();
+        let notes = notes
+            .into_iter()
+            .map(|note| {
+                let link = PageLink {
+                    name: note.title,
+                    href: href(&note.render_path),
+                };
+                (link, note.path)
+            })
+            .collect();
+        let title = match tag {
+            Some(tag) => format!("Tag: #{}", tag),
+            None => String::from("Tags"),
+        };
+
+        Self {
+            opts,
+            tag,
+            title: Some(title),
+            render_path,
+            front_matter: FrontMatter::default(),
+            breadcrumbs,
+            siblings: Siblings::default(),
+            tags,
+            notes,
+        }
+    }
+
+    pub fn render_path(&self) -> &Path {
+        &self.render_path
+    }
+
+    pub fn template(&self) -> Template<'_> {
+        Template {
+            kind: PageKind::Index,
+            content: self.render(),
+            title: &self.title,
+            toc: &[],
+            front_matter: &self.front_matter,
+            tags: &[],
+            breadcrumbs: &self.breadcrumbs,
+            siblings: &self.siblings,
+            stylesheet: &self.opts.stylesheet,
+            syntax_stylesheet: &None,
+            mermaid_script: None,
+            mathjax_policy: &MathjaxPolicy::Never,
+            html_policy: &self.opts.html_policy,
+            links: &self.opts.links,
+            path: Some(&self.render_path),
+            search_page: self.opts.search_page.as_deref(),
+            table_sort: false,
+        }
+    }
+}
+
+impl<'a> Render for TagPage<'a> {
+    fn render(&self) -> Markup {
+        html! {
+            h1 { (self.title.as_deref().unwrap_or_default()) }
+            @if !self.tags.is_empty() {
+                ul class="tag-list" {
+                    @for (tag, count) in &self.tags {
+                        li {
+                            a class="tag" href=(tag.href) { "#" (tag.name) }
+                            " "
+                            span class="index-details" { (count) }
+                        }
+                    }
+                }
+            }
+            @if self.tag.is_some() {
+                ul class="index-entries" {
+                    @for (link, path) in &self.notes {
+                        li class="index-note" {
+                            a href=(link.href) { (link.name) }
+                            " "
+                            span class="index-details" { (path) }
+                        }
+                    }
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn normalize_tags() {
+        assert_eq!(
+            normalize("#Project/Carbon"),
+            Some(String::from("project/carbon"))
+        );
+        assert_eq!(normalize("to read"), Some(String::from("to-read")));
+        assert_eq!(normalize("a//b/"), Some(String::from("a/b")));
+        assert_eq!(normalize("#!?"), None);
+    }
+
+    #[test]
+    fn inline_tags() {
+        assert_eq!(
+            from_text("#rust and #project/carbon, not a#b, #1 or # heading"),
+            vec!["rust", "project/carbon"]
+        );
+    }
+
+    #[test]
+    fn front_matter_tags() {
+        let front_matter = FrontMatter::parse("tags: [Rust, project/carbon]").unwrap();
+        assert_eq!(
+            from_front_matter(&front_matter),
+            vec!["rust", "project/carbon"]
+        );
+        let front_matter = FrontMatter::parse("tags: rust, notes").unwrap();
+        assert_eq!(from_front_matter(&front_matter), vec!["rust", "notes"]);
+    }
+
+    #[test]
+    fn tag_pages_apart_from_index() {
+        let tags_dir = Path::new("/notes/_tags");
+        assert_ne!(page_path(tags_dir, "index"), tags_dir.join(INDEX_FILE_NAME));
+        assert_eq!(
+            page_path(tags_dir, "project/carbon"),
+            Path::new("/notes/_tags/tag/project/carbon.html")
+        );
+    }
+
+    #[test]
+    fn tag_ancestors() {
+        let ancestors: Vec<&str> = with_ancestors("a/b/c").collect();
+        assert_eq!(ancestors, vec!["a", "a/b", "a/b/c"]);
+    }
+}
@@ -1,16 +1,77 @@
-use crate::render::{mathjax::MathjaxPolicy, stylesheet::Stylesheet, ToHtml};
+use crate::render::{
+    csp::ContentSecurityPolicy,
+    diagram::MermaidScript,
+    front_matter::FrontMatter,
+    header::TocEntry,
+    links::Links,
+    mathjax::MathjaxPolicy,
+    nav::{Breadcrumbs, PageLink, Siblings},
+    sanitize::HtmlPolicy,
+    search::SearchBox,
+    stylesheet::Stylesheet,
+    tags::TagChips,
+    ToHtml,
+};
 use maud::{html, Markup, DOCTYPE};
+use minijinja::{context, path_loader, Environment, Value};
+use std::{
+    io,
+    path::{Path, PathBuf},
+};
+
+/// Kind of page, which selects the user template it's rendered with.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum PageKind {
+    Note,
+    Index,
+}
+
+impl PageKind {
+    pub fn name(self) -> &'static str {
+        match self {
+            PageKind::Note => "note",
+            PageKind::Index => "index",
+        }
+    }
+
+    fn template_name(self) -> String {
+        format!("{}.html", self.name())
+    }
+}
 
 pub struct Template<'a> {
+    pub kind: PageKind,
     pub content: Markup,
     pub title: &'a Option<String>,
+    pub toc: &'a [TocEntry],
+    pub front_matter: &'a FrontMatter,
+    /// Links to the pages of the page's tags.
+    pub tags: &'a [PageLink],
+    /// Breadcrumbs to the directory of the page.
+    pub breadcrumbs: &'a [PageLink],
+    /// Links to the notes before and after a note in its directory.
+    pub siblings: &'a Siblings,
     pub stylesheet: &'a Option<Stylesheet>,
+    pub syntax_stylesheet: &'a Option<Stylesheet>,
+    /// Mermaid script to include, if the page has mermaid diagrams.
+    pub mermaid_script: Option<&'a Path>,
     pub mathjax_policy: &'a MathjaxPolicy,
+    /// Policy for raw HTML in the content. Pages with untrusted HTML get a Content Security
+    /// Policy.
+    pub html_policy: &'a HtmlPolicy,
+    /// How links to stylesheets and scripts are written.
+    pub links: &'a Links,
+    /// Path the page is rendered to, if it's written to a file.
+    pub path: Option<&'a Path>,
+    /// Search page that the search box opens. Pages get a search box when there is one.
+    pub search_page: Option<&'a Path>,
+    /// Whether the content has sortable tables, with the script that sorts them.
+    pub table_sort: bool,
 }
 
 impl<'a> ToHtml for Template<'a> {
     fn to_html(&self) -> String {
-        page(&self).into_string()
+        page(self).into_string()
     }
 }
 
@@ -20,7 +81,16 @@ fn page(ctx: &Template) -> Markup {
         html {
             (head(ctx))
             body {
+                (Breadcrumbs {
+                    links: ctx.breadcrumbs,
+                    is_index: ctx.kind == PageKind::Index,
+                })
+                @if let Some(search_page) = ctx.search_page {
+                    (SearchBox(href(ctx, search_page)))
+                }
                 (ctx.content)
+                (TagChips(ctx.tags))
+                (ctx.siblings)
                 (footer(ctx))
             }
         }
@@ -31,18 +101,263 @@ fn head(ctx: &Template) -> Markup {
     html! {
         head {
             meta charset="utf-8";
+            (csp(ctx))
             @if let Some(ref title) = ctx.title {
                 title { (title) }
             }
-            @if let Some(ref stylesheet) = ctx.stylesheet {
-                (stylesheet)
-            }
+            (stylesheets(ctx))
         }
     }
 }
 
 fn footer(ctx: &Template) -> Markup {
     html! {
-        footer { (ctx.mathjax_policy) }
+        footer {
+            (scripts(ctx))
+        }
+    }
+}
+
+fn csp(ctx: &Template) -> Markup {
+    html! {
+        @if *ctx.html_policy != HtmlPolicy::Trust {
+            (ContentSecurityPolicy {
+                mermaid: ctx.mermaid_script.is_some(),
+                mathjax: ctx.mathjax_policy.inclusion(),
+                index_filter: ctx.kind == PageKind::Index,
+                search_form: ctx.search_page.is_some(),
+                search_scripts: ctx.search_page.is_some() && ctx.search_page == ctx.path,
+                table_sort: ctx.table_sort,
+            })
+        }
+    }
+}
+
+fn stylesheets(ctx: &Template) -> Markup {
+    html! {
+        @for stylesheet in ctx.stylesheet.iter().chain(ctx.syntax_stylesheet) {
+            @match stylesheet {
+                Stylesheet::Link(path) => (Stylesheet::Link(PathBuf::from(href(ctx, path)))),
+                Stylesheet::Inline(_) => (stylesheet),
+            }
+        }
+    }
+}
+
+fn scripts(ctx: &Template) -> Markup {
+    html! {
+        @if let Some(mermaid_script) = ctx.mermaid_script {
+            (MermaidScript(Path::new(&href(ctx, mermaid_script))))
+        }
+        (ctx.mathjax_policy)
+    }
+}
+
+/// Link from the page to the file at `target`.
+fn href(ctx: &Template, target: &Path) -> String {
+    ctx.links.href(ctx.path, target)
+}
+
+/// Page templates from the user's template directory.
+///
+/// `note.html` and `index.html` in the directory replace the built-in note and index pages. They
+/// are [minijinja](https://docs.rs/minijinja) templates, and can include other templates from the
+/// directory.
+pub struct Templates {
+    dir: Option<PathBuf>,
+    env: Environment<'static>,
+    kinds: Vec<PageKind>,
+}
+
+impl Templates {
+    /// Templates that render every page with the built-in template.
+    pub fn builtin() -> Self {
+        Self {
+            dir: None,
+            env: Environment::new(),
+            kinds: vec![],
+        }
+    }
+
+    /// Load the templates in `dir`, if it exists.
+    ///
+    /// The page templates are compiled up front so syntax errors are found before any notes are
+    /// rendered.
+    pub fn load(dir: Option<&Path>) -> io::Result<Self> {
+        let dir = match dir.filter(|dir| dir.is_dir()) {
+            Some(dir) => dir,
+            None => return Ok(Templates::builtin()),
+        };
+
+        let mut env = Environment::new();
+        env.set_loader(path_loader(dir));
+        let mut kinds = vec![];
+        for kind in [PageKind::Note, PageKind::Index] {
+            if dir.join(kind.template_name()).is_file() {
+                env.get_template(&kind.template_name())
+                    .map_err(|e| template_error(dir, &e))?;
+                kinds.push(kind);
+            }
+        }
+        Ok(Self {
+            dir: Some(dir.to_path_buf()),
+            env,
+            kinds,
+        })
+    }
+
+    /// Render the page with the user template for its kind, or the built-in template if there
+    /// isn't one.
+    pub fn render(&self, template: &Template) -> io::Result<String> {
+        let dir = match &self.dir {
+            Some(dir) if self.kinds.contains(&template.kind) => dir,
+            _ => return Ok(template.to_html()),
+        };
+
+        let ctx = context! {
+            kind => template.kind.name(),
+            title => template.title,
+            content => Value::from_safe_string(template.content.0.clone()),
+            toc => template.toc,
+            metadata => template.front_matter,
+            tags => template.tags,
+            breadcrumbs => template.breadcrumbs,
+            previous => template.siblings.previous,
+            next => template.siblings.next,
+            csp => Value::from_safe_string(csp(template).into_string()),
+            stylesheets => Value::from_safe_string(stylesheets(template).into_string()),
+            scripts => Value::from_safe_string(scripts(template).into_string()),
+            math => template.mathjax_policy.inclusion(),
+            mermaid => template.mermaid_script.is_some(),
+            search => template.search_page.map(|search_page| href(template, search_page)),
+        };
+        self.env
+            .get_template(&template.kind.template_name())
+            .and_then(|page| page.render(ctx))
+            .map_err(|e| template_error(dir, &e))
+    }
+}
+
+/// Error naming the template file, and line if known, where the error is.
+fn template_error(dir: &Path, e: &minijinja::Error) -> io::Error {
+    let location = match (e.name(), e.line()) {
+        (Some(name), Some(line)) => format!("{}:{}", dir.join(name).display(), line),
+        (Some(name), None) => dir.join(name).display().to_string(),
+        _ => dir.display().to_string(),
+    };
+    let detail = match e.detail() {
+        Some(detail) => format!("{}: {}", e.kind(), detail),
+        None => e.kind().to_string(),
+    };
+    io::Error::new(
+        io::ErrorKind::InvalidData,
+        format!("{}: {}", location, detail),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::render::links::LinkStyle;
+    use crate::test_util::temp_dir;
+    use std::fs;
+
+    fn render(templates: &Templates, kind: PageKind) -> io::Result<String> {
+        let toc = vec![TocEntry {
+            level: 2,
+            id: String::from("part"),
+            title: String::from("Part"),
+        }];
+        let front_matter = FrontMatter::parse("author: Me").unwrap();
+        templates.render(&Template {
+            kind,
+            content: html! { p { "Content" } },
+            title: &Some(String::from("A & B")),
+            toc: &toc,
+            front_matter: &front_matter,
+            tags: &[],
+            breadcrumbs: &[],
+            siblings: &Siblings::default(),
+            stylesheet: &None,
+            syntax_stylesheet: &None,
+            mermaid_script: None,
+            mathjax_policy: &MathjaxPolicy::Never,
+            html_policy: &HtmlPolicy::Trust,
+            links: &Links::default(),
+            path: None,
+            search_page: None,
+            table_sort: false,
+        })
+    }
+
+    #[test]
+    fn builtin_template() {
+        let html = render(&Templates::builtin(), PageKind::Note).unwrap();
+        assert!(html.starts_with("<!DOCTYPE html>"));
+        assert!(html.contains("<title>A &amp; B</title>"));
+    }
+
+    #[test]
+    fn relative_stylesheet_link() {
+        let links = Links::new(LinkStyle::Relative, Some(PathBuf::from("/rendered")), None);
+        let stylesheet = Some(Stylesheet::Link(PathBuf::from("/rendered/style.css")));
+        let html = Templates::builtin()
+            .render(&Template {
+                kind: PageKind::Note,
+                content: html! {},
+                title: &None,
+                toc: &[],
+                front_matter: &FrontMatter::default(),
+                tags: &[],
+                breadcrumbs: &[],
+                siblings: &Siblings::default(),
+                stylesheet: &stylesheet,
+                syntax_stylesheet: &None,
+                mermaid_script: None,
+                mathjax_policy: &MathjaxPolicy::Never,
+                html_policy: &HtmlPolicy::Trust,
+                links: &links,
+                path: Some(Path::new("/rendered/a/note.html")),
+                search_page: None,
+                table_sort: false,
+            })
+            .unwrap();
+        assert!(html.contains("href=\"../style.css\""), "{}", html);
+    }
+
+    #[test]
+    fn user_template() {
+        let dir = temp_dir("template-user");
+        fs::write(
+            dir.join("note.html"),
+            "<h1>{{ title }}</h1>{{ content }}\
+             {% for entry in toc %}<a href=\"#{{ entry.id }}\">{{ entry.title }}</a>{% endfor %}\
+             {{ metadata.author }}",
+        )
+        .unwrap();
+
+        let templates = Templates::load(Some(&dir)).unwrap();
+        assert_eq!(
+            render(&templates, PageKind::Note).unwrap(),
+            "<h1>A &amp; B</h1><p>Content</p><a href=\"#part\">Part</a>Me"
+        );
+        // Index pages fall back to the built-in template.
+        assert!(render(&templates, PageKind::Index)
+            .unwrap()
+            .starts_with("<!DOCTYPE html>"));
+    }
+
+    #[test]
+    fn syntax_error_names_file_and_line() {
+        let dir = temp_dir("template-syntax-error");
+        fs::write(dir.join("index.html"), "<html>\n{% if %}\n</html>").unwrap();
+
+        let err = Templates::load(Some(&dir)).err().unwrap();
+        let message = err.to_string();
+        assert!(
+            message.starts_with(&format!("{}:2: ", dir.join("index.html").display())),
+            "{}",
+            message
+        );
     }
 }
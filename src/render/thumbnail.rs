@@ -0,0 +1,110 @@
+use crate::sync::hash::SourceContentsHash;
+use std::{
+    fs, io,
+    path::{Path, PathBuf},
+    process::Command,
+};
+
+/// Directory of the thumbnails of images, relative to the render root.
+pub const THUMBNAILS_DIR_NAME: &str = "_thumbnails";
+
+/// Thumbnails of raster images, shown in the gallery of index pages.
+///
+/// Thumbnails are created with an ImageMagick style command, and cached in the render root by the
+/// hash of the image so an image is only resized when it changes.
+pub struct Thumbnails {
+    /// Command that resizes images, such as `convert`. Images are shown at their full size,
+    /// scaled down by the browser, if this is `None` or the command fails.
+    pub command: Option<PathBuf>,
+    /// Largest width and height of a thumbnail, in pixels.
+    pub size: u32,
+    /// Directory the thumbnails are cached in.
+    pub dir: PathBuf,
+}
+
+impl Thumbnails {
+    /// Thumbnail of the image with the contents hash, which may not have been created yet.
+    pub fn path(&self, hash: &SourceContentsHash) -> PathBuf {
+        let hash = u64::from(hash.clone());
+        self.dir.join(format!("{:016x}.png", hash))
+    }
+
+    /// Thumbnail to show for the image with the hash, which is `image` if it doesn't have one.
+    pub fn of(&self, image: &Path, hash: &SourceContentsHash) -> PathBuf {
+        let path = self.path(hash);
+        if path.exists() {
+            path
+        } else {
+            image.to_path_buf()
+        }
+    }
+
+    /// Create the thumbnail of the image, unless it's already cached.
+    pub fn create(&self, image: &Path, hash: &SourceContentsHash) -> io::Result<PathBuf> {
+        let path = self.path(hash);
+        if path.exists() {
+            return Ok(path);
+        }
+        let command = self.command.as_ref().ok_or_else(|| {
+            io::Error::new(io::ErrorKind::NotFound, "creating thumbnails is disabled")
+        })?;
+        fs::create_dir_all(&self.dir)?;
+
+        // Only the first frame of animated images, and only shrinking images that are larger.
+        let output = Command::new(command)
+            .arg(format!("{}[0]", image.display()))
+            .arg("-thumbnail")
+            .arg(format!("{0}x{0}>", self.size))
+            .arg(&path)
+            .output()
+            .map_err(|e| {
+                io::Error::new(
+                    e.kind(),
+                    format!("failed to run {}: {}", command.display(), e),
+                )
+            })?;
+        if !output.status.success() {
+            let _ = fs::remove_file(&path);
+            let stderr = String::from_utf8_lossy(&output.stderr);
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                format!(
+                    "{} failed: {}",
+                    command.display(),
+                    stderr.lines().next().unwrap_or_default()
+                ),
+            ));
+        }
+        Ok(path)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_util::temp_dir;
+
+    #[test]
+    fn cached_thumbnail() {
+        let dir = temp_dir("thumbnail-cached");
+        let thumbnails = Thumbnails {
+            command: None,
+            size: 256,
+            dir: dir.clone(),
+        };
+        let image = Path::new("/notes/pic.png");
+        let hash = SourceContentsHash::from(0xabc);
+        assert_eq!(thumbnails.of(image, &hash), image);
+        assert!(thumbnails.create(image, &hash).is_err());
+
+        fs::write(dir.join("0000000000000abc.png"), "").unwrap();
+        assert_eq!(
+            thumbnails.create(image, &hash).unwrap(),
+            thumbnails.path(&hash)
+        );
+        assert_eq!(
+            thumbnails.of(image, &hash),
+            dir.join("0000000000000abc.png")
+        );
+    }
+}
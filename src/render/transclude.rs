@@ -0,0 +1,198 @@
+use crate::render::{front_matter::FrontMatter, Warning};
+use std::{
+    fs,
+    path::{Path, PathBuf},
+};
+
+/// Deepest that transcluded notes are expanded within each other.
+const MAX_DEPTH: usize = 8;
+
+/// Expand the notes transcluded into the markdown with a `![[note]]` line, with the markdown of
+/// the note without its front matter.
+///
+/// Notes are looked up relative to the directory of the note at `path`, then relative to `root`.
+/// The `.md` extension can be left out, and a `#heading` or `|alias` after the name is ignored.
+/// Notes that can't be read, or that transclude themselves, are left as they are with a warning.
+pub fn expand(markdown: &str, path: &Path, root: &Path) -> (String, Vec<Warning>) {
+    let mut warnings = vec![];
+    let dir = path.parent().unwrap_or(root);
+    let mut parents = vec![fs::canonicalize(path).unwrap_or_else(|_| path.to_path_buf())];
+    let expanded = expand_within(markdown, dir, root, &mut parents, &mut |offset, message| {
+        warnings.push(Warning::new(markdown, offset, message))
+    });
+    (expanded, warnings)
+}
+
+fn expand_within(
+    markdown: &str,
+    dir: &Path,
+    root: &Path,
+    parents: &mut Vec<PathBuf>,
+    warn: &mut dyn FnMut(usize, String),
+) -> String {
+    let mut expanded = String::with_capacity(markdown.len());
+    let mut fence: Option<&str> = None;
+    let mut offset = 0;
+    for line in markdown.split_inclusive('\n') {
+        let line_offset = offset;
+        offset += line.len();
+        let trimmed = line.trim();
+        if let Some(marker) = fence {
+            if trimmed.starts_with(marker) {
+                fence = None;
+            }
+            expanded.push_str(line);
+            continue;
+        }
+        if trimmed.starts_with("```") || trimmed.starts_with("~~~") {
+            fence = Some(&trimmed[..3]);
+            expanded.push_str(line);
+            continue;
+        }
+
+        let path = match embedded_note(trimmed) {
+            Some(name) => resolve(name, dir, root),
+            None => {
+                expanded.push_str(line);
+                continue;
+            }
+        };
+        let path = match path {
+            Some(path) if parents.contains(&path) => {
+                warn(
+                    line_offset,
+                    format!("note transcludes itself: {}", path.display()),
+                );
+                expanded.push_str(line);
+                continue;
+            }
+            Some(_) if parents.len() >= MAX_DEPTH => {
+                warn(
+                    line_offset,
+                    format!("transclusion is over {} deep", MAX_DEPTH),
+                );
+                expanded.push_str(line);
+                continue;
+            }
+            Some(path) => path,
+            None => {
+                warn(
+                    line_offset,
+                    format!("transcluded note not found: {}", trimmed),
+                );
+                expanded.push_str(line);
+                continue;
+            }
+        };
+        let note = match fs::read_to_string(&path) {
+            Ok(note) => note,
+            Err(e) => {
+                warn(
+                    line_offset,
+                    format!("failed to read transcluded note {}: {}", path.display(), e),
+                );
+                expanded.push_str(line);
+                continue;
+            }
+        };
+
+        let (_, body_start) = FrontMatter::split(&note);
+        let note_dir = path.parent().unwrap_or(dir).to_path_buf();
+        parents.push(path);
+        // Warnings within the transcluded note are reported at the line that transcludes it.
+        let body = expand_within(
+            &note[body_start..],
+            &note_dir,
+            root,
+            parents,
+            &mut |_, m| warn(line_offset, m),
+        );
+        parents.pop();
+
+        expanded.push('\n');
+        expanded.push_str(body.trim_end());
+        expanded.push_str("\n\n");
+    }
+    expanded
+}
+
+/// Name of the note a line embeds with `![[name]]`. Embedded files other than notes are left for
+/// the renderer.
+fn embedded_note(line: &str) -> Option<&str> {
+    let name = line.strip_prefix("![[")?.strip_suffix("]]")?;
+    let name = name.split(['|', '#']).next().unwrap_or_default().trim();
+    if name.is_empty() || name.contains("]]") {
+        return None;
+    }
+    match Path::new(name).extension() {
+        Some(extension) if extension != "md" => None,
+        _ => Some(name),
+    }
+}
+
+/// Path of an embedded note, relative to the directory of the note and then the root.
+fn resolve(name: &str, dir: &Path, root: &Path) -> Option<PathBuf> {
+    let name = Path::new(name);
+    let name = match name.extension() {
+        Some(_) => name.to_path_buf(),
+        None => name.with_extension("md"),
+    };
+    [dir, root]
+        .iter()
+        .map(|base| base.join(&name))
+        .find(|path| path.is_file())
+        .map(|path| fs::canonicalize(&path).unwrap_or(path))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_util::temp_dir;
+
+    #[test]
+    fn embedded_note_names() {
+        assert_eq!(embedded_note("![[note]]"), Some("note"));
+        assert_eq!(
+            embedded_note("![[sub/note.md#Heading|Alias]]"),
+            Some("sub/note.md")
+        );
+        assert_eq!(embedded_note("see ![[note]]"), None);
+        assert_eq!(embedded_note("![[]]"), None);
+        assert_eq!(embedded_note("![[pic.png]]"), None);
+    }
+
+    #[test]
+    fn expand_nested_notes() {
+        let root = temp_dir("transclude-nested");
+        fs::create_dir(root.join("sub")).unwrap();
+        fs::write(
+            root.join("a.md"),
+            "---\ntitle: A\n---\nA body\n![[sub/b]]\n",
+        )
+        .unwrap();
+        fs::write(root.join("sub/b.md"), "B body\n![[c]]\n").unwrap();
+        fs::write(root.join("c.md"), "C body\n").unwrap();
+
+        let markdown = "# Top\n\n![[a|The A]]\n\n```\n![[a]]\n```\n";
+        let (expanded, warnings) = expand(markdown, &root.join("top.md"), &root);
+        assert_eq!(
+            expanded,
+            "# Top\n\n\nA body\n\nB body\n\nC body\n\n\n```\n![[a]]\n```\n"
+        );
+        assert!(warnings.is_empty());
+    }
+
+    #[test]
+    fn warn_on_cycles_and_missing_notes() {
+        let root = temp_dir("transclude-cycle");
+        fs::write(root.join("a.md"), "![[b]]\n![[top]]\n").unwrap();
+        fs::write(root.join("b.md"), "![[a]]\n").unwrap();
+        fs::write(root.join("top.md"), "").unwrap();
+
+        let markdown = "![[a]]\n![[missing]]\n";
+        let (_, warnings) = expand(markdown, &root.join("top.md"), &root);
+        let lines: Vec<_> = warnings.iter().map(|warning| warning.line).collect();
+        assert_eq!(lines, vec![1, 1, 2]);
+        assert!(warnings[0].message.contains("transcludes itself"));
+    }
+}
@@ -0,0 +1,221 @@
+use crate::{
+    config::Config,
+    render::{search::tokens, tags},
+    sync::tree::DirTree,
+};
+use globset::GlobSet;
+use index::InvertedIndex;
+use query::Query;
+use serde::Serialize;
+use std::{
+    collections::HashMap,
+    env, fmt, fs,
+    io::{self, IsTerminal},
+    path::PathBuf,
+};
+
+pub mod index;
+pub mod query;
+
+/// Longest snippet shown for a match, in characters.
+const SNIPPET_LENGTH: usize = 120;
+/// Characters of context shown before the first highlight when a snippet is shortened.
+const SNIPPET_CONTEXT: usize = 30;
+
+pub struct SearchOpts {
+    /// Root source directory containing the notes to search.
+    pub src_root: PathBuf,
+    /// Render root, where the inverted index is kept.
+    pub dst_root: PathBuf,
+    pub ignore: GlobSet,
+    /// Tag the notes have to have, or have a tag nested in.
+    pub tag: Option<String>,
+    /// Directory the notes have to be in, relative to the source root.
+    pub dir: Option<PathBuf>,
+}
+
+impl SearchOpts {
+    /// Update the inverted index with the notes that have changed, and search it.
+    pub fn search(&self, query: &Query) -> io::Result<Vec<SearchMatch>> {
+        // Only notes are searched, so other files don't need renderers.
+        let tree = DirTree::with_root(
+            self.src_root.clone(),
+            &self.dst_root,
+            &self.ignore,
+            &HashMap::new(),
+        )?;
+        let mut index = InvertedIndex::read(&self.dst_root);
+        if index.update(&tree.root.source_files())? {
+            index.write(&self.dst_root)?;
+        }
+
+        let tag = match self.tag {
+            Some(ref tag) => Some(tags::normalize(tag).ok_or_else(|| {
+                io::Error::new(io::ErrorKind::InvalidInput, format!("invalid tag: {}", tag))
+            })?),
+            None => None,
+        };
+        let dir = self.dir.as_ref().map(|dir| self.src_root.join(dir));
+        let hits = index.search(query, |note| {
+            tag.as_ref().is_none_or(|tag| note.has_tag(tag))
+                && dir.as_ref().is_none_or(|dir| note.path.starts_with(dir))
+        });
+
+        let mut matches = vec![];
+        for hit in hits {
+            let content = fs::read_to_string(&hit.note.path)?;
+            let line = content
+                .lines()
+                .nth(hit.line as usize - 1)
+                .unwrap_or_default();
+            let (snippet, highlights) = snippet(line, query);
+            matches.push(SearchMatch {
+                path: hit.note.path.clone(),
+                line: hit.line,
+                title: hit.note.title.clone(),
+                score: hit.score,
+                snippet,
+                highlights,
+            });
+        }
+        Ok(matches)
+    }
+}
+
+impl From<Config> for SearchOpts {
+    fn from(config: Config) -> Self {
+        Self {
+            src_root: config.sync.notes_dir,
+            dst_root: config.sync.render_dir,
+            ignore: config.sync.ignore,
+            tag: None,
+            dir: None,
+        }
+    }
+}
+
+/// Note matching a search, with the line that matches best.
+#[derive(Debug, Serialize)]
+pub struct SearchMatch {
+    pub path: PathBuf,
+    pub line: u32,
+    pub title: Option<String>,
+    pub score: f64,
+    /// Line with the match, shortened if it's long.
+    pub snippet: String,
+    /// Byte ranges in the snippet of the words that match the query.
+    pub highlights: Vec<(usize, usize)>,
+}
+
+impl SearchMatch {
+    /// Match shown as `path:line: snippet`, with the path relative to the current directory when
+    /// it's in it, and the matching words in bold if `highlight` is set.
+    pub fn display(&self, highlight: bool) -> impl fmt::Display + '_ {
+        DisplayMatch {
+            search_match: self,
+            highlight,
+        }
+    }
+}
+
+struct DisplayMatch<'a> {
+    search_match: &'a SearchMatch,
+    highlight: bool,
+}
+
+impl<'a> fmt::Display for DisplayMatch<'a> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let search_match = self.search_match;
+        let cwd = env::current_dir().unwrap_or_default();
+        let path = search_match
+            .path
+            .strip_prefix(&cwd)
+            .unwrap_or(&search_match.path);
+        write!(f, "{}:{}: ", path.display(), search_match.line)?;
+
+        let snippet = &search_match.snippet;
+        if !self.highlight {
+            return f.write_str(snippet);
+        }
+        let mut end = 0;
+        for &(start, next_end) in &search_match.highlights {
+            write!(
+                f,
+                "{}\x1b[1;31m{}\x1b[0m",
+                &snippet[end..start],
+                &snippet[start..next_end]
+            )?;
+            end = next_end;
+        }
+        f.write_str(&snippet[end..])
+    }
+}
+
+/// Whether matches written to stdout should be highlighted.
+pub fn should_highlight() -> bool {
+    io::stdout().is_terminal()
+}
+
+/// Snippet of the line, and the ranges of the words in it that match the query.
+fn snippet(line: &str, query: &Query) -> (String, Vec<(usize, usize)>) {
+    let line = line.trim();
+    let highlights: Vec<(usize, usize)> = tokens(line)
+        .filter(|(_, word)| query.matches(word))
+        .map(|(start, _)| (start, start + word_len(&line[start..])))
+        .collect();
+    if line.chars().count() <= SNIPPET_LENGTH {
+        return (line.to_string(), highlights);
+    }
+
+    // Shorten the line around the first highlight.
+    let first = highlights.first().map_or(0, |&(start, _)| start);
+    let start = line[..first]
+        .char_indices()
+        .rev()
+        .nth(SNIPPET_CONTEXT)
+        .map_or(0, |(i, _)| i);
+    let end = line[start..]
+        .char_indices()
+        .nth(SNIPPET_LENGTH)
+        .map_or(line.len(), |(i, _)| start + i);
+    let prefix = if start > 0 { "…" } else { "" };
+    let suffix = if end < line.len() { "…" } else { "" };
+    let snippet = format!("{}{}{}", prefix, &line[start..end], suffix);
+    let highlights = highlights
+        .into_iter()
+        .filter(|&(_, highlight_end)| highlight_end <= end)
+        .filter(|&(highlight_start, _)| highlight_start >= start)
+        .map(|(s, e)| (s - start + prefix.len(), e - start + prefix.len()))
+        .collect();
+    (snippet, highlights)
+}
+
+/// Length in bytes of the word at the start of the text. This is measured in the text rather
+/// than taken from the lowercase word, as lowercasing can change the length of a character.
+fn word_len(text: &str) -> usize {
+    text.find(|c: char| !c.is_alphanumeric())
+        .unwrap_or(text.len())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn snippet_highlights() {
+        let query: Query = "rust* note".parse().unwrap();
+        let (snippet, highlights) = snippet("  A Rusty note, not notes.", &query);
+        assert_eq!(snippet, "A Rusty note, not notes.");
+        assert_eq!(highlights, vec![(2, 7), (8, 12)]);
+    }
+
+    #[test]
+    fn long_snippet_is_shortened() {
+        let line = format!("{} needle {}", "a ".repeat(100), "b ".repeat(100));
+        let query: Query = "needle".parse().unwrap();
+        let (snippet, highlights) = snippet(&line, &query);
+        assert!(snippet.starts_with('…') && snippet.ends_with('…'));
+        let (start, end) = highlights[0];
+        assert_eq!(&snippet[start..end], "needle");
+    }
+}
@@ -0,0 +1,569 @@
+use crate::{
+    render::{search::tokens, source::SourceFormat, tags},
+    search::query::{Clause, Query, Term},
+    sync::{hash::SourceContentsHash, object::SourceFileObject},
+};
+use pulldown_cmark::{Event, Tag};
+use serde::{Deserialize, Serialize};
+use std::{
+    collections::{BTreeMap, HashMap, HashSet},
+    fs, io,
+    path::{Path, PathBuf},
+    str::FromStr,
+};
+
+/// File name of the inverted index, which is kept in the render root next to the hash store.
+const INVERTED_INDEX_FILE_NAME: &str = ".carbon-inverted-index.json";
+
+/// Part of a note that a word is in.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Field {
+    Title,
+    Heading,
+    Tag,
+    Code,
+    Body,
+}
+
+impl Field {
+    /// How much a match in the field counts towards the rank of a note.
+    fn weight(self) -> f64 {
+        match self {
+            Field::Title => 10.0,
+            Field::Heading | Field::Tag => 5.0,
+            Field::Code | Field::Body => 1.0,
+        }
+    }
+}
+
+impl FromStr for Field {
+    type Err = io::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "title" => Ok(Field::Title),
+            "heading" => Ok(Field::Heading),
+            "tag" => Ok(Field::Tag),
+            "code" => Ok(Field::Code),
+            "body" => Ok(Field::Body),
+            _ => Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                format!("unknown search field: {}", s),
+            )),
+        }
+    }
+}
+
+/// Note in the inverted index.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct IndexedNote {
+    pub path: PathBuf,
+    hash: SourceContentsHash,
+    pub title: Option<String>,
+    pub tags: Vec<String>,
+}
+
+impl IndexedNote {
+    /// Whether the note has the tag, or a tag nested in it.
+    pub fn has_tag(&self, tag: &str) -> bool {
+        self.tags.iter().any(|t| {
+            t == tag
+                || t.strip_prefix(tag)
+                    .is_some_and(|rest| rest.starts_with('/'))
+        })
+    }
+}
+
+/// Occurrences of a word in a field of a note.
+#[derive(Debug, Serialize, Deserialize)]
+struct Posting {
+    note: u32,
+    field: Field,
+    /// Position of each occurrence among the words of the note, and the line it's on.
+    occurrences: Vec<(u32, u32)>,
+}
+
+/// Note matching a query.
+#[derive(Debug)]
+pub struct Hit<'a> {
+    pub note: &'a IndexedNote,
+    pub score: f64,
+    /// Line with the most matches.
+    pub line: u32,
+}
+
+/// Inverted index from words to the notes they're in, kept between searches and syncs so only
+/// changed notes are read again.
+#[derive(Debug, Default, Serialize, Deserialize)]
+pub struct InvertedIndex {
+    notes: BTreeMap<u32, IndexedNote>,
+    words: BTreeMap<String, Vec<Posting>>,
+    next_id: u32,
+}
+
+impl InvertedIndex {
+    /// Read the index in the render root, or an empty index if there isn't a valid one.
+    pub fn read(render_root: &Path) -> Self {
+        fs::read_to_string(render_root.join(INVERTED_INDEX_FILE_NAME))
+            .ok()
+            .and_then(|json| serde_json::from_str(&json).ok())
+            .unwrap_or_default()
+    }
+
+    pub fn write(&self, render_root: &Path) -> io::Result<()> {
+        let json = serde_json::to_string(&self)
+            .map_err(|e| io::Error::other(format!("failed to serialize inverted index: {}", e)))?;
+        fs::create_dir_all(render_root)?;
+        fs::write(render_root.join(INVERTED_INDEX_FILE_NAME), json)
+    }
+
+    /// Index the notes that have changed since the index was written, and drop the notes that
+    /// no longer exist. Returns whether the index changed.
+    pub fn update(&mut self, notes: &[&SourceFileObject]) -> io::Result<bool> {
+        let current: HashMap<&Path, &SourceContentsHash> = notes
+            .iter()
+            .map(|note| (note.path.as_path(), &note.contents_hash))
+            .collect();
+        let stale: HashSet<u32> = self
+            .notes
+            .iter()
+            .filter(|(_, note)| current.get(note.path.as_path()) != Some(&&note.hash))
+            .map(|(&id, _)| id)
+            .collect();
+        let indexed: HashSet<&Path> = self
+            .notes
+            .iter()
+            .filter(|(id, _)| !stale.contains(id))
+            .map(|(_, note)| note.path.as_path())
+            .collect();
+        let changed: Vec<&SourceFileObject> = notes
+            .iter()
+            .copied()
+            .filter(|note| !indexed.contains(note.path.as_path()))
+            .collect();
+        if stale.is_empty() && changed.is_empty() {
+            return Ok(false);
+        }
+
+        self.notes.retain(|id, _| !stale.contains(id));
+        for postings in self.words.values_mut() {
+            postings.retain(|posting| !stale.contains(&posting.note));
+        }
+        self.words.retain(|_, postings| !postings.is_empty());
+
+        for note in changed {
+            self.insert(note, &note.read_content()?, note.format());
+        }
+        Ok(true)
+    }
+
+    fn insert(&mut self, note: &SourceFileObject, source: &str, format: SourceFormat) {
+        let id = self.next_id;
+        self.next_id += 1;
+
+        let parsed = ParsedNote::parse(source, format);
+        let mut postings: BTreeMap<(&str, Field), Vec<(u32, u32)>> = BTreeMap::new();
+        for (position, word) in parsed.words.iter().enumerate() {
+            postings
+                .entry((&word.text, word.field))
+                .or_default()
+                .push((position as u32, word.line));
+        }
+        for ((text, field), occurrences) in postings {
+            self.words
+                .entry(text.to_string())
+                .or_default()
+                .push(Posting {
+                    note: id,
+                    field,
+                    occurrences,
+                });
+        }
+
+        let indexed = IndexedNote {
+            path: note.path.clone(),
+            hash: note.contents_hash.clone(),
+            title: parsed.title,
+            tags: parsed.tags,
+        };
+        self.notes.insert(id, indexed);
+    }
+
+    /// Notes that match the query and pass the filter, best match first.
+    pub fn search<F>(&self, query: &Query, filter: F) -> Vec<Hit<'_>>
+    where
+        F: Fn(&IndexedNote) -> bool,
+    {
+        let mut matches: Option<HashMap<u32, Vec<(Field, u32)>>> = None;
+        let mut scores: HashMap<u32, f64> = HashMap::new();
+        for clause in &query.clauses {
+            let occurrences = self.clause_occurrences(clause);
+            for (note, occurrences) in &occurrences {
+                let mut counts: HashMap<Field, usize> = HashMap::new();
+                for (field, _) in occurrences {
+                    *counts.entry(*field).or_default() += 1;
+                }
+                let score: f64 = counts
+                    .iter()
+                    .map(|(field, &count)| field.weight() * (1.0 + (count as f64).ln()))
+                    .sum();
+                *scores.entry(*note).or_default() += score;
+            }
+
+            matches = Some(match matches {
+                None => occurrences,
+                Some(mut matches) => {
+                    matches.retain(|note, _| occurrences.contains_key(note));
+                    for (note, found) in matches.iter_mut() {
+                        found.extend(&occurrences[note]);
+                    }
+                    matches
+                }
+            });
+        }
+
+        let mut hits: Vec<Hit> = matches
+            .unwrap_or_default()
+            .into_iter()
+            .filter_map(|(id, occurrences)| {
+                let note = self.notes.get(&id).filter(|&note| filter(note))?;
+                Some(Hit {
+                    note,
+                    score: scores[&id],
+                    line: best_line(&occurrences),
+                })
+            })
+            .collect();
+        hits.sort_by(|a, b| {
+            b.score
+                .partial_cmp(&a.score)
+                .unwrap_or(std::cmp::Ordering::Equal)
+                .then_with(|| a.note.path.cmp(&b.note.path))
+        });
+        hits
+    }
+
+    /// Field and line of each match of the clause, by note.
+    fn clause_occurrences(&self, clause: &Clause) -> HashMap<u32, Vec<(Field, u32)>> {
+        let mut matches: HashMap<u32, Vec<(Field, u32)>> = HashMap::new();
+        let (first, rest) = match clause.terms.split_first() {
+            Some(terms) => terms,
+            None => return matches,
+        };
+        // Positions of the rest of the phrase, which have to follow the first term.
+        let following: Vec<HashSet<(u32, Field, u32)>> = rest
+            .iter()
+            .map(|term| {
+                self.postings(term, clause.field)
+                    .flat_map(|posting| {
+                        posting
+                            .occurrences
+                            .iter()
+                            .map(move |&(position, _)| (posting.note, posting.field, position))
+                    })
+                    .collect()
+            })
+            .collect();
+
+        for posting in self.postings(first, clause.field) {
+            for &(position, line) in &posting.occurrences {
+                let is_phrase = following.iter().enumerate().all(|(i, positions)| {
+                    positions.contains(&(posting.note, posting.field, position + i as u32 + 1))
+                });
+                if is_phrase {
+                    matches
+                        .entry(posting.note)
+                        .or_default()
+                        .push((posting.field, line));
+                }
+            }
+        }
+        matches
+    }
+
+    /// Postings of the words matching the term, in the field if there is one.
+    fn postings<'a>(
+        &'a self,
+        term: &'a Term,
+        field: Option<Field>,
+    ) -> impl Iterator<Item = &'a Posting> + 'a {
+        self.words
+            .range(term.text.clone()..)
+            .take_while(move |(word, _)| word.starts_with(&term.text))
+            .filter(move |(word, _)| term.matches(word))
+            .flat_map(|(_, postings)| postings)
+            .filter(move |posting| field.is_none_or(|field| posting.field == field))
+    }
+}
+
+/// Line with the most weight of matches, or the first of them if there's a tie.
+fn best_line(occurrences: &[(Field, u32)]) -> u32 {
+    let mut lines: BTreeMap<u32, f64> = BTreeMap::new();
+    for &(field, line) in occurrences {
+        *lines.entry(line).or_default() += field.weight();
+    }
+    lines
+        .into_iter()
+        .fold(
+            None,
+            |best: Option<(u32, f64)>, (line, weight)| match best {
+                Some((_, best_weight)) if best_weight >= weight => best,
+                _ => Some((line, weight)),
+            },
+        )
+        .map_or(1, |(line, _)| line)
+}
+
+/// Word in a note, along with the field and line it's in.
+struct Word {
+    text: String,
+    field: Field,
+    line: u32,
+}
+
+/// Words, title and tags of a note.
+struct ParsedNote {
+    title: Option<String>,
+    tags: Vec<String>,
+    words: Vec<Word>,
+}
+
+impl ParsedNote {
+    fn parse(source: &str, format: SourceFormat) -> Self {
+        let lines = LineIndex::new(source);
+        let parsed = format.parse(source);
+        let body_start = parsed.body_start;
+        let front_matter = parsed.front_matter.unwrap_or_default();
+        let mut words = vec![];
+        let mut push_words = |text: &str, offset: Option<usize>, field: Field, line: u32| {
+            for (i, word) in tokens(text) {
+                words.push(Word {
+                    text: word,
+                    field,
+                    line: offset.map_or(line, |offset| lines.line(offset + i)),
+                });
+            }
+        };
+
+        // Fields in the front matter are found on the line with their key, which is `#+KEY:` in
+        // Org notes.
+        let key_line = |keys: &[&str]| {
+            let mut offset = 0;
+            for line in source[..body_start].split_inclusive('\n') {
+                let line_offset = offset;
+                offset += line.len();
+                let line = line.strip_prefix("#+").unwrap_or(line);
+                let matches = |key: &&str| {
+                    line.get(..key.len() + 1)
+                        .is_some_and(|start| start.eq_ignore_ascii_case(&format!("{}:", key)))
+                };
+                if keys.iter().any(matches) {
+                    return lines.line(line_offset);
+                }
+            }
+            1
+        };
+        let mut title = front_matter.title().map(String::from);
+        if let Some(ref title) = title {
+            push_words(title, None, Field::Title, key_line(&["title"]));
+        }
+        let mut tags = tags::from_front_matter(&front_matter);
+        for tag in &tags {
+            push_words(tag, None, Field::Tag, key_line(&["tags", "filetags"]));
+        }
+
+        let mut fields = vec![];
+        let mut heading = None;
+        let mut inline_tags = vec![];
+        for (i, (event, range)) in parsed.events.into_iter().enumerate() {
+            let start = body_start + range.start;
+            match event {
+                Event::Start(Tag::Header(level)) => {
+                    let is_title = level == 1 && i == 0 && title.is_none();
+                    heading = Some(String::new());
+                    fields.push(if is_title {
+                        Field::Title
+                    } else {
+                        Field::Heading
+                    });
+                }
+                Event::End(Tag::Header(_)) => {
+                    if fields.pop() == Some(Field::Title) {
+                        title = heading.take();
+                    }
+                    heading = None;
+                }
+                Event::Start(Tag::Code) | Event::Start(Tag::CodeBlock(_)) => {
+                    fields.push(Field::Code)
+                }
+                Event::End(Tag::Code) | Event::End(Tag::CodeBlock(_)) => {
+                    fields.pop();
+                }
+                Event::Text(text) => {
+                    let field = fields.last().copied().unwrap_or(Field::Body);
+                    if let Some(ref mut heading) = heading {
+                        heading.push_str(&text);
+                    }
+                    // Text is usually the source itself, so words can be found on their own
+                    // lines. Otherwise they're all put on the line the text starts on.
+                    let is_source = source.get(start..start + text.len()) == Some(&text);
+                    let offset = is_source.then_some(start);
+                    push_words(&text, offset, field, lines.line(start));
+                    if field != Field::Code {
+                        let line = lines.line(start);
+                        inline_tags
+                            .extend(tags::from_text(&text).into_iter().map(|tag| (tag, line)));
+                    }
+                }
+                _ => {}
+            }
+        }
+        // Inline tags go after the rest of the words, so they don't come between the words of a
+        // phrase.
+        for (tag, line) in inline_tags {
+            push_words(&tag, None, Field::Tag, line);
+            tags.push(tag);
+        }
+        tags.sort();
+        tags.dedup();
+
+        Self { title, tags, words }
+    }
+}
+
+/// Offsets of the start of each line, to find the line of an offset.
+struct LineIndex(Vec<usize>);
+
+impl LineIndex {
+    fn new(text: &str) -> Self {
+        let starts = std::iter::once(0)
+            .chain(text.match_indices('\n').map(|(i, _)| i + 1))
+            .collect();
+        Self(starts)
+    }
+
+    /// Line number, starting at 1, of the offset.
+    fn line(&self, offset: usize) -> u32 {
+        self.0.partition_point(|&start| start <= offset) as u32
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn index(notes: &[(&str, &str)]) -> InvertedIndex {
+        let mut index = InvertedIndex::default();
+        for (path, markdown) in notes {
+            index.insert(
+                &SourceFileObject::from(*path),
+                markdown,
+                SourceFormat::Markdown,
+            );
+        }
+        index
+    }
+
+    fn search(index: &InvertedIndex, query: &str) -> Vec<(String, u32)> {
+        index
+            .search(&query.parse().unwrap(), |_| true)
+            .into_iter()
+            .map(|hit| (hit.note.path.display().to_string(), hit.line))
+            .collect()
+    }
+
+    #[test]
+    fn parse_note_fields() {
+        let note = ParsedNote::parse(
+            "---\ntags: [notes]\n---\n# Title\n\nSome text #rust\n\n## Part\n\n`code`\n",
+            SourceFormat::Markdown,
+        );
+        assert_eq!(note.title.as_deref(), Some("Title"));
+        assert_eq!(note.tags, vec!["notes", "rust"]);
+        let words: Vec<(&str, Field, u32)> = note
+            .words
+            .iter()
+            .map(|word| (word.text.as_str(), word.field, word.line))
+            .collect();
+        assert_eq!(
+            words,
+            vec![
+                ("notes", Field::Tag, 2),
+                ("title", Field::Title, 4),
+                ("some", Field::Body, 6),
+                ("text", Field::Body, 6),
+                ("rust", Field::Body, 6),
+                ("part", Field::Heading, 8),
+                ("code", Field::Code, 10),
+                ("rust", Field::Tag, 6),
+            ]
+        );
+    }
+
+    #[test]
+    fn parse_org_note_fields() {
+        let note = ParsedNote::parse(
+            "#+TITLE: Org Title\n#+FILETAGS: :notes:\n\n* Part\nSome =code=\n",
+            SourceFormat::Org,
+        );
+        assert_eq!(note.title.as_deref(), Some("Org Title"));
+        let words: Vec<(&str, Field, u32)> = note
+            .words
+            .iter()
+            .map(|word| (word.text.as_str(), word.field, word.line))
+            .collect();
+        assert_eq!(
+            words,
+            vec![
+                ("org", Field::Title, 1),
+                ("title", Field::Title, 1),
+                ("notes", Field::Tag, 2),
+                ("part", Field::Heading, 4),
+                ("some", Field::Body, 5),
+                ("code", Field::Code, 5),
+            ]
+        );
+    }
+
+    #[test]
+    fn rank_by_field() {
+        let index = index(&[
+            ("body.md", "# Other\n\nAbout sync here"),
+            ("title.md", "# Sync\n\nText"),
+        ]);
+        assert_eq!(
+            search(&index, "sync"),
+            vec![(String::from("title.md"), 1), (String::from("body.md"), 3)]
+        );
+        assert_eq!(
+            search(&index, "title:sync"),
+            vec![(String::from("title.md"), 1)]
+        );
+    }
+
+    #[test]
+    fn phrases_and_prefixes() {
+        let index = index(&[
+            ("a.md", "the quick brown fox"),
+            ("b.md", "the brown quick fox"),
+        ]);
+        assert_eq!(search(&index, "\"quick brown\"").len(), 1);
+        assert_eq!(search(&index, "\"quick bro*\"").len(), 1);
+        assert_eq!(search(&index, "qui* fox").len(), 2);
+        assert!(search(&index, "qui").is_empty());
+    }
+
+    #[test]
+    fn nested_tags() {
+        let note = IndexedNote {
+            path: PathBuf::from("a.md"),
+            hash: SourceContentsHash::default(),
+            title: None,
+            tags: vec![String::from("project/carbon")],
+        };
+        assert!(note.has_tag("project"));
+        assert!(note.has_tag("project/carbon"));
+        assert!(!note.has_tag("proj"));
+    }
+}
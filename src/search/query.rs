@@ -0,0 +1,175 @@
+use crate::{render::search::tokenize, search::index::Field};
+use std::{io, str::FromStr};
+
+/// Word to search for, which matches words starting with it if it's a prefix.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Term {
+    pub text: String,
+    pub prefix: bool,
+}
+
+impl Term {
+    pub fn matches(&self, word: &str) -> bool {
+        if self.prefix {
+            word.starts_with(&self.text)
+        } else {
+            word == self.text
+        }
+    }
+}
+
+/// Part of a query that every matching note has to contain. A clause with more than one term is
+/// a phrase, where the terms have to be consecutive words.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Clause {
+    /// Field the clause is limited to, if any.
+    pub field: Option<Field>,
+    pub terms: Vec<Term>,
+}
+
+/// Search query.
+///
+/// A query is made of words, which match whole words, `prefix*` words, which match words starting
+/// with the prefix, and `"quoted phrases"`. Each can be limited to a field with `title:`,
+/// `heading:`, `tag:`, `code:` or `body:`. Notes match when they match every part of the query.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Query {
+    pub clauses: Vec<Clause>,
+}
+
+impl Query {
+    /// Whether a word matches any of the terms in the query.
+    pub fn matches(&self, word: &str) -> bool {
+        self.clauses
+            .iter()
+            .flat_map(|clause| &clause.terms)
+            .any(|term| term.matches(word))
+    }
+}
+
+impl FromStr for Query {
+    type Err = io::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut clauses = vec![];
+        let mut rest = s.trim_start();
+        while !rest.is_empty() {
+            let mut field = None;
+            if let Some((name, after)) = rest.split_once(':') {
+                if !name.contains(char::is_whitespace) {
+                    if let Ok(name) = name.parse() {
+                        field = Some(name);
+                        rest = after;
+                    }
+                }
+            }
+
+            let text = match rest.strip_prefix('"') {
+                Some(quoted) => {
+                    let end = quoted.find('"').ok_or_else(|| {
+                        io::Error::new(
+                            io::ErrorKind::InvalidInput,
+                            format!("unterminated phrase in query: {}", s),
+                        )
+                    })?;
+                    rest = &quoted[end + 1..];
+                    &quoted[..end]
+                }
+                None => {
+                    let end = rest.find(char::is_whitespace).unwrap_or(rest.len());
+                    let word = &rest[..end];
+                    rest = &rest[end..];
+                    word
+                }
+            };
+            rest = rest.trim_start();
+
+            let terms = terms(text);
+            if !terms.is_empty() {
+                clauses.push(Clause { field, terms });
+            }
+        }
+
+        if clauses.is_empty() {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                format!("query has no words to search for: {}", s),
+            ));
+        }
+        Ok(Self { clauses })
+    }
+}
+
+/// Terms in the text of a word or phrase. A word ending with `*` is a prefix.
+fn terms(text: &str) -> Vec<Term> {
+    let mut terms = vec![];
+    for word in text.split_whitespace() {
+        let start = terms.len();
+        terms.extend(tokenize(word).map(|text| Term {
+            text,
+            prefix: false,
+        }));
+        if word.ends_with('*') && terms.len() > start {
+            if let Some(last) = terms.last_mut() {
+                last.prefix = true;
+            }
+        }
+    }
+    terms
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn term(text: &str, prefix: bool) -> Term {
+        Term {
+            text: String::from(text),
+            prefix,
+        }
+    }
+
+    #[test]
+    fn parse_query() {
+        let query: Query = "Rust title:\"Getting Start*\" tag:notes sync*"
+            .parse()
+            .unwrap();
+        assert_eq!(
+            query.clauses,
+            vec![
+                Clause {
+                    field: None,
+                    terms: vec![term("rust", false)],
+                },
+                Clause {
+                    field: Some(Field::Title),
+                    terms: vec![term("getting", false), term("start", true)],
+                },
+                Clause {
+                    field: Some(Field::Tag),
+                    terms: vec![term("notes", false)],
+                },
+                Clause {
+                    field: None,
+                    terms: vec![term("sync", true)],
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn unknown_field_is_a_word() {
+        let query: Query = "see:also".parse().unwrap();
+        assert_eq!(query.clauses[0].field, None);
+        assert_eq!(
+            query.clauses[0].terms,
+            vec![term("see", false), term("also", false)]
+        );
+    }
+
+    #[test]
+    fn invalid_queries() {
+        assert!("\"open phrase".parse::<Query>().is_err());
+        assert!("a ! ?".parse::<Query>().is_err());
+    }
+}
@@ -1,25 +1,57 @@
 use crate::{
     config::Config,
+    journal::{self, Date, Journal},
     render::{
-        code::SyntaxHighlighter, index::Index, mathjax::MathjaxPolicy, stylesheet::Stylesheet,
-        RenderOpts, ToHtml,
+        calendar::CalendarPage,
+        code::{HighlightStyle, SyntaxHighlighter},
+        diagram::Diagrams,
+        file::{FilePage, FileRenderer},
+        index::{self, Index, IndexSort, INDEX_FILE_NAME},
+        links::Links,
+        mathjax::MathjaxPolicy,
+        nav::{self, PageLink, Siblings},
+        sanitize::HtmlPolicy,
+        search::{SearchPage, SEARCH_PAGE_FILE_NAME, SEARCH_SCRIPT, SEARCH_SCRIPT_FILE_NAME},
+        source::SourceFormat,
+        stylesheet::Stylesheet,
+        tags::{self, TagPage, TaggedNote, TAGS_DIR_NAME, TAG_PAGES_DIR_NAME},
+        template::Templates,
+        thumbnail::{Thumbnails, THUMBNAILS_DIR_NAME},
+        RenderOpts, Rendered,
     },
+    search::index::InvertedIndex,
 };
 use globset::GlobSet;
-use object::SourceFileObject;
+use journal_store::JournalStore;
+use object::{DirObject, Object, SourceFileObject};
+use search_index::SearchIndex;
 use std::{
+    collections::{BTreeSet, HashMap, HashSet},
     convert::TryFrom,
     fs::{self, File},
     io::{self, Write},
-    path::PathBuf,
+    path::{Path, PathBuf},
 };
+use summary::SyncSummary;
+use tag_store::{TagStore, TaggedSource};
 use tree::DirTree;
 
+pub mod hash;
 pub mod object;
+pub mod summary;
+pub mod tree;
 
-mod hash;
+/// File name of the generated stylesheet for classed code blocks, relative to the render root.
+const SYNTAX_STYLESHEET_FILE_NAME: &str = "syntax.css";
+/// File name of the copy of the mermaid script, relative to the render root.
+const MERMAID_SCRIPT_FILE_NAME: &str = "mermaid.min.js";
+/// File name of the copy of the stylesheet when links are portable, relative to the render root.
+const STYLESHEET_FILE_NAME: &str = "style.css";
+
+mod journal_store;
+mod search_index;
 mod store;
-mod tree;
+mod tag_store;
 
 pub struct SyncOpts {
     /// Root source directory containing notes to be synced.
@@ -27,51 +59,612 @@ pub struct SyncOpts {
     /// Root destination directory which nodes from `src` will be synced to.
     pub dst_root: PathBuf,
     pub ignore: GlobSet,
+    pub index_sort: IndexSort,
     pub mathjax_policy: MathjaxPolicy,
+    pub html_policy: HtmlPolicy,
+    /// How links between rendered pages, and to stylesheets and scripts, are written.
+    pub links: Links,
+    /// User templates for note and index pages.
+    pub templates: Templates,
     pub stylesheet: Option<Stylesheet>,
+    /// Stylesheet that is copied into the render root when links are portable.
+    pub stylesheet_source: Option<PathBuf>,
+    /// Stylesheet for classed code blocks, if the syntax highlighter uses classes.
+    pub syntax_stylesheet: Option<Stylesheet>,
     pub syntax_highlighter: SyntaxHighlighter,
+    pub diagrams: Diagrams,
+    /// Mermaid script that is copied into the render root for pages with mermaid diagrams.
+    pub mermaid_script_source: Option<PathBuf>,
+    /// Search page that every page links to with a search box, if search is enabled.
+    pub search_page: Option<PathBuf>,
+    /// How files that aren't notes are rendered as pages, by their extension.
+    pub file_renderers: HashMap<String, FileRenderer>,
+    pub thumbnails: Thumbnails,
+    /// Directory of daily notes, whose years and months get calendar pages.
+    pub journal_root: Option<PathBuf>,
 }
 
 impl SyncOpts {
-    pub fn sync(&self) -> io::Result<()> {
+    pub fn sync(&self) -> io::Result<SyncSummary> {
         if !self.dst_root.exists() {
             fs::create_dir_all(&self.dst_root)?;
         }
+        self.write_syntax_stylesheet()?;
+        if let (Some(source), Some(Stylesheet::Link(dest))) =
+            (&self.stylesheet_source, &self.stylesheet)
+        {
+            copy_asset(source, dest)?;
+        }
+        if let (Some(source), Some(dest)) =
+            (&self.mermaid_script_source, &self.diagrams.mermaid_script)
+        {
+            copy_asset(source, dest)?;
+        }
+
+        let tags_dir = self.dst_root.join(TAGS_DIR_NAME);
+        let mut tag_store = TagStore::read(&tags_dir);
+        let mut changed_tags = BTreeSet::new();
 
-        let mut tree = DirTree::with_root(self.src_root.clone(), &self.dst_root, &self.ignore)?;
+        let mut summary = SyncSummary::default();
+        // Files embedded by each note that's rendered, so it's rendered again when they change.
+        let mut dependencies = HashMap::new();
+        let mut tree = DirTree::with_root(
+            self.src_root.clone(),
+            &self.dst_root,
+            &self.ignore,
+            &self.file_renderers,
+        )?;
+        if self.links.is_portable() {
+            self.copy_files(&tree.root)?;
+        }
+        let journal = self
+            .journal_root
+            .as_deref()
+            .map(|root| Journal::of(&tree.root, root));
+        let mut rendered_days = HashSet::new();
+        let mut changed_years = BTreeSet::new();
         for dir in tree.walk() {
             if !dir.object.render_path.exists() {
                 fs::create_dir(&dir.object.render_path)?;
             }
 
-            for source_file in &dir.to_render {
-                self.render(source_file)?;
+            let notes = sibling_notes(dir.object);
+            let links: Vec<PageLink> = notes
+                .iter()
+                .map(|note| note_link(note, &self.links))
+                .collect();
+            // Adding or removing a note changes the siblings of the notes next to it, which
+            // also changes the children of the directory.
+            let changed_siblings = if dir.should_render_index {
+                let paths: Vec<&Path> = notes.iter().map(|note| note.path.as_path()).collect();
+                let previous = sibling_paths(dir.previous_sources.iter().map(PathBuf::as_path));
+                changed_siblings(&paths, &previous)
+            } else {
+                HashSet::new()
+            };
+            for (i, source_file) in notes.into_iter().enumerate() {
+                let is_changed = dir
+                    .to_render
+                    .iter()
+                    .any(|file| file.path == source_file.path);
+                if !is_changed && !changed_siblings.contains(source_file.path.as_path()) {
+                    continue;
+                }
+                // Daily notes link to the days before and after them, wherever they are.
+                let date = journal
+                    .as_ref()
+                    .and_then(|journal| journal.date_of(&source_file.path));
+                let siblings = match (&journal, date) {
+                    (Some(journal), Some(date)) => journal.siblings(date, &self.links),
+                    _ => Siblings::of(&links, i),
+                };
+                let rendered = self.render(source_file, siblings)?;
+                rendered_days.extend(date);
+                summary.add_warnings(&source_file.path, rendered.warnings);
+                summary.notes_rendered += 1;
+                dependencies.insert(source_file.path.clone(), rendered.dependencies);
+
+                let tagged = TaggedSource {
+                    title: rendered.title.unwrap_or_else(|| links[i].name.clone()),
+                    render_path: source_file.render_path.clone(),
+                    tags: rendered.tags,
+                };
+                changed_tags.extend(tag_store.update(&source_file.path, tagged));
+            }
+
+            for file in &dir.files_to_render {
+                if file.renderer == FileRenderer::Image && self.thumbnails.command.is_some() {
+                    if let Err(e) = self.thumbnails.create(&file.path, &file.contents_hash) {
+                        eprintln!(
+                            "failed to create thumbnail for {}: {}",
+                            file.path.display(),
+                            e
+                        );
+                    }
+                }
+                let page = FilePage::new(self, dir.object, file)?;
+                self.write_page(
+                    page.render_path(),
+                    &self.templates.render(&page.template())?,
+                )?;
+                println!("rendered file at: {}", file.render_path.display());
+                summary.files_rendered += 1;
             }
 
-            if dir.should_render_index {
+            // Years and months of the journal have calendars instead, which only change with the
+            // days that have notes.
+            let calendar = journal
+                .as_ref()
+                .and_then(|journal| journal::calendar_of(journal.root, &dir.object.path));
+            if let Some((year, _)) = calendar {
+                if dir.should_render_index {
+                    changed_years.insert(year);
+                }
+            // Index pages show the title and details of each note and file, so they change with
+            // them.
+            } else if dir.should_render_index
+                || !dir.to_render.is_empty()
+                || !dir.files_to_render.is_empty()
+            {
                 println!("building index for {:?}", dir.object.path);
-                let index = Index::new(self, &dir.object)?;
+                let intro = match index::intro_file(dir.object) {
+                    Some(file) => {
+                        let mut intro = self.render_intro(dir.object, file)?;
+                        summary.add_warnings(&file.path, std::mem::take(&mut intro.warnings));
+                        dependencies
+                            .insert(file.path.clone(), std::mem::take(&mut intro.dependencies));
+                        Some(intro)
+                    }
+                    None => None,
+                };
+                let index = Index::new(self, dir.object, intro)?;
+                let html = self.templates.render(&index.template())?;
                 File::create(index.render_path())
-                    .and_then(|mut fh| fh.write_all(index.to_html().as_bytes()))?;
+                    .and_then(|mut fh| fh.write_all(html.as_bytes()))?;
+                summary.indexes_rendered += 1;
+            }
+        }
+
+        if let Some(ref journal) = journal {
+            self.render_journal(journal, &rendered_days, changed_years, &mut summary)?;
+        }
+
+        let pages = source_pages(&tree.root);
+        let sources: HashSet<&Path> = pages.iter().map(|(file, _)| file.path.as_path()).collect();
+        changed_tags.extend(tag_store.retain(|path| sources.contains(path)));
+        summary.indexes_rendered += self.render_tags(&tag_store, &changed_tags)?;
+        tag_store.write(&tags_dir)?;
+        if self.render_search(&pages)? {
+            summary.indexes_rendered += 1;
+        }
+        let mut inverted_index = InvertedIndex::read(&self.dst_root);
+        if inverted_index.update(&tree.root.source_files())? {
+            inverted_index.write(&self.dst_root)?;
+        }
+
+        tree.persist_hashes(&dependencies)?;
+        Ok(summary)
+    }
+
+    /// Options for rendering single notes outside of a sync. Assets are linked where they are
+    /// instead of being copied into the render root, and the stylesheet for classed code blocks
+    /// is inlined.
+    pub fn standalone(mut self) -> Self {
+        if let (Some(source), Some(Stylesheet::Link(path))) =
+            (self.stylesheet_source.take(), &mut self.stylesheet)
+        {
+            *path = source;
+        }
+        if self.syntax_highlighter.style() == HighlightStyle::Classed {
+            let css = self.syntax_highlighter.stylesheet();
+            self.syntax_stylesheet = Some(Stylesheet::Inline(css));
+        }
+        if let Some(source) = self.mermaid_script_source.take() {
+            self.diagrams.mermaid_script = Some(source);
+        }
+        self
+    }
+
+    /// Render a single note written in `format`, where `base_dir` is the directory the note is
+    /// in and `output` is where the page is written, if it's written to a file.
+    ///
+    /// Notes in the notes root are rendered as they are in a sync, with breadcrumbs, tags and
+    /// links to other notes going to the render root.
+    pub fn render_note(
+        &self,
+        markdown: &str,
+        format: SourceFormat,
+        base_dir: &Path,
+        output: Option<&Path>,
+    ) -> io::Result<Rendered> {
+        let mut opts = self
+            .render_opts()
+            .with_links(self.links.clone())
+            .with_base_dir(base_dir.to_path_buf())
+            .with_format(format);
+        if let Some(output) = output {
+            opts = opts.with_render_path(output.to_path_buf());
+        }
+        if let Ok(relative_dir) = base_dir.strip_prefix(&self.src_root) {
+            let breadcrumbs = nav::breadcrumbs(
+                &self.src_root,
+                &self.dst_root,
+                base_dir,
+                &self.links,
+                output,
+            );
+            opts = opts
+                .with_breadcrumbs(breadcrumbs)
+                .with_tags_dir(self.dst_root.join(TAGS_DIR_NAME))
+                .with_rendered_dir(self.dst_root.join(relative_dir));
+        }
+        opts.render(markdown)
+    }
+
+    /// Copy in the render root of a file in `dir` that isn't a note, which pages link to instead
+    /// of the file in the source tree so the render output can be moved.
+    ///
+    /// Files are only copied when links are portable, and not over the pages rendered into the
+    /// directory, e.g. `foo.html` next to `foo.md`.
+    pub fn copy_of(&self, dir: &DirObject, path: &Path) -> Option<PathBuf> {
+        if !self.links.is_portable() || !path.is_file() {
+            return None;
+        }
+        let copy = self.dst_root.join(path.strip_prefix(&self.src_root).ok()?);
+        let is_page = copy == dir.render_path.join(INDEX_FILE_NAME)
+            || self.search_page.as_ref() == Some(&copy)
+            || dir.children.iter().any(|child| match child {
+                Object::SourceFile(file) => file.render_path == copy,
+                Object::RenderedFile(file) => file.render_path == copy,
+                _ => false,
+            });
+        if is_page {
+            return None;
+        }
+        Some(copy)
+    }
+
+    /// Copy the files in the directory and its subdirectories that aren't notes into the render
+    /// root, next to the notes that link to them relative to themselves.
+    fn copy_files(&self, dir: &DirObject) -> io::Result<()> {
+        for child in &dir.children {
+            match child {
+                Object::Dir(child_dir) => self.copy_files(child_dir)?,
+                Object::RenderedFile(_) | Object::File(_) | Object::Symlink(_) => {
+                    if let Some(dest) = self.copy_of(dir, child.path()) {
+                        if let Some(parent) = dest.parent() {
+                            fs::create_dir_all(parent)?;
+                        }
+                        copy_asset(child.path(), &dest)?;
+                    }
+                }
+                Object::SourceFile(_) => {}
             }
         }
-        tree.persist_hashes()
+        Ok(())
     }
 
-    fn render(&self, file: &SourceFileObject) -> io::Result<()> {
+    fn render(&self, file: &SourceFileObject, siblings: Siblings) -> io::Result<Rendered> {
         println!("rendered note at: {}", file.render_path.display());
-        let opts = self.render_opts();
-        let html = file.read_content().and_then(|md| opts.render(&md))?;
-        File::create(&file.render_path).and_then(|mut fh| fh.write_all(html.as_bytes()))
+        let dir = file.path.parent().unwrap_or(&self.src_root);
+        let breadcrumbs = nav::breadcrumbs(
+            &self.src_root,
+            &self.dst_root,
+            dir,
+            &self.links,
+            Some(&file.render_path),
+        );
+        let opts = self
+            .render_opts()
+            .with_breadcrumbs(breadcrumbs)
+            .with_siblings(siblings)
+            .with_links(self.links.clone())
+            .with_render_path(file.render_path.clone())
+            .with_tags_dir(self.dst_root.join(TAGS_DIR_NAME))
+            .with_source_dir(dir.to_path_buf())
+            .with_format(file.format());
+        let rendered = file.read_content().and_then(|md| opts.render(&md))?;
+        File::create(&file.render_path)
+            .and_then(|mut fh| fh.write_all(rendered.html.as_bytes()))?;
+        Ok(rendered)
+    }
+
+    /// Render the pages of the changed tags and the tags they're nested in, along with the tags
+    /// index. Pages of tags that no longer have any notes are removed.
+    ///
+    /// Returns the number of pages rendered.
+    fn render_tags(&self, store: &TagStore, changed: &BTreeSet<String>) -> io::Result<usize> {
+        let tags_dir = self.dst_root.join(TAGS_DIR_NAME);
+        let pages_dir = tags_dir.join(TAG_PAGES_DIR_NAME);
+        let index_path = tags_dir.join(INDEX_FILE_NAME);
+        if changed.is_empty() && index_path.exists() {
+            return Ok(0);
+        }
+        fs::create_dir_all(&tags_dir)?;
+
+        let counts = store.counts();
+        let pages: BTreeSet<&str> = changed
+            .iter()
+            .flat_map(|tag| tags::with_ancestors(tag))
+            .collect();
+        let mut rendered = 0;
+        // Nested tags first, so their directories are empty by the time their parents are removed.
+        for tag in pages.into_iter().rev() {
+            let page_path = tags::page_path(&tags_dir, tag);
+            if !counts.contains_key(tag) {
+                if page_path.exists() {
+                    fs::remove_file(&page_path)?;
+                }
+                // The directory of a nested tag is removed once it's empty.
+                if let Some(parent) = page_path.parent().filter(|&dir| dir != pages_dir) {
+                    let _ = fs::remove_dir(parent);
+                }
+                continue;
+            }
+
+            let nested = format!("{}/", tag);
+            let nested_tags: Vec<(String, usize)> = counts
+                .iter()
+                .filter(|(name, _)| {
+                    name.strip_prefix(&nested)
+                        .is_some_and(|rest| !rest.contains('/'))
+                })
+                .map(|(name, count)| (name.clone(), *count))
+                .collect();
+            let notes = store
+                .notes_with(tag)
+                .into_iter()
+                .map(|(path, note)| TaggedNote {
+                    title: note.title.clone(),
+                    render_path: note.render_path.clone(),
+                    path: path
+                        .strip_prefix(&self.src_root)
+                        .unwrap_or(path)
+                        .display()
+                        .to_string(),
+                })
+                .collect();
+            let page = TagPage::new(self, Some(tag), &nested_tags, notes);
+            self.write_page(
+                page.render_path(),
+                &self.templates.render(&page.template())?,
+            )?;
+            rendered += 1;
+        }
+
+        let all_tags: Vec<(String, usize)> = counts.into_iter().collect();
+        let index = TagPage::new(self, None, &all_tags, vec![]);
+        self.write_page(
+            index.render_path(),
+            &self.templates.render(&index.template())?,
+        )?;
+        Ok(rendered + 1)
+    }
+
+    /// Render the daily notes next to days that were added or removed, which weren't rendered
+    /// with the rest of the changed notes, along with the calendars of the changed years and
+    /// their months.
+    fn render_journal(
+        &self,
+        journal: &Journal,
+        rendered_days: &HashSet<Date>,
+        mut changed_years: BTreeSet<i32>,
+        summary: &mut SyncSummary,
+    ) -> io::Result<()> {
+        let mut store = JournalStore::read(&self.dst_root);
+        let days = journal.dates();
+        for (&date, file) in &journal.days {
+            if rendered_days.contains(&date)
+                || journal::adjacent(&store.days, date) == journal::adjacent(&days, date)
+            {
+                continue;
+            }
+            let rendered = self.render(file, journal.siblings(date, &self.links))?;
+            summary.add_warnings(&file.path, rendered.warnings);
+            summary.notes_rendered += 1;
+        }
+
+        changed_years.extend(store.changed_years(&days));
+        for (&year, dir) in &journal.years {
+            let is_rendered = dir.render_path.join(INDEX_FILE_NAME).exists();
+            if !changed_years.contains(&year) && is_rendered {
+                continue;
+            }
+            let months = journal
+                .months
+                .range((year, 1)..=(year, 12))
+                .map(|(&(_, month), dir)| (dir, Some(month)));
+            for (dir, month) in std::iter::once((dir, None)).chain(months) {
+                println!("building calendar for {:?}", dir.path);
+                let page = CalendarPage::new(self, journal, dir, year, month);
+                self.write_page(
+                    page.render_path(),
+                    &self.templates.render(&page.template())?,
+                )?;
+                summary.indexes_rendered += 1;
+            }
+        }
+
+        store.days = days;
+        store.write(&self.dst_root)
+    }
+
+    /// Update the search index with the changed notes, and render the search page along with its
+    /// index and script if the index changed.
+    ///
+    /// Returns whether the search page was rendered.
+    fn render_search(&self, pages: &[(&SourceFileObject, PathBuf)]) -> io::Result<bool> {
+        let search_page = match self.search_page {
+            Some(ref search_page) => search_page,
+            None => return Ok(false),
+        };
+        let mut index = SearchIndex::read(&self.dst_root);
+        if !index.update(pages, &self.dst_root)? && search_page.exists() {
+            return Ok(false);
+        }
+        index.write(&self.dst_root)?;
+
+        let script_path = self.dst_root.join(SEARCH_SCRIPT_FILE_NAME);
+        if fs::read_to_string(&script_path).ok().as_deref() != Some(SEARCH_SCRIPT) {
+            fs::write(&script_path, SEARCH_SCRIPT)?;
+        }
+        let page = SearchPage::new(self);
+        self.write_page(
+            page.render_path(),
+            &self.templates.render(&page.template())?,
+        )?;
+        Ok(true)
+    }
+
+    fn write_page(&self, path: &Path, html: &str) -> io::Result<()> {
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        File::create(path).and_then(|mut fh| fh.write_all(html.as_bytes()))
+    }
+
+    /// Render the intro note of a directory, to go at the top of its index page.
+    fn render_intro(&self, dir: &DirObject, file: &SourceFileObject) -> io::Result<Rendered> {
+        let opts = self
+            .render_opts()
+            .with_links(self.links.clone())
+            .with_render_path(dir.render_path.join(INDEX_FILE_NAME))
+            .with_source_dir(dir.path.clone())
+            .with_format(file.format());
+        file.read_content().and_then(|md| opts.render(&md))
+    }
+
+    /// Write the stylesheet for classed code blocks into the render root.
+    ///
+    /// This is cheap enough to do on every sync, and keeps the stylesheet up to date when the
+    /// theme changes without any notes changing.
+    fn write_syntax_stylesheet(&self) -> io::Result<()> {
+        if self.syntax_highlighter.style() != HighlightStyle::Classed {
+            return Ok(());
+        }
+        let path = self.dst_root.join(SYNTAX_STYLESHEET_FILE_NAME);
+        let css = self.syntax_highlighter.stylesheet();
+        if fs::read_to_string(&path).ok().as_ref() == Some(&css) {
+            return Ok(());
+        }
+        File::create(path).and_then(|mut fh| fh.write_all(css.as_bytes()))
     }
 
     #[inline]
-    fn render_opts(&self) -> RenderOpts {
-        RenderOpts::new(
+    pub fn render_opts(&self) -> RenderOpts<'_> {
+        let opts = RenderOpts::new(
             &self.stylesheet,
+            &self.syntax_stylesheet,
             &self.syntax_highlighter,
+            &self.diagrams,
             &self.mathjax_policy,
-        )
+            &self.html_policy,
+            &self.templates,
+        );
+        match self.search_page {
+            Some(ref search_page) => opts.with_search_page(search_page.clone()),
+            None => opts,
+        }
+    }
+}
+
+/// Notes in the directory, other than its intro, ordered by file name.
+fn sibling_notes(dir: &DirObject) -> Vec<&SourceFileObject> {
+    let intro = index::intro_file(dir).map(|file| &file.path);
+    let mut notes: Vec<&SourceFileObject> = dir
+        .children
+        .iter()
+        .filter_map(|child| match child {
+            Object::SourceFile(file) if Some(&file.path) != intro => Some(file),
+            _ => None,
+        })
+        .collect();
+    notes.sort_by(|a, b| a.path.file_name().cmp(&b.path.file_name()));
+    notes
+}
+
+/// Paths of the notes among `paths` in the order of `sibling_notes`, for the notes of a directory
+/// when it was last rendered.
+fn sibling_paths<'a>(paths: impl Iterator<Item = &'a Path>) -> Vec<&'a Path> {
+    let mut notes: Vec<&Path> = paths
+        .filter(|path| SourceFormat::from_path(path).is_some())
+        .collect();
+    let intro = index::INTRO_FILE_NAMES.iter().find(|name| {
+        notes
+            .iter()
+            .any(|path| path.file_name() == Some(name.as_ref()))
+    });
+    if let Some(intro) = intro {
+        notes.retain(|path| path.file_name() != Some(intro.as_ref()));
+    }
+    notes.sort_by(|a, b| a.file_name().cmp(&b.file_name()));
+    notes
+}
+
+/// Notes whose previous or next note isn't the one it was in `previous`, including notes that
+/// weren't there before.
+fn changed_siblings<'a>(notes: &[&'a Path], previous: &[&Path]) -> HashSet<&'a Path> {
+    fn neighbours<'a>(
+        paths: &[&'a Path],
+        path: &Path,
+    ) -> Option<(Option<&'a Path>, Option<&'a Path>)> {
+        let i = paths.iter().position(|other| *other == path)?;
+        Some((
+            i.checked_sub(1).map(|i| paths[i]),
+            paths.get(i + 1).copied(),
+        ))
+    }
+    notes
+        .iter()
+        .copied()
+        .filter(|note| neighbours(notes, note) != neighbours(previous, note))
+        .collect()
+}
+
+/// Every note in the directory and its subdirectories, along with the page it's rendered to. The
+/// intro of a directory is rendered to its index page.
+fn source_pages(dir: &DirObject) -> Vec<(&SourceFileObject, PathBuf)> {
+    let intro = index::intro_file(dir).map(|file| &file.path);
+    let mut pages = vec![];
+    for child in &dir.children {
+        match child {
+            Object::SourceFile(file) if Some(&file.path) == intro => {
+                pages.push((file, dir.render_path.join(INDEX_FILE_NAME)));
+            }
+            Object::SourceFile(file) => pages.push((file, file.render_path.clone())),
+            Object::Dir(child_dir) => pages.extend(source_pages(child_dir)),
+            _ => {}
+        }
+    }
+    pages
+}
+
+/// Copy an asset such as the mermaid script into the render root, so rendered notes don't depend
+/// on a CDN or on the location of the config directory.
+fn copy_asset(source: &Path, dest: &Path) -> io::Result<()> {
+    let is_current = fs::metadata(dest)
+        .and_then(|dest_meta| {
+            let source_meta = fs::metadata(source)?;
+            Ok(dest_meta.len() == source_meta.len()
+                && dest_meta.modified()? >= source_meta.modified()?)
+        })
+        .unwrap_or(false);
+    if !is_current {
+        fs::copy(source, dest)?;
+    }
+    Ok(())
+}
+
+/// Link to a note from the other notes in its directory.
+fn note_link(note: &SourceFileObject, links: &Links) -> PageLink {
+    PageLink {
+        name: note
+            .path
+            .file_stem()
+            .unwrap_or_default()
+            .to_string_lossy()
+            .to_string(),
+        href: links.href(Some(&note.render_path), &note.render_path),
     }
 }
 
@@ -79,20 +672,229 @@ impl TryFrom<Config> for SyncOpts {
     type Error = io::Error;
 
     fn try_from(config: Config) -> Result<Self, Self::Error> {
+        let links = Links::new(
+            config.render.link_style,
+            Some(config.sync.render_dir.clone()),
+            config.render.base_url.clone(),
+        );
+
         let stylesheet_path = config.render.stylesheet_path.as_ref();
-        let stylesheet = stylesheet_path
+        let mut stylesheet = stylesheet_path
             .map(|path| Stylesheet::new(path, config.render.should_inline_stylesheet))
             .transpose()?;
+        let mut stylesheet_source = None;
+        if let Some(Stylesheet::Link(path)) = &mut stylesheet {
+            if links.is_portable() {
+                let dest = config.sync.render_dir.join(STYLESHEET_FILE_NAME);
+                stylesheet_source = Some(std::mem::replace(path, dest));
+            }
+        }
+
+        let syntax_highlighter = SyntaxHighlighter::try_from(&config.render)?;
+        let syntax_stylesheet = match syntax_highlighter.style() {
+            HighlightStyle::Inline => None,
+            HighlightStyle::Classed => Some(Stylesheet::Link(
+                config.sync.render_dir.join(SYNTAX_STYLESHEET_FILE_NAME),
+            )),
+        };
+
+        let templates = Templates::load(config.render.template_dir.as_deref())?;
 
-        let syntax_highlighter = SyntaxHighlighter::with_theme(&config.render.code_block_theme)?;
+        let mut diagrams = Diagrams::from(&config.render);
+        let mermaid_script_source = diagrams.mermaid_script.take();
+        if mermaid_script_source.is_some() {
+            diagrams.mermaid_script = Some(config.sync.render_dir.join(MERMAID_SCRIPT_FILE_NAME));
+        }
+
+        let search_page = if config.sync.search {
+            Some(config.sync.render_dir.join(SEARCH_PAGE_FILE_NAME))
+        } else {
+            None
+        };
+
+        let thumbnails = Thumbnails {
+            command: config.sync.thumbnail_command,
+            size: config.sync.thumbnail_size,
+            dir: config.sync.render_dir.join(THUMBNAILS_DIR_NAME),
+        };
 
         Ok(Self {
             src_root: config.sync.notes_dir,
             dst_root: config.sync.render_dir,
             ignore: config.sync.ignore,
+            index_sort: config.sync.index_sort,
             mathjax_policy: config.render.mathjax_policy,
+            html_policy: config.render.html_policy,
+            links,
+            templates,
             stylesheet,
+            stylesheet_source,
+            syntax_stylesheet,
             syntax_highlighter,
+            diagrams,
+            mermaid_script_source,
+            search_page,
+            file_renderers: config.sync.file_renderers,
+            thumbnails,
+            journal_root: config.sync.journal_dir,
         })
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        render::links::{self, LinkStyle},
+        test_util::temp_dir,
+    };
+    use regex::Regex;
+
+    /// Config that syncs `dir/notes` into `dir/rendered`, without any of the user's assets.
+    fn sync_config(dir: &Path, link_style: LinkStyle) -> Config {
+        let mut config = Config::default();
+        config.sync.notes_dir = dir.join("notes");
+        config.sync.render_dir = dir.join("rendered");
+        config.sync.thumbnail_command = None;
+        config.sync.journal_dir = None;
+        config.render.stylesheet_path = None;
+        config.render.syntax_dirs = vec![];
+        config.render.theme_dirs = vec![];
+        config.render.cache_dir = None;
+        config.render.mermaid_script = None;
+        config.render.template_dir = None;
+        config.render.link_style = link_style;
+        config
+    }
+
+    /// Paths of the local links and sources in the HTML files in `dir` and its subdirectories,
+    /// resolved against the page they're in.
+    fn local_links(dir: &Path, links: &mut Vec<(PathBuf, PathBuf)>) {
+        let re = Regex::new(r##"(?:href|src)="([^"#?]*)[^"]*""##).unwrap();
+        for path in fs::read_dir(dir)
+            .unwrap()
+            .map(|entry| entry.unwrap().path())
+        {
+            if path.is_dir() {
+                local_links(&path, links);
+            } else if path.extension().unwrap_or_default() == "html" {
+                let html = fs::read_to_string(&path).unwrap();
+                for caps in re.captures_iter(&html) {
+                    let href = &caps[1];
+                    if href.is_empty() || href.contains(':') {
+                        continue;
+                    }
+                    let target = path.parent().unwrap().join(links::decode_path(href));
+                    links.push((path.clone(), target));
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn notes_with_changed_siblings() {
+        let previous = vec![
+            Path::new("/notes/a.md"),
+            Path::new("/notes/_index.md"),
+            Path::new("/notes/c.md"),
+            Path::new("/notes/d.md"),
+            Path::new("/notes/e.png"),
+        ];
+        let previous = sibling_paths(previous.into_iter());
+        assert_eq!(
+            previous,
+            vec![
+                Path::new("/notes/a.md"),
+                Path::new("/notes/c.md"),
+                Path::new("/notes/d.md")
+            ]
+        );
+
+        // Adding b.md only changes the notes next to it.
+        let notes = vec![
+            Path::new("/notes/a.md"),
+            Path::new("/notes/b.md"),
+            Path::new("/notes/c.md"),
+            Path::new("/notes/d.md"),
+        ];
+        assert_eq!(
+            changed_siblings(&notes, &previous),
+            notes[..3].iter().copied().collect()
+        );
+        assert!(changed_siblings(&previous, &previous).is_empty());
+    }
+
+    #[test]
+    fn relocatable_render_output() {
+        let dir = temp_dir("sync-relocatable");
+        let notes_dir = dir.join("notes");
+        fs::create_dir_all(notes_dir.join("a")).unwrap();
+        fs::write(
+            notes_dir.join("a/note.md"),
+            "# Note\n\n![Picture](my%20pic.png) [data](data.bin) [other](../other.md)\n#tag\n",
+        )
+        .unwrap();
+        fs::write(notes_dir.join("a/my pic.png"), b"png").unwrap();
+        fs::write(notes_dir.join("a/data.bin"), b"data").unwrap();
+        fs::write(notes_dir.join("other.md"), "# Other\n").unwrap();
+
+        let config = sync_config(&dir, LinkStyle::Relative);
+        SyncOpts::try_from(config).unwrap().sync().unwrap();
+
+        let moved = dir.join("moved");
+        fs::rename(dir.join("rendered"), &moved).unwrap();
+        fs::remove_dir_all(dir.join("notes")).unwrap();
+
+        let mut links = vec![];
+        local_links(&moved, &mut links);
+        assert!(links
+            .iter()
+            .any(|(_, target)| target.ends_with("a/my pic.png")));
+        for (page, target) in links {
+            assert!(
+                target.exists(),
+                "{} links to missing {}",
+                page.display(),
+                target.display()
+            );
+        }
+    }
+
+    #[test]
+    fn absolute_links_to_source_files() {
+        let dir = temp_dir("sync-absolute-files");
+        let notes_dir = dir.join("notes");
+        fs::create_dir_all(&notes_dir).unwrap();
+        fs::write(notes_dir.join("data.bin"), b"data").unwrap();
+
+        let config = sync_config(&dir, LinkStyle::Absolute);
+        SyncOpts::try_from(config).unwrap().sync().unwrap();
+
+        assert!(!dir.join("rendered/data.bin").exists());
+        let index = fs::read_to_string(dir.join("rendered/index.html")).unwrap();
+        let source = notes_dir.join("data.bin");
+        assert!(index.contains(&format!("href=\"{}\"", source.display())));
+    }
+
+    #[test]
+    fn copies_never_replace_pages() {
+        let dir = temp_dir("sync-copies-over-pages");
+        let notes_dir = dir.join("notes");
+        fs::create_dir_all(&notes_dir).unwrap();
+        fs::write(notes_dir.join("foo.md"), "# Foo note\n").unwrap();
+        fs::write(notes_dir.join("foo.html"), "raw foo").unwrap();
+        fs::write(notes_dir.join("index.html"), "raw index").unwrap();
+
+        // The second sync doesn't render the unchanged note again, so a copy made then would
+        // replace it for good.
+        for _ in 0..2 {
+            let config = sync_config(&dir, LinkStyle::Relative);
+            SyncOpts::try_from(config).unwrap().sync().unwrap();
+        }
+
+        let foo = fs::read_to_string(dir.join("rendered/foo.html")).unwrap();
+        assert!(foo.contains("Foo note"));
+        let index = fs::read_to_string(dir.join("rendered/index.html")).unwrap();
+        assert!(index.contains("Index: /"));
+    }
+}
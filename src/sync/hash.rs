@@ -54,3 +54,21 @@ impl From<SourceContentsHash> for u64 {
         hash.0
     }
 }
+
+/// Hash of the files a note embeds or includes, such as CSV files and code.
+///
+/// This is the content hash of each file, where a file that doesn't exist has no content.
+#[derive(Debug, Default, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub struct DependenciesHash(u64);
+
+impl From<u64> for DependenciesHash {
+    fn from(hash: u64) -> Self {
+        Self(hash)
+    }
+}
+
+impl From<DependenciesHash> for u64 {
+    fn from(hash: DependenciesHash) -> Self {
+        hash.0
+    }
+}
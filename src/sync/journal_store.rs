@@ -0,0 +1,66 @@
+use crate::journal::Date;
+use serde::{Deserialize, Serialize};
+use std::{
+    collections::BTreeSet,
+    fs, io,
+    path::{Path, PathBuf},
+};
+
+const JOURNAL_STORE_FILE_NAME: &str = ".carbon-journal-store.json";
+
+/// Days with daily notes, kept between syncs so the notes next to new and removed days get their
+/// links to them, and only the calendars of changed years are rendered.
+#[derive(Debug, Default, Serialize, Deserialize)]
+pub struct JournalStore {
+    pub days: BTreeSet<Date>,
+}
+
+impl JournalStore {
+    /// Read the store in the render root, or an empty store if there isn't a valid one.
+    pub fn read(render_root: &Path) -> Self {
+        fs::read_to_string(JournalStore::store_path(render_root))
+            .ok()
+            .and_then(|json| serde_json::from_str(&json).ok())
+            .unwrap_or_default()
+    }
+
+    pub fn write(&self, render_root: &Path) -> io::Result<()> {
+        let json = serde_json::to_string(&self)
+            .map_err(|e| io::Error::other(format!("failed to serialize journal store: {}", e)))?;
+        fs::write(JournalStore::store_path(render_root), json)
+    }
+
+    pub fn store_path(render_root: &Path) -> PathBuf {
+        render_root.join(JOURNAL_STORE_FILE_NAME)
+    }
+
+    /// Years whose days are different in `days`, including years that are only in one of them.
+    pub fn changed_years(&self, days: &BTreeSet<Date>) -> BTreeSet<i32> {
+        self.days
+            .symmetric_difference(days)
+            .map(|date| date.year)
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn changed_years() {
+        let date = |s: &str| s.parse::<Date>().unwrap();
+        let store = JournalStore {
+            days: vec![date("2025-12-31"), date("2026-01-01")]
+                .into_iter()
+                .collect(),
+        };
+        let days = vec![date("2026-01-01"), date("2027-03-04")]
+            .into_iter()
+            .collect();
+        assert_eq!(
+            store.changed_years(&days),
+            vec![2025, 2027].into_iter().collect()
+        );
+    }
+}
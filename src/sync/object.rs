@@ -1,7 +1,16 @@
-use crate::sync::hash::{DirChildrenHash, MerkleHash, SourceContentsHash};
+use crate::{
+    render::{
+        file::{self, FileRenderer},
+        source::SourceFormat,
+    },
+    sync::{
+        hash::{DirChildrenHash, MerkleHash, SourceContentsHash},
+        store::Dependencies,
+    },
+};
 use std::{
-    collections::hash_map::DefaultHasher,
-    fs::File,
+    collections::{hash_map::DefaultHasher, HashMap},
+    fs::{self, File},
     hash::{Hash, Hasher},
     io::{self, Read},
     path::{Path, PathBuf},
@@ -9,8 +18,10 @@ use std::{
 
 #[derive(Debug, Clone, Hash)]
 pub enum Object {
-    /// File that is renderable. For now this is only markdown files.
+    /// File that is renderable, which is a note in one of the source formats.
     SourceFile(SourceFileObject),
+    /// File that isn't a note but is rendered as a page of its own, such as a text or CSV file.
+    RenderedFile(RenderedFileObject),
     /// File that is non-renderable.
     File(FileObject),
     Dir(DirObject),
@@ -18,11 +29,24 @@ pub enum Object {
 }
 
 impl Object {
-    pub fn new(path: PathBuf, source_root: &Path, render_root: &Path) -> io::Result<Self> {
+    /// Object at `path`, where files that aren't notes are rendered as pages if `renderers` has a
+    /// renderer for their extension.
+    pub fn new(
+        path: PathBuf,
+        source_root: &Path,
+        render_root: &Path,
+        renderers: &HashMap<String, FileRenderer>,
+    ) -> io::Result<Self> {
         let ft = path.metadata()?.file_type();
         if ft.is_file() {
-            if path.extension().unwrap_or_default() != "md" {
-                return Ok(Self::File(FileObject::new(path)));
+            if SourceFormat::from_path(&path).is_none() {
+                return match file::renderer_for(&path, renderers) {
+                    Some(renderer) => {
+                        RenderedFileObject::with_source(path, source_root, render_root, renderer)
+                            .map(Self::RenderedFile)
+                    }
+                    None => Ok(Self::File(FileObject::new(path))),
+                };
             }
             return SourceFileObject::with_source(path, source_root, render_root)
                 .map(Self::SourceFile);
@@ -37,16 +61,17 @@ impl Object {
         if ft.is_symlink() {
             return Ok(Self::Symlink(LinkObject::new(path)));
         }
-        Err(io::Error::new(
-            io::ErrorKind::Other,
-            format!("unknown filetype at path {}", path.display()),
-        ))
+        Err(io::Error::other(format!(
+            "unknown filetype at path {}",
+            path.display()
+        )))
     }
 
     pub fn path(&self) -> &Path {
         match self {
             Object::File(x) => x.path.as_ref(),
             Object::SourceFile(x) => x.path.as_ref(),
+            Object::RenderedFile(x) => x.path.as_ref(),
             Object::Dir(x) => x.path.as_ref(),
             Object::Symlink(x) => x.path.as_ref(),
         }
@@ -59,6 +84,12 @@ impl From<SourceFileObject> for Object {
     }
 }
 
+impl From<RenderedFileObject> for Object {
+    fn from(file: RenderedFileObject) -> Self {
+        Self::RenderedFile(file)
+    }
+}
+
 impl From<FileObject> for Object {
     fn from(file: FileObject) -> Self {
         Self::File(file)
@@ -82,6 +113,8 @@ pub struct SourceFileObject {
     pub path: PathBuf,
     pub render_path: PathBuf,
     pub contents_hash: SourceContentsHash,
+    /// Files the note embedded when it was last rendered, hashed with their current contents.
+    pub dependencies: Option<Dependencies>,
 }
 
 impl SourceFileObject {
@@ -94,9 +127,15 @@ impl SourceFileObject {
             path,
             render_path,
             contents_hash,
+            dependencies: None,
         })
     }
 
+    /// Format the note is written in, by its extension.
+    pub fn format(&self) -> SourceFormat {
+        SourceFormat::from_path(&self.path).unwrap_or_default()
+    }
+
     pub fn read_content(&self) -> io::Result<String> {
         SourceFileObject::read_to_string(&self.path)
     }
@@ -131,6 +170,45 @@ impl Hash for SourceFileObject {
     }
 }
 
+#[derive(Debug, Clone)]
+pub struct RenderedFileObject {
+    pub path: PathBuf,
+    /// Page the file is rendered to, which keeps the extension of the file so `data.csv` and
+    /// `data.md` don't collide.
+    pub render_path: PathBuf,
+    pub renderer: FileRenderer,
+    pub contents_hash: SourceContentsHash,
+}
+
+impl RenderedFileObject {
+    pub fn with_source(
+        path: PathBuf,
+        source_root: &Path,
+        render_root: &Path,
+        renderer: FileRenderer,
+    ) -> io::Result<Self> {
+        let mut render_path = render_path(&path, source_root, render_root).into_os_string();
+        render_path.push(".html");
+        let mut hasher = DefaultHasher::new();
+        fs::read(&path)?.hash(&mut hasher);
+        // The renderer is part of the hash so the page is rendered again when it changes.
+        renderer.hash(&mut hasher);
+
+        Ok(Self {
+            path,
+            render_path: render_path.into(),
+            renderer,
+            contents_hash: SourceContentsHash::from(hasher.finish()),
+        })
+    }
+}
+
+impl Hash for RenderedFileObject {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.path.hash(state);
+    }
+}
+
 #[derive(Debug, Clone)]
 pub struct FileObject {
     pub path: PathBuf,
@@ -178,6 +256,19 @@ impl DirObject {
         Self::new(path, render_path)
     }
 
+    /// Source files in the directory and its subdirectories.
+    pub fn source_files(&self) -> Vec<&SourceFileObject> {
+        let mut files = vec![];
+        for child in &self.children {
+            match child {
+                Object::SourceFile(file) => files.push(file),
+                Object::Dir(dir) => files.extend(dir.source_files()),
+                _ => {}
+            }
+        }
+        files
+    }
+
     pub fn extend<I: IntoIterator<Item = Object>>(&mut self, children: I) {
         self.children.extend(children);
 
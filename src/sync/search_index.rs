@@ -0,0 +1,119 @@
+use crate::{
+    render::search::{SearchEntry, SEARCH_INDEX_FILE_NAME},
+    sync::{hash::SourceContentsHash, object::SourceFileObject},
+};
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+use std::{
+    collections::{BTreeMap, HashSet},
+    fs, io,
+    path::{Path, PathBuf},
+};
+
+const SEARCH_STORE_FILE_NAME: &str = ".carbon-search-store.json";
+
+#[derive(Debug, Serialize, Deserialize)]
+struct IndexedNote {
+    hash: SourceContentsHash,
+    entry: SearchEntry,
+}
+
+/// Search index of every note, kept between syncs so only changed notes are read again.
+#[derive(Debug, Default, Serialize, Deserialize)]
+pub struct SearchIndex {
+    notes: BTreeMap<PathBuf, IndexedNote>,
+}
+
+impl SearchIndex {
+    /// Read the store in the render root, or an empty index if there isn't a valid one.
+    pub fn read(render_root: &Path) -> Self {
+        fs::read_to_string(render_root.join(SEARCH_STORE_FILE_NAME))
+            .ok()
+            .and_then(|json| serde_json::from_str(&json).ok())
+            .unwrap_or_default()
+    }
+
+    /// Write the store, and the search index script for the search page.
+    pub fn write(&self, render_root: &Path) -> io::Result<()> {
+        let store = serde_json::to_string(&self).map_err(serialize_error)?;
+        fs::write(render_root.join(SEARCH_STORE_FILE_NAME), store)?;
+        fs::write(render_root.join(SEARCH_INDEX_FILE_NAME), self.to_script()?)
+    }
+
+    /// Index the notes that have changed since the index was written, and drop the notes that
+    /// no longer exist. `pages` are the notes along with the pages they're rendered to.
+    ///
+    /// Returns whether the index changed.
+    pub fn update(
+        &mut self,
+        pages: &[(&SourceFileObject, PathBuf)],
+        render_root: &Path,
+    ) -> io::Result<bool> {
+        let mut changed = false;
+        for (note, page) in pages {
+            let path = page
+                .strip_prefix(render_root)
+                .unwrap_or(page)
+                .components()
+                .map(|component| component.as_os_str().to_string_lossy())
+                .collect::<Vec<_>>()
+                .join("/");
+            let is_current = self.notes.get(&note.path).is_some_and(|indexed| {
+                indexed.hash == note.contents_hash && indexed.entry.path == path
+            });
+            if is_current {
+                continue;
+            }
+
+            let name = note.path.file_stem().unwrap_or_default().to_string_lossy();
+            let indexed = IndexedNote {
+                hash: note.contents_hash.clone(),
+                entry: SearchEntry::parse(&note.read_content()?, note.format(), path, &name),
+            };
+            self.notes.insert(note.path.clone(), indexed);
+            changed = true;
+        }
+
+        let paths: HashSet<&Path> = pages.iter().map(|(note, _)| note.path.as_path()).collect();
+        let count = self.notes.len();
+        self.notes.retain(|path, _| paths.contains(path.as_path()));
+        Ok(changed || self.notes.len() != count)
+    }
+
+    fn to_script(&self) -> io::Result<String> {
+        let entries: Vec<&SearchEntry> = self.notes.values().map(|note| &note.entry).collect();
+        let json = serde_json::to_string(&json!({ "notes": entries })).map_err(serialize_error)?;
+        Ok(format!("window.carbonSearchIndex = {};\n", json))
+    }
+}
+
+fn serialize_error(e: serde_json::Error) -> io::Error {
+    io::Error::other(format!("failed to serialize search index: {}", e))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_util::temp_dir;
+
+    #[test]
+    fn update_changed_notes() {
+        let dir = temp_dir("search-update");
+        let render_root = dir.join("rendered");
+        fs::write(dir.join("a.md"), "# Alpha\n\nWords").unwrap();
+        let note = SourceFileObject::with_source(dir.join("a.md"), &dir, &render_root).unwrap();
+        let pages = [(&note, note.render_path.clone())];
+
+        let mut index = SearchIndex::default();
+        assert!(index.update(&pages, &render_root).unwrap());
+        assert!(!index.update(&pages, &render_root).unwrap());
+        assert_eq!(index.notes[&note.path].entry.path, "a.html");
+
+        let script = index.to_script().unwrap();
+        assert!(script.starts_with("window.carbonSearchIndex = {\"notes\":[{"));
+        assert!(script.contains("\"title\":\"Alpha\""));
+
+        assert!(index.update(&[], &render_root).unwrap());
+        assert!(index.notes.is_empty());
+    }
+}
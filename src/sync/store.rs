@@ -1,11 +1,12 @@
 use crate::sync::{
-    hash::{DirChildrenHash, MerkleHash, SourceContentsHash},
+    hash::{DependenciesHash, DirChildrenHash, MerkleHash, SourceContentsHash},
     object::{DirObject, Object},
 };
 use serde::{Deserialize, Serialize};
 use std::{
-    collections::HashMap,
-    fs::File,
+    collections::{hash_map::DefaultHasher, HashMap},
+    fs::{self, File},
+    hash::{Hash, Hasher},
     io::{self, Read},
     path::{Path, PathBuf},
 };
@@ -17,24 +18,43 @@ pub struct HashStore {
     pub merkle: MerkleHash,
     pub dir: DirChildrenHash,
     pub source: HashMap<PathBuf, SourceContentsHash>,
+    /// Files embedded in the notes of the directory, for notes that embed any.
+    #[serde(default)]
+    pub dependencies: HashMap<PathBuf, Dependencies>,
+}
+
+/// Files that a note embeds or includes, which aren't notes so aren't tracked on their own.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Dependencies {
+    pub paths: Vec<PathBuf>,
+    pub hash: DependenciesHash,
+}
+
+impl Dependencies {
+    /// Dependencies on the files at `paths`, hashed with their current contents.
+    pub fn new(paths: Vec<PathBuf>) -> Self {
+        let mut hasher = DefaultHasher::new();
+        for path in &paths {
+            path.hash(&mut hasher);
+            fs::read(path).ok().hash(&mut hasher);
+        }
+        Self {
+            paths,
+            hash: DependenciesHash::from(hasher.finish()),
+        }
+    }
 }
 
 impl HashStore {
     pub fn to_json(&self) -> io::Result<String> {
         serde_json::to_string(&self).map_err(|e| {
-            io::Error::new(
-                io::ErrorKind::Other,
-                format!("failed to serialize directory hash store: {}", e),
-            )
+            io::Error::other(format!("failed to serialize directory hash store: {}", e))
         })
     }
 
     fn from_json(content: String) -> io::Result<Self> {
         serde_json::from_str(&content).map_err(|e| {
-            io::Error::new(
-                io::ErrorKind::Other,
-                format!("failed to deserialize json into hash store: {}", e),
-            )
+            io::Error::other(format!("failed to deserialize json into hash store: {}", e))
         })
     }
 
@@ -46,10 +66,20 @@ impl HashStore {
 impl From<&DirObject> for HashStore {
     fn from(dir: &DirObject) -> Self {
         let mut source_hash = HashMap::new();
+        let mut dependencies = HashMap::new();
         for child in &dir.children {
-            if let Object::SourceFile(file) = child {
-                // DirStore::from shouldn't need to clone file.path.
-                source_hash.insert(file.path.clone(), file.contents_hash.clone());
+            // DirStore::from shouldn't need to clone file.path.
+            match child {
+                Object::SourceFile(file) => {
+                    source_hash.insert(file.path.clone(), file.contents_hash.clone());
+                    if let Some(ref deps) = file.dependencies {
+                        dependencies.insert(file.path.clone(), deps.clone());
+                    }
+                }
+                Object::RenderedFile(file) => {
+                    source_hash.insert(file.path.clone(), file.contents_hash.clone());
+                }
+                _ => {}
             }
         }
 
@@ -57,6 +87,7 @@ impl From<&DirObject> for HashStore {
             merkle: dir.merkle_hash.clone(),
             dir: dir.children_hash.clone(),
             source: source_hash,
+            dependencies,
         }
     }
 }
@@ -99,6 +130,25 @@ impl HashStoreRw {
             .map(|source_hash| *source_hash == *hash)
             .unwrap_or(false)
     }
+
+    /// Notes and files that were in the directory when it was last rendered.
+    pub fn source_paths(&self) -> Vec<PathBuf> {
+        self.store
+            .as_ref()
+            .map(|store| store.source.keys().cloned().collect())
+            .unwrap_or_default()
+    }
+
+    /// Files the note embedded when it was last rendered.
+    pub fn dependencies(&self, path: &Path) -> Option<&Dependencies> {
+        self.store
+            .as_ref()
+            .and_then(|store| store.dependencies.get(path))
+    }
+
+    pub fn dependencies_eq(&self, path: &Path, dependencies: &Option<Dependencies>) -> bool {
+        self.dependencies(path) == dependencies.as_ref()
+    }
 }
 
 impl From<HashStore> for HashStoreRw {
@@ -0,0 +1,76 @@
+use crate::render::Warning;
+use std::{
+    fmt,
+    path::{Path, PathBuf},
+};
+
+/// Outcome of a sync.
+#[derive(Debug, Default)]
+pub struct SyncSummary {
+    pub notes_rendered: usize,
+    /// Pages of files that aren't notes, such as text and CSV files.
+    pub files_rendered: usize,
+    pub indexes_rendered: usize,
+    pub warnings: Vec<NoteWarning>,
+}
+
+impl SyncSummary {
+    pub fn add_warnings<I: IntoIterator<Item = Warning>>(&mut self, path: &Path, warnings: I) {
+        self.warnings
+            .extend(warnings.into_iter().map(|warning| NoteWarning {
+                path: path.to_path_buf(),
+                warning,
+            }));
+    }
+}
+
+impl fmt::Display for SyncSummary {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "rendered {} notes, {} files and {} indexes with {} warnings",
+            self.notes_rendered,
+            self.files_rendered,
+            self.indexes_rendered,
+            self.warnings.len()
+        )
+    }
+}
+
+/// Warning found while rendering the note at `path`.
+#[derive(Debug)]
+pub struct NoteWarning {
+    pub path: PathBuf,
+    pub warning: Warning,
+}
+
+impl fmt::Display for NoteWarning {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}:{}", self.path.display(), self.warning)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn display_warning_with_path_and_line() {
+        let mut summary = SyncSummary::default();
+        summary.add_warnings(
+            Path::new("/notes/a.md"),
+            vec![Warning {
+                line: 3,
+                message: String::from("unknown code block language `tsx`"),
+            }],
+        );
+        assert_eq!(
+            summary.warnings[0].to_string(),
+            "/notes/a.md:3: unknown code block language `tsx`"
+        );
+        assert_eq!(
+            summary.to_string(),
+            "rendered 0 notes, 0 files and 0 indexes with 1 warnings"
+        );
+    }
+}
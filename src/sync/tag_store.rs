@@ -0,0 +1,159 @@
+use crate::render::tags;
+use serde::{Deserialize, Serialize};
+use std::{
+    collections::{BTreeMap, BTreeSet},
+    fs, io,
+    path::{Path, PathBuf},
+};
+
+const TAG_STORE_FILE_NAME: &str = ".carbon-tag-store.json";
+
+/// Tagged note, as it's listed on tag pages.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct TaggedSource {
+    pub title: String,
+    pub render_path: PathBuf,
+    pub tags: Vec<String>,
+}
+
+/// Tags of every tagged note, kept between syncs so only the pages of tags on changed notes
+/// are rendered.
+#[derive(Debug, Default, Serialize, Deserialize)]
+pub struct TagStore {
+    notes: BTreeMap<PathBuf, TaggedSource>,
+}
+
+impl TagStore {
+    /// Read the store in the tags directory, or an empty store if there isn't a valid one.
+    pub fn read(tags_dir: &Path) -> Self {
+        fs::read_to_string(TagStore::store_path(tags_dir))
+            .ok()
+            .and_then(|json| serde_json::from_str(&json).ok())
+            .unwrap_or_default()
+    }
+
+    pub fn write(&self, tags_dir: &Path) -> io::Result<()> {
+        let json = serde_json::to_string(&self)
+            .map_err(|e| io::Error::other(format!("failed to serialize tag store: {}", e)))?;
+        fs::write(TagStore::store_path(tags_dir), json)
+    }
+
+    pub fn store_path(tags_dir: &Path) -> PathBuf {
+        tags_dir.join(TAG_STORE_FILE_NAME)
+    }
+
+    /// Record the note at `path`, returning the tags with pages that have changed.
+    pub fn update(&mut self, path: &Path, note: TaggedSource) -> Vec<String> {
+        let previous = if note.tags.is_empty() {
+            self.notes.remove(path)
+        } else {
+            self.notes.insert(path.to_path_buf(), note.clone())
+        };
+        match previous {
+            Some(previous) if previous == note => vec![],
+            Some(previous) => previous.tags.into_iter().chain(note.tags).collect(),
+            None => note.tags,
+        }
+    }
+
+    /// Forget the notes that no longer exist, returning the tags with pages that have changed.
+    pub fn retain<F: Fn(&Path) -> bool>(&mut self, exists: F) -> Vec<String> {
+        let mut changed = vec![];
+        self.notes.retain(|path, note| {
+            let keep = exists(path);
+            if !keep {
+                changed.extend(note.tags.iter().cloned());
+            }
+            keep
+        });
+        changed
+    }
+
+    /// Every tag, including tags that only have nested tags, with its number of notes.
+    pub fn counts(&self) -> BTreeMap<String, usize> {
+        let mut counts = BTreeMap::new();
+        for note in self.notes.values() {
+            let note_tags: BTreeSet<&str> = note
+                .tags
+                .iter()
+                .flat_map(|tag| tags::with_ancestors(tag))
+                .collect();
+            for tag in note_tags {
+                *counts.entry(tag.to_string()).or_insert(0) += 1;
+            }
+        }
+        counts
+    }
+
+    /// Notes with the tag or a tag nested in it, ordered by title.
+    pub fn notes_with(&self, tag: &str) -> Vec<(&Path, &TaggedSource)> {
+        let nested = format!("{}/", tag);
+        let mut notes: Vec<(&Path, &TaggedSource)> = self
+            .notes
+            .iter()
+            .filter(|(_, note)| {
+                note.tags
+                    .iter()
+                    .any(|note_tag| note_tag == tag || note_tag.starts_with(&nested))
+            })
+            .map(|(path, note)| (path.as_path(), note))
+            .collect();
+        notes.sort_by_key(|(_, note)| note.title.to_lowercase());
+        notes
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn note(title: &str, tags: &[&str]) -> TaggedSource {
+        TaggedSource {
+            title: title.to_string(),
+            render_path: PathBuf::from(format!("/rendered/{}.html", title)),
+            tags: tags.iter().map(|tag| tag.to_string()).collect(),
+        }
+    }
+
+    #[test]
+    fn update_returns_changed_tags() {
+        let mut store = TagStore::default();
+        let path = Path::new("/notes/a.md");
+        assert_eq!(store.update(path, note("a", &["rust"])), vec!["rust"]);
+        assert!(store.update(path, note("a", &["rust"])).is_empty());
+        assert_eq!(store.update(path, note("a", &["go"])), vec!["rust", "go"]);
+        assert_eq!(store.update(path, note("a", &[])), vec!["go"]);
+        assert!(store.counts().is_empty());
+    }
+
+    #[test]
+    fn retain_existing_notes() {
+        let mut store = TagStore::default();
+        store.update(Path::new("/notes/a.md"), note("a", &["rust"]));
+        store.update(Path::new("/notes/b.md"), note("b", &["go"]));
+        let changed = store.retain(|path| path == Path::new("/notes/a.md"));
+        assert_eq!(changed, vec!["go"]);
+        assert_eq!(store.counts().keys().collect::<Vec<_>>(), vec!["rust"]);
+    }
+
+    #[test]
+    fn hierarchical_tags() {
+        let mut store = TagStore::default();
+        store.update(Path::new("/notes/a.md"), note("a", &["project/carbon"]));
+        store.update(
+            Path::new("/notes/b.md"),
+            note("b", &["project", "project/web"]),
+        );
+
+        let counts = store.counts();
+        assert_eq!(counts["project"], 2);
+        assert_eq!(counts["project/carbon"], 1);
+        let titles: Vec<&str> = store
+            .notes_with("project")
+            .iter()
+            .map(|(_, note)| note.title.as_str())
+            .collect();
+        assert_eq!(titles, vec!["a", "b"]);
+        assert_eq!(store.notes_with("project/web").len(), 1);
+    }
+}
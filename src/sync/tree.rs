@@ -1,11 +1,14 @@
-use crate::sync::{
-    hash::MerkleHash,
-    object::{DirObject, Object, SourceFileObject},
-    store::{HashStore, HashStoreRw},
+use crate::{
+    render::file::FileRenderer,
+    sync::{
+        hash::MerkleHash,
+        object::{DirObject, Object, RenderedFileObject, SourceFileObject},
+        store::{Dependencies, HashStore, HashStoreRw},
+    },
 };
 use globset::GlobSet;
 use std::{
-    collections::{hash_map::DefaultHasher, VecDeque},
+    collections::{hash_map::DefaultHasher, HashMap, VecDeque},
     fs::{self, File},
     hash::{Hash, Hasher},
     io::{self, Write},
@@ -18,8 +21,13 @@ pub struct DirTree {
 }
 
 impl DirTree {
-    pub fn with_root(root: PathBuf, render_root: &Path, ignore: &GlobSet) -> io::Result<Self> {
-        let mut root_dir = match Object::new(root.clone(), &root, &render_root)? {
+    pub fn with_root(
+        root: PathBuf,
+        render_root: &Path,
+        ignore: &GlobSet,
+        renderers: &HashMap<String, FileRenderer>,
+    ) -> io::Result<Self> {
+        let mut root_dir = match Object::new(root.clone(), &root, render_root, renderers)? {
             Object::Dir(dir) => Ok(dir),
             other => Err(io::Error::new(
                 io::ErrorKind::InvalidInput,
@@ -35,11 +43,25 @@ impl DirTree {
 
         while !unseen_dirs.is_empty() {
             let dir = unseen_dirs.pop_front().unwrap();
-            dir.extend(dir_children(&dir.path, &root, &render_root, ignore));
-
+            dir.extend(dir_children(
+                &dir.path,
+                &root,
+                render_root,
+                ignore,
+                renderers,
+            ));
+
+            // Embedded files are hashed again so notes are rendered when they change.
+            let store = HashStoreRw::read_dir(&dir.render_path);
             for child in &mut dir.children {
-                if let Object::Dir(child_dir) = child {
-                    unseen_dirs.push_back(child_dir);
+                match child {
+                    Object::Dir(child_dir) => unseen_dirs.push_back(child_dir),
+                    Object::SourceFile(file) => {
+                        file.dependencies = store
+                            .dependencies(&file.path)
+                            .map(|deps| Dependencies::new(deps.paths.clone()));
+                    }
+                    _ => {}
                 }
             }
         }
@@ -55,7 +77,14 @@ impl DirTree {
         DirWalk { unseen_dirs }
     }
 
-    pub fn persist_hashes(&mut self) -> io::Result<()> {
+    /// Write the hashes of the walked directories, where `dependencies` are the files embedded by
+    /// each note that was rendered.
+    pub fn persist_hashes(
+        &mut self,
+        dependencies: &HashMap<PathBuf, Vec<PathBuf>>,
+    ) -> io::Result<()> {
+        update_dependencies(&mut self.root, dependencies);
+        DirTree::compute_merkle_hash(&mut self.root);
         for dir in self.walk() {
             let store = HashStore::from(dir.object);
             let json = store.to_json()?;
@@ -80,7 +109,13 @@ impl DirTree {
                     DirTree::compute_merkle_hash(child_dir);
                     child_dir.merkle_hash.hash(&mut hasher);
                 }
-                Object::SourceFile(ref child_file) => child_file.contents_hash.hash(&mut hasher),
+                Object::SourceFile(ref child_file) => {
+                    child_file.contents_hash.hash(&mut hasher);
+                    if let Some(ref deps) = child_file.dependencies {
+                        deps.hash.hash(&mut hasher);
+                    }
+                }
+                Object::RenderedFile(ref child_file) => child_file.contents_hash.hash(&mut hasher),
                 _ => {}
             };
         }
@@ -92,7 +127,11 @@ impl DirTree {
 pub struct Dir<'a> {
     pub object: &'a DirObject,
     pub to_render: Vec<&'a SourceFileObject>,
+    /// Files that aren't notes whose pages need rendering.
+    pub files_to_render: Vec<&'a RenderedFileObject>,
     pub should_render_index: bool,
+    /// Notes and files that were in the directory when it was last rendered.
+    pub previous_sources: Vec<PathBuf>,
 }
 
 pub struct DirWalk<'a> {
@@ -111,6 +150,7 @@ impl<'a> Iterator for DirWalk<'a> {
         let store = HashStoreRw::read_dir(&dir.render_path);
 
         let mut to_render = vec![];
+        let mut files_to_render = vec![];
         for child in &dir.children {
             match child {
                 Object::Dir(child_dir) => {
@@ -119,33 +159,62 @@ impl<'a> Iterator for DirWalk<'a> {
                     }
                 }
                 Object::SourceFile(child_file) => {
-                    if !store.source_hash_eq(&child_file.path, &child_file.contents_hash) {
+                    if !store.source_hash_eq(&child_file.path, &child_file.contents_hash)
+                        || !store.dependencies_eq(&child_file.path, &child_file.dependencies)
+                    {
                         to_render.push(child_file)
                     }
                 }
+                Object::RenderedFile(child_file)
+                    if !store.source_hash_eq(&child_file.path, &child_file.contents_hash) =>
+                {
+                    files_to_render.push(child_file)
+                }
                 _ => {}
             };
         }
         Some(Dir {
             object: dir,
             to_render,
+            files_to_render,
             should_render_index: !store.dir_hash_eq(&dir.children_hash),
+            previous_sources: store.source_paths(),
         })
     }
 }
 
+/// Replace the dependencies of the notes that were rendered with the files they embedded.
+fn update_dependencies(dir: &mut DirObject, dependencies: &HashMap<PathBuf, Vec<PathBuf>>) {
+    for child in &mut dir.children {
+        match child {
+            Object::Dir(child_dir) => update_dependencies(child_dir, dependencies),
+            Object::SourceFile(file) => {
+                if let Some(paths) = dependencies.get(&file.path) {
+                    file.dependencies = if paths.is_empty() {
+                        None
+                    } else {
+                        Some(Dependencies::new(paths.clone()))
+                    };
+                }
+            }
+            _ => {}
+        }
+    }
+}
+
 fn dir_children(
     path: &Path,
     source_root: &Path,
     render_root: &Path,
     ignore: &GlobSet,
+    renderers: &HashMap<String, FileRenderer>,
 ) -> Vec<Object> {
     fs::read_dir(path)
         .unwrap()
         .filter_map(Result::ok)
         .map(|entry| entry.path())
         .filter(|ref entry_path| !ignore.is_match(entry_path))
-        .filter_map(|entry_path| Object::new(entry_path, source_root, render_root).ok())
+        .filter_map(|entry_path| Object::new(entry_path, source_root, render_root, renderers).ok())
         .collect()
 }
 